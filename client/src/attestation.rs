@@ -0,0 +1,62 @@
+use anchor_lang::solana_program::{instruction::Instruction, pubkey::Pubkey};
+use sla::sla_constants;
+use std::str::FromStr;
+
+pub use sla::sla_attestation::{attestation_message, AttestedAction};
+
+// Layout of the Ed25519 program instruction data for a single signature: header + offsets,
+// then the public key, the signature and the message
+const HEADER_LEN: u16 = 2 + 14;
+const PUBKEY_LEN: u16 = 32;
+const SIGNATURE_LEN: u16 = 64;
+
+// New metadata URI of an agent, signed by the renderer (see `attestation_message`)
+#[derive(Clone, Debug)]
+pub struct UriAttestation {
+  pub uri: String,
+  pub nonce: u64,
+  pub expiry: i64,
+  pub signature: [u8; 64],
+}
+
+impl UriAttestation {
+  pub fn message(&self, action: AttestedAction, avatar_mint: &Pubkey) -> Vec<u8> {
+    attestation_message(action, avatar_mint, &self.uri, self.nonce, self.expiry)
+  }
+}
+
+// Ed25519 program instruction verifying the attestation, to be placed right before the
+// `*_attested` instruction it was signed for
+pub fn ed25519_instruction(
+  renderer: &Pubkey,
+  action: AttestedAction,
+  avatar_mint: &Pubkey,
+  attestation: &UriAttestation,
+) -> Instruction {
+  let message = attestation.message(action, avatar_mint);
+  let pubkey_offset = HEADER_LEN;
+  let signature_offset = pubkey_offset + PUBKEY_LEN;
+  let message_offset = signature_offset + SIGNATURE_LEN;
+
+  let mut data = vec![1, 0];
+  for offset in [
+    signature_offset,
+    u16::MAX,
+    pubkey_offset,
+    u16::MAX,
+    message_offset,
+    message.len() as u16,
+    u16::MAX,
+  ] {
+    data.extend_from_slice(&offset.to_le_bytes());
+  }
+  data.extend_from_slice(&renderer.to_bytes());
+  data.extend_from_slice(&attestation.signature);
+  data.extend_from_slice(&message);
+
+  Instruction {
+    program_id: Pubkey::from_str(sla_constants::ED25519_PROGRAM).unwrap(),
+    accounts: vec![],
+    data,
+  }
+}
@@ -0,0 +1,896 @@
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::{pubkey::Pubkey, system_program, sysvar};
+use anchor_lang::{InstructionData, ToAccountMetas};
+use sla::sla_accounts::{ConfigParams, GlobalConfig, PauseFlags, RankDiscounts, RankTierParams};
+use sla::sla_fungible_token::FungibleAsset;
+
+use crate::attestation::{ed25519_instruction, AttestedAction, UriAttestation};
+use crate::pda::*;
+
+
+// Some accounts are plain `AccountInfo`s on-chain but still have to sign (e.g. the `user`
+// paying $HAY in `mint_id_card`), so their metas are flagged as signers here
+fn build(accounts: impl ToAccountMetas, data: impl InstructionData, signers: &[Pubkey]) -> Instruction {
+  let mut metas = accounts.to_account_metas(None);
+  for meta in metas.iter_mut() {
+    if signers.contains(&meta.pubkey) {
+      meta.is_signer = true;
+    }
+  }
+
+  Instruction {
+    program_id: sla::ID,
+    accounts: metas,
+    data: data.data(),
+  }
+}
+
+// M-of-N approvals of the authority set are passed as signing remaining accounts
+fn with_approvers(mut instruction: Instruction, approvers: &[Pubkey]) -> Instruction {
+  instruction
+    .accounts
+    .extend(approvers.iter().map(|approver| AccountMeta::new_readonly(*approver, true)));
+  instruction
+}
+
+
+pub fn initialize_config(admin: &Pubkey, params: ConfigParams) -> Instruction {
+  build(
+    sla::accounts::InitializeConfig {
+      config: find_config_pda().0,
+      admin: *admin,
+      program: sla::ID,
+      program_data: find_program_data_pda().0,
+      system_program: system_program::ID,
+    },
+    sla::instruction::InitializeConfig { params },
+    &[],
+  )
+}
+
+pub fn update_config(admin: &Pubkey, params: ConfigParams) -> Instruction {
+  build(
+    sla::accounts::UpdateConfig {
+      config: find_config_pda().0,
+      admin: *admin,
+    },
+    sla::instruction::UpdateConfig { params },
+    &[],
+  )
+}
+
+pub fn merge(
+  config: &GlobalConfig,
+  user: &Pubkey,
+  avatar_mint: &Pubkey,
+  trait_mint: &Pubkey,
+  metadata_uri: String,
+) -> Instruction {
+  build(
+    sla::accounts::Merge {
+      avatar: find_avatar_pda(avatar_mint).0,
+      avatar_v2: find_avatar_v2_pda(avatar_mint).0,
+      avatar_mint: *avatar_mint,
+      trait_mint: *trait_mint,
+      avatar_token: get_ata(user, avatar_mint),
+      trait_token: get_ata(user, trait_mint),
+      avatar_metadata: find_metadata_pda(avatar_mint).0,
+      trait_metadata: find_metadata_pda(trait_mint).0,
+      payer: *user,
+      combine_authority: config.combine_authority,
+      metadata_authority: find_metadata_authority_pda().0,
+      config: find_config_pda().0,
+      token_program: anchor_spl::token::ID,
+      metadata_program: mpl_token_metadata::ID,
+      system_program: system_program::ID,
+    },
+    sla::instruction::MergeV2 { metadata_uri },
+    &[],
+  )
+}
+
+// Merge without the combine authority, for agents whose update authority was transferred
+pub fn merge_v3(user: &Pubkey, avatar_mint: &Pubkey, trait_mint: &Pubkey) -> Instruction {
+  build(
+    sla::accounts::MergeV3 {
+      avatar: find_avatar_pda(avatar_mint).0,
+      avatar_v2: find_avatar_v2_pda(avatar_mint).0,
+      avatar_mint: *avatar_mint,
+      trait_mint: *trait_mint,
+      avatar_token: get_ata(user, avatar_mint),
+      trait_token: get_ata(user, trait_mint),
+      avatar_metadata: find_metadata_pda(avatar_mint).0,
+      trait_metadata: find_metadata_pda(trait_mint).0,
+      payer: *user,
+      metadata_authority: find_metadata_authority_pda().0,
+      config: find_config_pda().0,
+      token_program: anchor_spl::token::ID,
+      metadata_program: mpl_token_metadata::ID,
+      system_program: system_program::ID,
+    },
+    sla::instruction::MergeV3 {},
+    &[],
+  )
+}
+
+// The attested builders return the Ed25519 instruction verifying the renderer signature
+// followed by the SLA instruction
+pub fn merge_attested(
+  config: &GlobalConfig,
+  user: &Pubkey,
+  avatar_mint: &Pubkey,
+  trait_mint: &Pubkey,
+  attestation: &UriAttestation,
+) -> Vec<Instruction> {
+  let instruction = build(
+    sla::accounts::MergeAttested {
+      avatar: find_avatar_pda(avatar_mint).0,
+      avatar_v2: find_avatar_v2_pda(avatar_mint).0,
+      avatar_mint: *avatar_mint,
+      trait_mint: *trait_mint,
+      avatar_token: get_ata(user, avatar_mint),
+      trait_token: get_ata(user, trait_mint),
+      avatar_metadata: find_metadata_pda(avatar_mint).0,
+      trait_metadata: find_metadata_pda(trait_mint).0,
+      payer: *user,
+      metadata_authority: find_metadata_authority_pda().0,
+      config: find_config_pda().0,
+      token_program: anchor_spl::token::ID,
+      metadata_program: mpl_token_metadata::ID,
+      instructions: sysvar::instructions::ID,
+      system_program: system_program::ID,
+    },
+    sla::instruction::MergeAttested {
+      metadata_uri: attestation.uri.clone(),
+      nonce: attestation.nonce,
+      expiry: attestation.expiry,
+    },
+    &[],
+  );
+  let action = AttestedAction::Merge { trait_mint: *trait_mint };
+  vec![ed25519_instruction(&config.renderer, action, avatar_mint, attestation), instruction]
+}
+
+// `returned_trait_mint` is a new keypair that must also sign the transaction
+pub fn unmerge(
+  config: &GlobalConfig,
+  user: &Pubkey,
+  avatar_mint: &Pubkey,
+  returned_trait_mint: &Pubkey,
+  trait_collection_mint: &Pubkey,
+  trait_id: u8,
+  metadata_uri: String,
+) -> Instruction {
+  build(
+    sla::accounts::Unmerge {
+      avatar: find_avatar_pda(avatar_mint).0,
+      avatar_v2: find_avatar_v2_pda(avatar_mint).0,
+      avatar_mint: *avatar_mint,
+      avatar_token: get_ata(user, avatar_mint),
+      avatar_metadata: find_metadata_pda(avatar_mint).0,
+      returned_trait_mint: *returned_trait_mint,
+      returned_trait_token: get_ata(user, returned_trait_mint),
+      returned_trait_metadata: find_metadata_pda(returned_trait_mint).0,
+      returned_trait_master_edition: find_master_edition_pda(returned_trait_mint).0,
+      trait_collection_mint: *trait_collection_mint,
+      trait_collection_metadata: find_metadata_pda(trait_collection_mint).0,
+      trait_collection_master_edition: find_master_edition_pda(trait_collection_mint).0,
+      payer: *user,
+      combine_authority: config.combine_authority,
+      metadata_authority: find_metadata_authority_pda().0,
+      config: find_config_pda().0,
+      rent: sysvar::rent::ID,
+      token_program: anchor_spl::token::ID,
+      associated_token_program: anchor_spl::associated_token::ID,
+      metadata_program: mpl_token_metadata::ID,
+      system_program: system_program::ID,
+    },
+    sla::instruction::Unmerge { trait_id, metadata_uri },
+    &[],
+  )
+}
+
+// `returned_trait_mint` is a new keypair that must also sign the transaction
+pub fn swap_trait(
+  config: &GlobalConfig,
+  user: &Pubkey,
+  avatar_mint: &Pubkey,
+  trait_mint: &Pubkey,
+  returned_trait_mint: &Pubkey,
+  trait_collection_mint: &Pubkey,
+  metadata_uri: String,
+) -> Instruction {
+  build(
+    sla::accounts::SwapTrait {
+      avatar: find_avatar_pda(avatar_mint).0,
+      avatar_v2: find_avatar_v2_pda(avatar_mint).0,
+      avatar_mint: *avatar_mint,
+      avatar_token: get_ata(user, avatar_mint),
+      avatar_metadata: find_metadata_pda(avatar_mint).0,
+      trait_mint: *trait_mint,
+      trait_token: get_ata(user, trait_mint),
+      trait_metadata: find_metadata_pda(trait_mint).0,
+      returned_trait_mint: *returned_trait_mint,
+      returned_trait_token: get_ata(user, returned_trait_mint),
+      returned_trait_metadata: find_metadata_pda(returned_trait_mint).0,
+      returned_trait_master_edition: find_master_edition_pda(returned_trait_mint).0,
+      trait_collection_mint: *trait_collection_mint,
+      trait_collection_metadata: find_metadata_pda(trait_collection_mint).0,
+      trait_collection_master_edition: find_master_edition_pda(trait_collection_mint).0,
+      payer: *user,
+      combine_authority: config.combine_authority,
+      metadata_authority: find_metadata_authority_pda().0,
+      config: find_config_pda().0,
+      rent: sysvar::rent::ID,
+      token_program: anchor_spl::token::ID,
+      associated_token_program: anchor_spl::associated_token::ID,
+      metadata_program: mpl_token_metadata::ID,
+      system_program: system_program::ID,
+    },
+    sla::instruction::SwapTrait { metadata_uri },
+    &[],
+  )
+}
+
+pub fn mint_id_card(config: &GlobalConfig, user: &Pubkey) -> Instruction {
+  let mint = config.get_mint(FungibleAsset::ID_CARD);
+
+  build(
+    sla::accounts::MintIdCard {
+      mint,
+      ata: get_ata(user, &mint),
+      user: *user,
+      treasury: find_treasury_pda().0,
+      hay_mint: config.hay_mint,
+      hay_user_ata: get_ata(user, &config.hay_mint),
+      hay_treasury_ata: config.hay_treasury_ata,
+      config: find_config_pda().0,
+      rent: sysvar::rent::ID,
+      token_program: anchor_spl::token::ID,
+      associated_token_program: anchor_spl::associated_token::ID,
+      system_program: system_program::ID,
+    },
+    sla::instruction::MintIdCardV2 {},
+    &[*user],
+  )
+}
+
+// Discounted by the rank of the agent
+pub fn mint_id_card_v3(config: &GlobalConfig, user: &Pubkey, avatar_mint: &Pubkey) -> Instruction {
+  let mint = config.get_mint(FungibleAsset::ID_CARD);
+
+  build(
+    sla::accounts::MintIdCardV3 {
+      mint,
+      ata: get_ata(user, &mint),
+      user: *user,
+      avatar_mint: *avatar_mint,
+      avatar_token: get_ata(user, avatar_mint),
+      avatar_metadata: find_metadata_pda(avatar_mint).0,
+      ranking_v2: find_ranking_v2_pda(avatar_mint).0,
+      rank_ladder: find_rank_ladder_pda().0,
+      treasury: find_treasury_pda().0,
+      hay_mint: config.hay_mint,
+      hay_user_ata: get_ata(user, &config.hay_mint),
+      hay_treasury_ata: config.hay_treasury_ata,
+      config: find_config_pda().0,
+      rent: sysvar::rent::ID,
+      token_program: anchor_spl::token::ID,
+      associated_token_program: anchor_spl::associated_token::ID,
+      system_program: system_program::ID,
+    },
+    sla::instruction::MintIdCardV3 {},
+    &[*user],
+  )
+}
+
+pub fn mint_scanner(config: &GlobalConfig, user: &Pubkey, avatar_mint: &Pubkey) -> Instruction {
+  build(
+    sla::accounts::MintScanner {
+      mint: config.scanner_mint,
+      ata: get_ata(user, &config.scanner_mint),
+      user: *user,
+      avatar_mint: *avatar_mint,
+      avatar_token: get_ata(user, avatar_mint),
+      avatar_metadata: find_metadata_pda(avatar_mint).0,
+      scanner_record: find_scanner_record_pda(avatar_mint).0,
+      ranking_v2: find_ranking_v2_pda(avatar_mint).0,
+      rank_ladder: find_rank_ladder_pda().0,
+      treasury: find_treasury_pda().0,
+      hay_mint: config.hay_mint,
+      hay_user_ata: get_ata(user, &config.hay_mint),
+      hay_treasury_ata: config.hay_treasury_ata,
+      config: find_config_pda().0,
+      rent: sysvar::rent::ID,
+      token_program: anchor_spl::token::ID,
+      associated_token_program: anchor_spl::associated_token::ID,
+      system_program: system_program::ID,
+    },
+    sla::instruction::MintScannerV2 {},
+    &[*user],
+  )
+}
+
+pub fn mint_badge_v2(
+  config: &GlobalConfig,
+  user: &Pubkey,
+  avatar_mint: &Pubkey,
+  badge: FungibleAsset,
+) -> Instruction {
+  let mint = config.get_mint(badge);
+
+  build(
+    sla::accounts::MintBadgeV4 {
+      mint,
+      ata: get_ata(user, &mint),
+      user: *user,
+      treasury: find_treasury_pda().0,
+      hay_mint: config.hay_mint,
+      hay_user_ata: get_ata(user, &config.hay_mint),
+      hay_treasury_ata: config.hay_treasury_ata,
+      config: find_config_pda().0,
+      avatar_mint: *avatar_mint,
+      avatar_token: get_ata(user, avatar_mint),
+      avatar_metadata: find_metadata_pda(avatar_mint).0,
+      ranking_v2: find_ranking_v2_pda(avatar_mint).0,
+      ranking_v3: find_ranking_v3_pda(avatar_mint).0,
+      rank_ladder: find_rank_ladder_pda().0,
+      rent: sysvar::rent::ID,
+      token_program: anchor_spl::token::ID,
+      associated_token_program: anchor_spl::associated_token::ID,
+      system_program: system_program::ID,
+    },
+    sla::instruction::MintBadgeV3 { asset_id: badge as u8 },
+    &[],
+  )
+}
+
+// `previous_alias` is the current alias of the agent (in its `AgentProfile`), if any
+pub fn change_alias(
+  config: &GlobalConfig,
+  user: &Pubkey,
+  avatar_mint: &Pubkey,
+  metadata_uri: String,
+  new_name: String,
+  previous_alias: Option<&str>,
+) -> Instruction {
+  let alias_record = find_alias_record_pda(&new_name).0;
+
+  build(
+    sla::accounts::ChangeAlias {
+      avatar_mint: *avatar_mint,
+      avatar_token: get_ata(user, avatar_mint),
+      avatar_metadata: find_metadata_pda(avatar_mint).0,
+      id_card_mint: config.id_card_mint,
+      id_card_ata: get_ata(user, &config.id_card_mint),
+      payer: *user,
+      alias_record,
+      previous_alias_record: previous_alias.map_or(alias_record, |alias| find_alias_record_pda(alias).0),
+      agent_profile: find_agent_profile_pda(avatar_mint).0,
+      combine_authority: config.combine_authority,
+      metadata_authority: find_metadata_authority_pda().0,
+      config: find_config_pda().0,
+      token_program: anchor_spl::token::ID,
+      metadata_program: mpl_token_metadata::ID,
+      system_program: system_program::ID,
+    },
+    sla::instruction::ChangeAlias { metadata_uri, new_name },
+    &[],
+  )
+}
+
+pub fn change_alias_attested(
+  config: &GlobalConfig,
+  user: &Pubkey,
+  avatar_mint: &Pubkey,
+  new_name: String,
+  previous_alias: Option<&str>,
+  attestation: &UriAttestation,
+) -> Vec<Instruction> {
+  let alias_record = find_alias_record_pda(&new_name).0;
+
+  let instruction = build(
+    sla::accounts::ChangeAliasAttested {
+      avatar_mint: *avatar_mint,
+      avatar_token: get_ata(user, avatar_mint),
+      avatar_metadata: find_metadata_pda(avatar_mint).0,
+      id_card_mint: config.id_card_mint,
+      id_card_ata: get_ata(user, &config.id_card_mint),
+      payer: *user,
+      alias_record,
+      previous_alias_record: previous_alias.map_or(alias_record, |alias| find_alias_record_pda(alias).0),
+      agent_profile: find_agent_profile_pda(avatar_mint).0,
+      avatar_v2: find_avatar_v2_pda(avatar_mint).0,
+      metadata_authority: find_metadata_authority_pda().0,
+      config: find_config_pda().0,
+      token_program: anchor_spl::token::ID,
+      metadata_program: mpl_token_metadata::ID,
+      instructions: sysvar::instructions::ID,
+      system_program: system_program::ID,
+    },
+    sla::instruction::ChangeAliasAttested {
+      metadata_uri: attestation.uri.clone(),
+      new_name: new_name.clone(),
+      nonce: attestation.nonce,
+      expiry: attestation.expiry,
+    },
+    &[],
+  );
+  let action = AttestedAction::ChangeAlias { new_name: &new_name };
+  vec![ed25519_instruction(&config.renderer, action, avatar_mint, attestation), instruction]
+}
+
+// `scan_index` is the number of scans of the agent so far (`AgentProfile::scans`)
+pub fn scan_agent(
+  config: &GlobalConfig,
+  user: &Pubkey,
+  avatar_mint: &Pubkey,
+  scan_index: u32,
+  metadata_uri: Option<String>,
+) -> Instruction {
+  build(
+    sla::accounts::ScanAgent {
+      avatar_mint: *avatar_mint,
+      avatar_token: get_ata(user, avatar_mint),
+      avatar_metadata: find_metadata_pda(avatar_mint).0,
+      scanner_mint: config.scanner_mint,
+      scanner_ata: get_ata(user, &config.scanner_mint),
+      user: *user,
+      agent_profile: find_agent_profile_pda(avatar_mint).0,
+      scan_record: find_scan_record_pda(avatar_mint, scan_index).0,
+      combine_authority: config.combine_authority,
+      metadata_authority: find_metadata_authority_pda().0,
+      config: find_config_pda().0,
+      token_program: anchor_spl::token::ID,
+      metadata_program: mpl_token_metadata::ID,
+      system_program: system_program::ID,
+    },
+    sla::instruction::ScanAgent { metadata_uri },
+    &[],
+  )
+}
+
+pub fn scan_agent_attested(
+  config: &GlobalConfig,
+  user: &Pubkey,
+  avatar_mint: &Pubkey,
+  scan_index: u32,
+  attestation: &UriAttestation,
+) -> Vec<Instruction> {
+  let instruction = build(
+    sla::accounts::ScanAgentAttested {
+      avatar_mint: *avatar_mint,
+      avatar_token: get_ata(user, avatar_mint),
+      avatar_metadata: find_metadata_pda(avatar_mint).0,
+      scanner_mint: config.scanner_mint,
+      scanner_ata: get_ata(user, &config.scanner_mint),
+      user: *user,
+      agent_profile: find_agent_profile_pda(avatar_mint).0,
+      scan_record: find_scan_record_pda(avatar_mint, scan_index).0,
+      avatar_v2: find_avatar_v2_pda(avatar_mint).0,
+      metadata_authority: find_metadata_authority_pda().0,
+      config: find_config_pda().0,
+      token_program: anchor_spl::token::ID,
+      metadata_program: mpl_token_metadata::ID,
+      instructions: sysvar::instructions::ID,
+      system_program: system_program::ID,
+    },
+    sla::instruction::ScanAgentAttested {
+      metadata_uri: attestation.uri.clone(),
+      nonce: attestation.nonce,
+      expiry: attestation.expiry,
+    },
+    &[],
+  );
+  let action = AttestedAction::Scan;
+  vec![ed25519_instruction(&config.renderer, action, avatar_mint, attestation), instruction]
+}
+
+// The scan is recorded under `avatar_mint`, the agent of the user
+pub fn scan_target(
+  config: &GlobalConfig,
+  user: &Pubkey,
+  avatar_mint: &Pubkey,
+  target_mint: &Pubkey,
+  scan_index: u32,
+) -> Instruction {
+  build(
+    sla::accounts::ScanTarget {
+      avatar_mint: *avatar_mint,
+      avatar_token: get_ata(user, avatar_mint),
+      avatar_metadata: find_metadata_pda(avatar_mint).0,
+      target_mint: *target_mint,
+      target_metadata: find_metadata_pda(target_mint).0,
+      scanner_mint: config.scanner_mint,
+      scanner_ata: get_ata(user, &config.scanner_mint),
+      user: *user,
+      agent_profile: find_agent_profile_pda(avatar_mint).0,
+      scan_record: find_scan_record_pda(avatar_mint, scan_index).0,
+      config: find_config_pda().0,
+      token_program: anchor_spl::token::ID,
+      system_program: system_program::ID,
+    },
+    sla::instruction::ScanTarget {},
+    &[],
+  )
+}
+
+pub fn merge_badge_v2(
+  config: &GlobalConfig,
+  user: &Pubkey,
+  avatar_mint: &Pubkey,
+  badge: FungibleAsset,
+  metadata_uri: String,
+) -> Instruction {
+  let badge_mint = config.get_mint(badge);
+
+  build(
+    sla::accounts::MergeBadgeV4 {
+      avatar_mint: *avatar_mint,
+      avatar_token: get_ata(user, avatar_mint),
+      avatar_metadata: find_metadata_pda(avatar_mint).0,
+      payer: *user,
+      badge_mint,
+      badge_ata: get_ata(user, &badge_mint),
+      ranking_v2: find_ranking_v2_pda(avatar_mint).0,
+      ranking_v3: find_ranking_v3_pda(avatar_mint).0,
+      rank_ladder: find_rank_ladder_pda().0,
+      combine_authority: config.combine_authority,
+      metadata_authority: find_metadata_authority_pda().0,
+      config: find_config_pda().0,
+      token_program: anchor_spl::token::ID,
+      metadata_program: mpl_token_metadata::ID,
+      system_program: system_program::ID,
+    },
+    sla::instruction::MergeBadgeV3 { asset_id: badge as u8, metadata_uri },
+    &[],
+  )
+}
+
+pub fn merge_badge_attested(
+  config: &GlobalConfig,
+  user: &Pubkey,
+  avatar_mint: &Pubkey,
+  badge: FungibleAsset,
+  attestation: &UriAttestation,
+) -> Vec<Instruction> {
+  let badge_mint = config.get_mint(badge);
+
+  let instruction = build(
+    sla::accounts::MergeBadgeAttested {
+      avatar_mint: *avatar_mint,
+      avatar_token: get_ata(user, avatar_mint),
+      avatar_metadata: find_metadata_pda(avatar_mint).0,
+      payer: *user,
+      badge_mint,
+      badge_ata: get_ata(user, &badge_mint),
+      ranking_v2: find_ranking_v2_pda(avatar_mint).0,
+      ranking_v3: find_ranking_v3_pda(avatar_mint).0,
+      rank_ladder: find_rank_ladder_pda().0,
+      avatar_v2: find_avatar_v2_pda(avatar_mint).0,
+      metadata_authority: find_metadata_authority_pda().0,
+      config: find_config_pda().0,
+      token_program: anchor_spl::token::ID,
+      metadata_program: mpl_token_metadata::ID,
+      instructions: sysvar::instructions::ID,
+      system_program: system_program::ID,
+    },
+    sla::instruction::MergeBadgeAttested {
+      asset_id: badge as u8,
+      metadata_uri: attestation.uri.clone(),
+      nonce: attestation.nonce,
+      expiry: attestation.expiry,
+    },
+    &[],
+  );
+  let action = AttestedAction::MergeBadge { asset_id: badge as u8 };
+  vec![ed25519_instruction(&config.renderer, action, avatar_mint, attestation), instruction]
+}
+
+pub fn add_badge(
+  config: &GlobalConfig,
+  avatar_mint: &Pubkey,
+  badge: FungibleAsset,
+  approvers: &[Pubkey],
+) -> Instruction {
+  let instruction = build(
+    sla::accounts::AddBadge {
+      avatar_mint: *avatar_mint,
+      ranking_v1: find_ranking_pda(avatar_mint).0,
+      ranking_v2: find_ranking_v2_pda(avatar_mint).0,
+      ranking_v3: find_ranking_v3_pda(avatar_mint).0,
+      rank_ladder: find_rank_ladder_pda().0,
+      combine_authority: config.combine_authority,
+      authority_set: find_authority_set_pda().0,
+      config: find_config_pda().0,
+      token_program: anchor_spl::token::ID,
+      metadata_program: mpl_token_metadata::ID,
+      system_program: system_program::ID,
+    },
+    sla::instruction::AddBadgeV2 { asset_id: badge as u8 },
+    &[],
+  );
+  with_approvers(instruction, approvers)
+}
+
+// `tier` is the rank ladder tier the agent is lowered to (None clears its rank), `reason` a code kept in the event
+pub fn revoke_rank(
+  admin: &Pubkey,
+  avatar_mint: &Pubkey,
+  tier: Option<u8>,
+  reason: u8,
+  allow_remint: bool,
+) -> Instruction {
+  build(
+    sla::accounts::RevokeRank {
+      avatar_mint: *avatar_mint,
+      ranking_v1: find_ranking_pda(avatar_mint).0,
+      ranking_v2: find_ranking_v2_pda(avatar_mint).0,
+      ranking_v3: find_ranking_v3_pda(avatar_mint).0,
+      rank_ladder: find_rank_ladder_pda().0,
+      config: find_config_pda().0,
+      admin: *admin,
+      system_program: system_program::ID,
+    },
+    sla::instruction::RevokeRank {
+      tier,
+      reason,
+      allow_remint,
+    },
+    &[],
+  )
+}
+
+// Must be sent once by the holder of the agent before any badge instruction
+// `badge_mint` is the mint of the tier in the `RankLadder`
+pub fn mint_badge_v4(
+  config: &GlobalConfig,
+  user: &Pubkey,
+  avatar_mint: &Pubkey,
+  tier: u8,
+  badge_mint: &Pubkey,
+) -> Instruction {
+  build(
+    sla::accounts::MintBadgeV4 {
+      mint: *badge_mint,
+      ata: get_ata(user, badge_mint),
+      user: *user,
+      treasury: find_treasury_pda().0,
+      hay_mint: config.hay_mint,
+      hay_user_ata: get_ata(user, &config.hay_mint),
+      hay_treasury_ata: config.hay_treasury_ata,
+      config: find_config_pda().0,
+      avatar_mint: *avatar_mint,
+      avatar_token: get_ata(user, avatar_mint),
+      avatar_metadata: find_metadata_pda(avatar_mint).0,
+      ranking_v2: find_ranking_v2_pda(avatar_mint).0,
+      ranking_v3: find_ranking_v3_pda(avatar_mint).0,
+      rank_ladder: find_rank_ladder_pda().0,
+      rent: sysvar::rent::ID,
+      token_program: anchor_spl::token::ID,
+      associated_token_program: anchor_spl::associated_token::ID,
+      system_program: system_program::ID,
+    },
+    sla::instruction::MintBadgeV4 { tier },
+    &[],
+  )
+}
+
+pub fn merge_badge_v4(
+  config: &GlobalConfig,
+  user: &Pubkey,
+  avatar_mint: &Pubkey,
+  tier: u8,
+  badge_mint: &Pubkey,
+  metadata_uri: String,
+) -> Instruction {
+  build(
+    sla::accounts::MergeBadgeV4 {
+      avatar_mint: *avatar_mint,
+      avatar_token: get_ata(user, avatar_mint),
+      avatar_metadata: find_metadata_pda(avatar_mint).0,
+      payer: *user,
+      badge_mint: *badge_mint,
+      badge_ata: get_ata(user, badge_mint),
+      ranking_v2: find_ranking_v2_pda(avatar_mint).0,
+      ranking_v3: find_ranking_v3_pda(avatar_mint).0,
+      rank_ladder: find_rank_ladder_pda().0,
+      combine_authority: config.combine_authority,
+      metadata_authority: find_metadata_authority_pda().0,
+      config: find_config_pda().0,
+      token_program: anchor_spl::token::ID,
+      metadata_program: mpl_token_metadata::ID,
+      system_program: system_program::ID,
+    },
+    sla::instruction::MergeBadgeV4 { tier, metadata_uri },
+    &[],
+  )
+}
+
+pub fn migrate_ranking(user: &Pubkey, avatar_mint: &Pubkey) -> Instruction {
+  build(
+    sla::accounts::MigrateRanking {
+      avatar_mint: *avatar_mint,
+      avatar_token: get_ata(user, avatar_mint),
+      avatar_metadata: find_metadata_pda(avatar_mint).0,
+      ranking_v1: find_ranking_pda(avatar_mint).0,
+      ranking_v2: find_ranking_v2_pda(avatar_mint).0,
+      payer: *user,
+      config: find_config_pda().0,
+      system_program: system_program::ID,
+    },
+    sla::instruction::MigrateRanking {},
+    &[],
+  )
+}
+
+pub fn init_authority_set(admin: &Pubkey, members: Vec<Pubkey>, threshold: u8) -> Instruction {
+  build(
+    sla::accounts::InitAuthoritySet {
+      authority_set: find_authority_set_pda().0,
+      config: find_config_pda().0,
+      admin: *admin,
+      system_program: system_program::ID,
+    },
+    sla::instruction::InitAuthoritySet { members, threshold },
+    &[],
+  )
+}
+
+pub fn update_authority_set(approvers: &[Pubkey], members: Vec<Pubkey>, threshold: u8) -> Instruction {
+  let instruction = build(
+    sla::accounts::UpdateAuthoritySet {
+      authority_set: find_authority_set_pda().0,
+    },
+    sla::instruction::UpdateAuthoritySet { members, threshold },
+    &[],
+  );
+  with_approvers(instruction, approvers)
+}
+
+pub fn rotate_combine_authority(approvers: &[Pubkey], new_combine_authority: Pubkey) -> Instruction {
+  let instruction = build(
+    sla::accounts::RotateCombineAuthority {
+      authority_set: find_authority_set_pda().0,
+      config: find_config_pda().0,
+    },
+    sla::instruction::RotateCombineAuthority { new_combine_authority },
+    &[],
+  );
+  with_approvers(instruction, approvers)
+}
+
+pub fn transfer_update_authority(config: &GlobalConfig, avatar_mint: &Pubkey) -> Instruction {
+  build(
+    sla::accounts::TransferUpdateAuthority {
+      avatar_metadata: find_metadata_pda(avatar_mint).0,
+      metadata_authority: find_metadata_authority_pda().0,
+      combine_authority: config.combine_authority,
+      config: find_config_pda().0,
+      metadata_program: mpl_token_metadata::ID,
+    },
+    sla::instruction::TransferUpdateAuthority {},
+    &[],
+  )
+}
+
+pub fn set_paused(admin: &Pubkey, paused: PauseFlags) -> Instruction {
+  build(
+    sla::accounts::UpdateConfig {
+      config: find_config_pda().0,
+      admin: *admin,
+    },
+    sla::instruction::SetPaused { paused },
+    &[],
+  )
+}
+
+pub fn set_renderer(admin: &Pubkey, renderer: Pubkey) -> Instruction {
+  build(
+    sla::accounts::UpdateConfig {
+      config: find_config_pda().0,
+      admin: *admin,
+    },
+    sla::instruction::SetRenderer { renderer },
+    &[],
+  )
+}
+
+pub fn set_metadata_base_uri(admin: &Pubkey, base_uri: String) -> Instruction {
+  build(
+    sla::accounts::UpdateConfig {
+      config: find_config_pda().0,
+      admin: *admin,
+    },
+    sla::instruction::SetMetadataBaseUri { base_uri },
+    &[],
+  )
+}
+
+pub fn init_badge_supply_counter(
+  admin: &Pubkey,
+  max_bronze: u16,
+  max_silver: u16,
+  max_gold: u16,
+  max_platinum: u16,
+  max_diamond: u16,
+) -> Instruction {
+  build(
+    sla::accounts::InitBadgeSupplyCounter {
+      badge_supply_counter: find_badge_supply_counter_pda().0,
+      config: find_config_pda().0,
+      admin: *admin,
+      system_program: system_program::ID,
+    },
+    sla::instruction::InitBadgeSupplyCounter {
+      max_bronze,
+      max_silver,
+      max_gold,
+      max_platinum,
+      max_diamond,
+    },
+    &[],
+  )
+}
+
+pub fn init_rank_ladder(admin: &Pubkey) -> Instruction {
+  build(
+    sla::accounts::InitRankLadder {
+      rank_ladder: find_rank_ladder_pda().0,
+      badge_supply_counter: find_badge_supply_counter_pda().0,
+      config: find_config_pda().0,
+      admin: *admin,
+      system_program: system_program::ID,
+    },
+    sla::instruction::InitRankLadder {},
+    &[],
+  )
+}
+
+pub fn set_rank_tier(admin: &Pubkey, index: u8, params: RankTierParams) -> Instruction {
+  build(
+    sla::accounts::UpdateRankLadder {
+      rank_ladder: find_rank_ladder_pda().0,
+      config: find_config_pda().0,
+      admin: *admin,
+    },
+    sla::instruction::SetRankTier { index, params },
+    &[],
+  )
+}
+
+pub fn set_rank_discounts(admin: &Pubkey, index: u8, rank_discounts: RankDiscounts) -> Instruction {
+  build(
+    sla::accounts::UpdateRankLadder {
+      rank_ladder: find_rank_ladder_pda().0,
+      config: find_config_pda().0,
+      admin: *admin,
+    },
+    sla::instruction::SetRankDiscounts { index, rank_discounts },
+    &[],
+  )
+}
+
+pub fn set_badge_supply(admin: &Pubkey, badge: FungibleAsset, max_supply: u16) -> Instruction {
+  build(
+    sla::accounts::UpdateRankLadder {
+      rank_ladder: find_rank_ladder_pda().0,
+      config: find_config_pda().0,
+      admin: *admin,
+    },
+    sla::instruction::SetBadgeSupply { asset_id: badge as u8, max_supply },
+    &[],
+  )
+}
+
+pub fn reset_badge_supply(admin: &Pubkey, badge: FungibleAsset) -> Instruction {
+  build(
+    sla::accounts::UpdateRankLadder {
+      rank_ladder: find_rank_ladder_pda().0,
+      config: find_config_pda().0,
+      admin: *admin,
+    },
+    sla::instruction::ResetBadgeSupply { asset_id: badge as u8 },
+    &[],
+  )
+}
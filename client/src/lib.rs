@@ -0,0 +1,13 @@
+//! Client helpers for the SLA program: PDA derivation and instruction builders.
+//!
+//! The builders only need the user, the avatar mint and the on-chain `GlobalConfig`
+//! (fetched once by the caller); every other account (PDAs, ATAs, Metaplex accounts,
+//! program IDs) and every bump is derived here.
+
+pub mod attestation;
+pub mod instructions;
+pub mod pda;
+
+pub use sla::sla_accounts::{ConfigParams, GlobalConfig, PauseFlags};
+pub use sla::sla_fungible_token::FungibleAsset;
+pub use sla::ID;
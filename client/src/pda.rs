@@ -0,0 +1,124 @@
+use anchor_lang::solana_program::{bpf_loader_upgradeable, pubkey::Pubkey};
+use sla::sla_accounts::{AliasRecord, ScanRecord};
+use sla::sla_constants;
+
+
+pub fn find_config_pda() -> (Pubkey, u8) {
+  Pubkey::find_program_address(&[sla_constants::PREFIX_CONFIG.as_bytes()], &sla::ID)
+}
+
+// Holds the upgrade authority allowed to call `initialize_config`
+pub fn find_program_data_pda() -> (Pubkey, u8) {
+  Pubkey::find_program_address(&[sla::ID.as_ref()], &bpf_loader_upgradeable::ID)
+}
+
+pub fn find_treasury_pda() -> (Pubkey, u8) {
+  Pubkey::find_program_address(&[sla_constants::PREFIX_TREASURY.as_bytes()], &sla::ID)
+}
+
+pub fn find_badge_supply_counter_pda() -> (Pubkey, u8) {
+  Pubkey::find_program_address(&[sla_constants::PREFIX_BADGE_POT.as_bytes()], &sla::ID)
+}
+
+pub fn find_authority_set_pda() -> (Pubkey, u8) {
+  Pubkey::find_program_address(&[sla_constants::PREFIX_AUTHORITY_SET.as_bytes()], &sla::ID)
+}
+
+pub fn find_metadata_authority_pda() -> (Pubkey, u8) {
+  Pubkey::find_program_address(&[sla_constants::PREFIX_UPDATE_AUTHORITY.as_bytes()], &sla::ID)
+}
+
+// Aliases differing only by case share the same record
+pub fn find_alias_record_pda(alias: &str) -> (Pubkey, u8) {
+  Pubkey::find_program_address(
+    &[sla_constants::PREFIX_ALIAS.as_bytes(), &AliasRecord::seed(alias)],
+    &sla::ID,
+  )
+}
+
+pub fn find_agent_profile_pda(avatar_mint: &Pubkey) -> (Pubkey, u8) {
+  Pubkey::find_program_address(
+    &[sla_constants::PREFIX_PROFILE.as_bytes(), &avatar_mint.to_bytes()],
+    &sla::ID,
+  )
+}
+
+pub fn find_avatar_pda(avatar_mint: &Pubkey) -> (Pubkey, u8) {
+  Pubkey::find_program_address(
+    &[sla_constants::PREFIX_LLAMA.as_bytes(), &avatar_mint.to_bytes()],
+    &sla::ID,
+  )
+}
+
+pub fn find_avatar_v2_pda(avatar_mint: &Pubkey) -> (Pubkey, u8) {
+  Pubkey::find_program_address(
+    &[sla_constants::PREFIX_LLAMA_V2.as_bytes(), &avatar_mint.to_bytes()],
+    &sla::ID,
+  )
+}
+
+pub fn find_ranking_pda(avatar_mint: &Pubkey) -> (Pubkey, u8) {
+  Pubkey::find_program_address(
+    &[sla_constants::PREFIX_RANKING.as_bytes(), &avatar_mint.to_bytes()],
+    &sla::ID,
+  )
+}
+
+pub fn find_ranking_v2_pda(avatar_mint: &Pubkey) -> (Pubkey, u8) {
+  Pubkey::find_program_address(
+    &[sla_constants::PREFIX_RANKING_V2.as_bytes(), &avatar_mint.to_bytes()],
+    &sla::ID,
+  )
+}
+
+pub fn find_ranking_v3_pda(avatar_mint: &Pubkey) -> (Pubkey, u8) {
+  Pubkey::find_program_address(
+    &[sla_constants::PREFIX_RANKING_V3.as_bytes(), &avatar_mint.to_bytes()],
+    &sla::ID,
+  )
+}
+
+pub fn find_rank_ladder_pda() -> (Pubkey, u8) {
+  Pubkey::find_program_address(&[sla_constants::PREFIX_RANK_LADDER.as_bytes()], &sla::ID)
+}
+
+pub fn find_scan_record_pda(avatar_mint: &Pubkey, index: u32) -> (Pubkey, u8) {
+  Pubkey::find_program_address(
+    &[sla_constants::PREFIX_SCAN.as_bytes(), &avatar_mint.to_bytes(), &ScanRecord::seed(index)],
+    &sla::ID,
+  )
+}
+
+pub fn find_scanner_record_pda(avatar_mint: &Pubkey) -> (Pubkey, u8) {
+  Pubkey::find_program_address(
+    &[sla_constants::PREFIX_SCANNER.as_bytes(), &avatar_mint.to_bytes()],
+    &sla::ID,
+  )
+}
+
+pub fn find_metadata_pda(mint: &Pubkey) -> (Pubkey, u8) {
+  Pubkey::find_program_address(
+    &[
+      mpl_token_metadata::state::PREFIX.as_bytes(),
+      &mpl_token_metadata::ID.to_bytes(),
+      &mint.to_bytes(),
+    ],
+    &mpl_token_metadata::ID,
+  )
+}
+
+pub fn find_master_edition_pda(mint: &Pubkey) -> (Pubkey, u8) {
+  Pubkey::find_program_address(
+    &[
+      mpl_token_metadata::state::PREFIX.as_bytes(),
+      &mpl_token_metadata::ID.to_bytes(),
+      &mint.to_bytes(),
+      mpl_token_metadata::state::EDITION.as_bytes(),
+    ],
+    &mpl_token_metadata::ID,
+  )
+}
+
+pub fn get_ata(wallet: &Pubkey, mint: &Pubkey) -> Pubkey {
+  anchor_spl::associated_token::get_associated_token_address(wallet, mint)
+}
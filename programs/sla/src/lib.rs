@@ -1,17 +1,26 @@
 use anchor_lang::{prelude::*};
 use anchor_spl;
+use std::convert::TryFrom;
 use mpl_token_metadata;
 
-mod sla_accounts;
-mod sla_errors;
+pub mod sla_accounts;
+pub mod sla_errors;
 mod utils;
 mod sla_metadata;
 mod sla_token;
-mod sla_constants;
+pub mod sla_constants;
 mod sla_collection;
-mod sla_fungible_token;
+pub mod sla_fungible_token;
+pub mod sla_events;
+pub mod sla_attestation;
+mod sla_alias;
+mod sla_handlers;
 use sla_errors::SlaErrors;
-use utils::{assert_address, verify_avatar, verify_trait};
+use sla_fungible_token::FungibleAsset;
+use sla_events::*;
+use sla_metadata::MetadataSigner;
+use sla_collection::check_collection;
+use utils::{agent_discounts, assert_address, assert_not_paused, close_account, find_pda, load_ranking_v1, verify_avatar, verify_target_avatar};
 
 declare_id!("GUSxqUfUdqchfErA3DrW1jNVJKGdMpxt71AeDkJJtG5R");
 
@@ -20,84 +29,190 @@ declare_id!("GUSxqUfUdqchfErA3DrW1jNVJKGdMpxt71AeDkJJtG5R");
 pub mod sla {
     use super::*;
 
-    pub fn merge(
-      ctx: Context<Merge>,
-      avatar_bump: u8,
-      metadata_uri: String,
+    pub fn initialize_config(
+      ctx: Context<InitializeConfig>,
+      params: sla_accounts::ConfigParams,
+    ) -> ProgramResult {
+      msg!("Initializing the global config");
+      let config = &mut ctx.accounts.config;
+      config.set(params);
+      config.store_bumps();
+      Ok(())
+    }
+
+    pub fn update_config(ctx: Context<UpdateConfig>, params: sla_accounts::ConfigParams) -> ProgramResult {
+      msg!("Updating the global config");
+      let config = &mut ctx.accounts.config;
+
+      // The combine authority can only be rotated by the authority set
+      let combine_authority = config.combine_authority;
+      config.set(params);
+      config.combine_authority = combine_authority;
+      Ok(())
+    }
+
+    pub fn init_authority_set(
+      ctx: Context<InitAuthoritySet>,
+      members: Vec<Pubkey>,
+      threshold: u8,
+    ) -> ProgramResult {
+      msg!("Initializing the authority set ({} of {})", threshold, members.len());
+      let authority_set = &mut ctx.accounts.authority_set;
+      authority_set.set(members, threshold)?;
+      authority_set.bump = find_pda(&[sla_constants::PREFIX_AUTHORITY_SET.as_bytes()]).1;
+
+      emit!(AuthoritySetUpdated {
+        members: authority_set.members.clone(),
+        threshold,
+      });
+
+      Ok(())
+    }
+
+    // Approved by `threshold` members of the current set, passed as signers in the remaining accounts
+    pub fn update_authority_set(
+      ctx: Context<UpdateAuthoritySet>,
+      members: Vec<Pubkey>,
+      threshold: u8,
+    ) -> ProgramResult {
+      msg!("Updating the authority set ({} of {})", threshold, members.len());
+      let authority_set = &mut ctx.accounts.authority_set;
+      authority_set.check_approvals(ctx.remaining_accounts)?;
+      authority_set.set(members, threshold)?;
+
+      emit!(AuthoritySetUpdated {
+        members: authority_set.members.clone(),
+        threshold,
+      });
+
+      Ok(())
+    }
+
+    // Approved by `threshold` members of the authority set, passed as signers in the remaining accounts
+    pub fn rotate_combine_authority(
+      ctx: Context<RotateCombineAuthority>,
+      new_combine_authority: Pubkey,
     ) -> ProgramResult {
+      msg!("Rotating the combine authority to {}", new_combine_authority);
+      ctx.accounts.authority_set.check_approvals(ctx.remaining_accounts)?;
+
+      let config = &mut ctx.accounts.config;
+      let old_combine_authority = config.combine_authority;
+      config.combine_authority = new_combine_authority;
+
+      emit!(CombineAuthorityRotated {
+        old_combine_authority,
+        new_combine_authority,
+      });
+
+      Ok(())
+    }
 
-      let avatar = &mut ctx.accounts.avatar;
+    // Once transferred, the program PDA signs the metadata updates of the avatar
+    pub fn transfer_update_authority(ctx: Context<TransferUpdateAuthority>) -> ProgramResult {
       let avatar_metadata = ctx.accounts.avatar_metadata.to_account_info();
-      let trait_metadata = ctx.accounts.trait_metadata.to_account_info();
-      let metadata_program = ctx.accounts.metadata_program.to_account_info();
-      let payer = ctx.accounts.payer.to_account_info();
-      let combine_authority = ctx.accounts.combine_authority.to_account_info();
 
-      let trait_ata = ctx.accounts.trait_token.to_account_info();
-      let trait_mint = ctx.accounts.trait_mint.to_account_info();
+      // Only SLA avatars can be handed over to the program
+      let metadata = mpl_token_metadata::state::Metadata::from_account_info(&avatar_metadata)
+        .map_err(|_| SlaErrors::InvalidMetadataAccount)?;
+      if !check_collection(&metadata, &utils::str_to_pubkey(sla_constants::LLAMA_COLLECTION)) {
+        return Err(SlaErrors::AvatarNotInCollection.into());
+      }
 
-      // Verify that the avatar belongs to the SLA collection
-      msg!("Verifying agent belongs to the right collection");
-      verify_avatar(
-        ctx.accounts.avatar_mint.key(),
-        ctx.accounts.avatar_token.clone(),
-        payer.key(),
-        &avatar_metadata,
+      msg!("Transferring the update authority of {} to the program", metadata.mint);
+      sla_metadata::transfer_update_authority(
+        avatar_metadata,
+        ctx.accounts.combine_authority.to_account_info(),
+        ctx.accounts.metadata_authority.to_account_info(),
+        ctx.accounts.metadata_program.to_account_info(),
       )?;
 
-      // Verify that the trait belongs to the SLA collection + extract the trait ID
-      msg!("Verifying trait belongs to the right collection");
-      let trait_id = verify_trait(
-        trait_mint.key(),
-        ctx.accounts.trait_token.clone(),
-        payer.key(),
-        &trait_metadata,
-      )?;
+      emit!(UpdateAuthorityTransferred {
+        avatar_mint: metadata.mint,
+        metadata_authority: ctx.accounts.metadata_authority.key(),
+      });
 
-      // Initialize the Avatar account if needed
-      msg!("Initializing agent PDA if needed");
-      match avatar.traits {
-        Some(_) => (),
-        None => avatar.init()?,
-      };
-
-      // Update the SLA Avatar data (while checking whether the merge is allowed)
-      msg!("Updating agent PDA");
-      avatar.merge(trait_id)?;
-
-      // Update the metadata URI through the Metaplex program
-      msg!("Updating agent metadata with new URI");
-      sla_metadata::update_metadata(
-        avatar_metadata, 
-        combine_authority,
-        metadata_program,
-        metadata_uri,
-        None,
-      )?;
+      Ok(())
+    }
 
-      // Burn the trait token
-      msg!("Burning trait token");
-      sla_token::burn_trait(
-        trait_ata, 
-        trait_mint, 
-        payer.clone(), 
-        ctx.accounts.token_program.to_account_info()
+    // Legacy signature: the bumps are ignored and derived on-chain
+    pub fn merge(ctx: Context<Merge>, _avatar_bump: u8, metadata_uri: String) -> ProgramResult {
+      merge_v2(ctx, metadata_uri)
+    }
+
+    pub fn merge_v2(ctx: Context<Merge>, metadata_uri: String) -> ProgramResult {
+      assert_not_paused(ctx.accounts.config.paused.merge)?;
+
+      let signer = MetadataSigner::CombineAuthority(ctx.accounts.combine_authority.to_account_info());
+      sla_handlers::merge_trait(ctx.accounts.merge_accounts(), signer, metadata_uri)
+    }
+
+    // No combine authority: the program PDA signs the metadata update and the new URI is derived
+    // from the config base URI, so the agent update authority must have been transferred
+    pub fn merge_v3(ctx: Context<MergeV3>) -> ProgramResult {
+      assert_not_paused(ctx.accounts.config.paused.merge)?;
+
+      let metadata_uri = ctx.accounts.config.metadata_uri(&ctx.accounts.avatar_mint.key(), Clock::get()?.slot)?;
+      sla_handlers::merge_trait(ctx.accounts.merge_accounts(), MetadataSigner::Program, metadata_uri)
+    }
+
+    // The URI is signed by the renderer (Ed25519 instruction right before this one) instead of
+    // being trusted because the combine authority co-signs
+    pub fn merge_attested(
+      ctx: Context<MergeAttested>,
+      metadata_uri: String,
+      nonce: u64,
+      expiry: i64,
+    ) -> ProgramResult {
+      assert_not_paused(ctx.accounts.config.paused.merge)?;
+
+      // Verify the renderer attested the new URI for this agent
+      msg!("Verifying the URI attestation");
+      sla_attestation::verify_attestation(
+        &ctx.accounts.instructions,
+        &ctx.accounts.config.renderer,
+        sla_attestation::AttestedAction::Merge { trait_mint: ctx.accounts.trait_mint.key() },
+        &ctx.accounts.avatar_mint.key(),
+        &metadata_uri,
+        nonce,
+        expiry,
       )?;
+      ctx.accounts.avatar_v2.use_nonce(nonce)?;
 
-      msg!("Instruction finished");
+      sla_handlers::merge_trait(ctx.accounts.merge_accounts(), MetadataSigner::Program, metadata_uri)
+    }
 
-      Ok(())
+
+    // The re-minted trait's name, symbol and URI are derived from its trait ID and the config base URI.
+    // The new agent URI is trusted through the `combine_authority` co-signature
+    pub fn unmerge(ctx: Context<Unmerge>, trait_id: u8, metadata_uri: String) -> ProgramResult {
+      assert_not_paused(ctx.accounts.config.paused.merge)?;
+
+      sla_handlers::unmerge_trait(ctx.accounts.unmerge_accounts(), trait_id, metadata_uri)
     }
 
+    // Same as `unmerge` for the replaced trait
+    pub fn swap_trait(ctx: Context<SwapTrait>, metadata_uri: String) -> ProgramResult {
+      assert_not_paused(ctx.accounts.config.paused.merge)?;
 
-    pub fn mint_id_card(ctx: Context<MintIdCard>, treasury_bump: u8, asset_id: u8) -> ProgramResult {
-      msg!("Entering the MintIdCard instruction");
+      sla_handlers::swap_trait(ctx.accounts.swap_accounts(), metadata_uri)
+    }
 
-      let fungible_asset = sla_fungible_token::FungibleAsset::from_u8(asset_id);
-      
-      if fungible_asset != sla_fungible_token::FungibleAsset::ID_CARD {
-        panic!("The asset_id provided is not an ID card")
+    // Legacy signature: the treasury bump is ignored and read from the config
+    pub fn mint_id_card(ctx: Context<MintIdCard>, _treasury_bump: u8, asset_id: u8) -> ProgramResult {
+      if FungibleAsset::try_from(asset_id)? != FungibleAsset::ID_CARD {
+        return Err(SlaErrors::AssetIsNotIdCard.into());
       }
+      mint_id_card_v2(ctx)
+    }
+
+    pub fn mint_id_card_v2(ctx: Context<MintIdCard>) -> ProgramResult {
+      assert_not_paused(ctx.accounts.config.paused.fungible)?;
+
+      msg!("Entering the MintIdCard instruction");
+
+      let fungible_asset = FungibleAsset::ID_CARD;
+      let price = ctx.accounts.config.get_price(fungible_asset);
 
       sla_fungible_token::mint_fungible_asset(
         ctx.accounts.mint.to_account_info(),
@@ -106,150 +221,240 @@ pub mod sla {
         ctx.accounts.treasury.to_account_info(),
         ctx.accounts.hay_user_ata.to_account_info(),
         ctx.accounts.hay_treasury_ata.to_account_info(),
+        ctx.accounts.hay_mint.to_account_info(),
+        ctx.accounts.hay_mint.decimals,
         ctx.accounts.token_program.to_account_info(),
         fungible_asset,
-        treasury_bump,
-      )
+        price,
+        ctx.accounts.config.treasury_bump,
+      )?;
+
+      emit!(FungibleAssetMinted {
+        user: ctx.accounts.user.key(),
+        avatar_mint: None,
+        mint: ctx.accounts.mint.key(),
+        asset: Some(fungible_asset),
+        price,
+        list_price: price,
+      });
+
+      Ok(())
     }
 
-    pub fn mint_scanner(ctx: Context<MintScanner>, treasury_bump: u8) -> ProgramResult {
-      msg!("Entering the MintScanner instruction");
+    // ID card bought by the holder of an agent, at the discounted price of its rank
+    pub fn mint_id_card_v3(ctx: Context<MintIdCardV3>) -> ProgramResult {
+      assert_not_paused(ctx.accounts.config.paused.fungible)?;
 
-      sla_fungible_token::mint_scanner(
+      msg!("Entering the MintIdCardV3 instruction");
+
+      msg!("Verifying agent belongs to the right collection");
+      verify_avatar(
+        ctx.accounts.avatar_mint.key(),
+        *ctx.accounts.avatar_token.clone(),
+        ctx.accounts.user.key(),
+        &ctx.accounts.avatar_metadata,
+        &ctx.accounts.config,
+      )?;
+
+      let fungible_asset = FungibleAsset::ID_CARD;
+      let list_price = ctx.accounts.config.get_price(fungible_asset);
+      let discounts = agent_discounts(&ctx.accounts.rank_ladder, &ctx.accounts.ranking_v2)?;
+      let price = discounts.id_card_price(list_price);
+
+      sla_fungible_token::mint_fungible_asset(
         ctx.accounts.mint.to_account_info(),
         ctx.accounts.ata.to_account_info(),
         ctx.accounts.user.to_account_info(),
         ctx.accounts.treasury.to_account_info(),
         ctx.accounts.hay_user_ata.to_account_info(),
         ctx.accounts.hay_treasury_ata.to_account_info(),
+        ctx.accounts.hay_mint.to_account_info(),
+        ctx.accounts.hay_mint.decimals,
         ctx.accounts.token_program.to_account_info(),
-        treasury_bump,
-      )
+        fungible_asset,
+        price,
+        ctx.accounts.config.treasury_bump,
+      )?;
+
+      emit!(FungibleAssetMinted {
+        user: ctx.accounts.user.key(),
+        avatar_mint: Some(ctx.accounts.avatar_mint.key()),
+        mint: ctx.accounts.mint.key(),
+        asset: Some(fungible_asset),
+        price,
+        list_price,
+      });
+
+      Ok(())
     }
 
-    pub fn mint_badge_v2(
-      ctx: Context<MintBadgeV2>, 
-      treasury_bump: u8, 
-      ranking_v1_bump: u8, 
-      ranking_v2_bump: u8, 
-      badge_supply_counter_bump: u8,
-      asset_id: u8,
-    ) -> ProgramResult {
-      msg!("Entering the MintBadgeV2 instruction");
+    // Legacy signature: the treasury bump is ignored and read from the config
+    pub fn mint_scanner(ctx: Context<MintScanner>, _treasury_bump: u8) -> ProgramResult {
+      mint_scanner_v2(ctx)
+    }
 
-      let user = ctx.accounts.user.to_account_info();
-      let asset_to_mint = sla_fungible_token::FungibleAsset::from_u8(asset_id);
+    pub fn mint_scanner_v2(ctx: Context<MintScanner>) -> ProgramResult {
+      assert_not_paused(ctx.accounts.config.paused.fungible)?;
 
-      // Verify that the avatar belongs to the SLA collection
+      msg!("Entering the MintScanner instruction");
+
+      // Only agent holders can mint scanners, within the limit and cooldown of their agent
       msg!("Verifying agent belongs to the right collection");
       verify_avatar(
         ctx.accounts.avatar_mint.key(),
-        ctx.accounts.avatar_token.clone(),
-        user.key(),
-        &ctx.accounts.avatar_metadata.to_account_info(),
+        *ctx.accounts.avatar_token.clone(),
+        ctx.accounts.user.key(),
+        &ctx.accounts.avatar_metadata,
+        &ctx.accounts.config,
       )?;
 
-      // Check the supply has not reached its max + increment the counter
-      let badge_supply_counter = &mut ctx.accounts.badge_supply_counter;
-      badge_supply_counter.increment(asset_to_mint)?;
+      let config = &ctx.accounts.config;
+      let scanner_record = &mut ctx.accounts.scanner_record;
+      scanner_record.record_mint(Clock::get()?.unix_timestamp, config.scanner_limit, config.scanner_cooldown)?;
+      scanner_record.bump = find_pda(
+        &[sla_constants::PREFIX_SCANNER.as_bytes(), &ctx.accounts.avatar_mint.key().to_bytes()]
+      ).1;
 
-      // Convert from Ranking V1 to V2
-      msg!("Updating from ranking v1 to v2");
-      let ranking_v1 = &mut ctx.accounts.ranking_v1;
-      let ranking_v2 = &mut ctx.accounts.ranking_v2;
-      ranking_v2.from_v1(ranking_v1);
+      let list_price = ctx.accounts.config.price_scanner;
+      let discounts = agent_discounts(&ctx.accounts.rank_ladder, &ctx.accounts.ranking_v2)?;
+      let price = discounts.scanner_price(list_price);
 
-      // Update the avatar ranking (+ check if the avatar is allowed to mint this badge)
-      msg!("Minting the badge + checking the agent is allowed to do so");
-      ranking_v2.mint(asset_to_mint)?;
-      
-      sla_fungible_token::mint_fungible_asset(
+      sla_fungible_token::mint_scanner(
         ctx.accounts.mint.to_account_info(),
         ctx.accounts.ata.to_account_info(),
         ctx.accounts.user.to_account_info(),
         ctx.accounts.treasury.to_account_info(),
         ctx.accounts.hay_user_ata.to_account_info(),
         ctx.accounts.hay_treasury_ata.to_account_info(),
+        ctx.accounts.hay_mint.to_account_info(),
+        ctx.accounts.hay_mint.decimals,
         ctx.accounts.token_program.to_account_info(),
-        sla_fungible_token::FungibleAsset::from_u8(asset_id),
-        treasury_bump,
+        price,
+        ctx.accounts.config.treasury_bump,
       )?;
 
+      emit!(FungibleAssetMinted {
+        user: ctx.accounts.user.key(),
+        avatar_mint: Some(ctx.accounts.avatar_mint.key()),
+        mint: ctx.accounts.mint.key(),
+        asset: None,
+        price,
+        list_price,
+      });
+
       Ok(())
     }
 
+    // Legacy signature: the bumps are ignored and derived on-chain
+    pub fn mint_badge_v2(
+      ctx: Context<MintBadgeV4>, 
+      _treasury_bump: u8, 
+      _ranking_v1_bump: u8, 
+      _ranking_v2_bump: u8, 
+      _badge_supply_counter_bump: u8,
+      asset_id: u8,
+    ) -> ProgramResult {
+      mint_badge_v3(ctx, asset_id)
+    }
+
+    // The Bronze to Diamond badges are the first tiers of the rank ladder
+    pub fn mint_badge_v3(ctx: Context<MintBadgeV4>, asset_id: u8) -> ProgramResult {
+      mint_badge_v4(ctx, sla_accounts::RankLadder::badge_tier(asset_id)?)
+    }
+
     pub fn change_alias(ctx: Context<ChangeAlias>, metadata_uri: String, new_name: String) -> ProgramResult {
+      assert_not_paused(ctx.accounts.config.paused.alias)?;
 
-      let avatar_metadata = ctx.accounts.avatar_metadata.to_account_info();
-      let metadata_program = ctx.accounts.metadata_program.to_account_info();
-      let payer = ctx.accounts.payer.to_account_info();
-      let combine_authority = ctx.accounts.combine_authority.to_account_info();
+      let signer = MetadataSigner::CombineAuthority(ctx.accounts.combine_authority.to_account_info());
+      sla_handlers::change_alias(ctx.accounts.alias_accounts(), signer, metadata_uri, new_name)
+    }
 
-      // Verify that the avatar belongs to the SLA collection
-      msg!("Verifying agent belongs to the right collection");
-      verify_avatar(
-        ctx.accounts.avatar_mint.key(),
-        ctx.accounts.avatar_token.clone(),
-        payer.key(),
-        &avatar_metadata,
+    pub fn change_alias_attested(
+      ctx: Context<ChangeAliasAttested>,
+      metadata_uri: String,
+      new_name: String,
+      nonce: u64,
+      expiry: i64,
+    ) -> ProgramResult {
+      assert_not_paused(ctx.accounts.config.paused.alias)?;
+
+      // Verify the renderer attested the new URI for this agent
+      msg!("Verifying the URI attestation");
+      sla_attestation::verify_attestation(
+        &ctx.accounts.instructions,
+        &ctx.accounts.config.renderer,
+        sla_attestation::AttestedAction::ChangeAlias { new_name: &new_name },
+        &ctx.accounts.avatar_mint.key(),
+        &metadata_uri,
+        nonce,
+        expiry,
       )?;
+      ctx.accounts.avatar_v2.use_nonce(nonce)?;
 
-      // Update the metadata URI through the Metaplex program
-      msg!("Updating agent metadata with new URI");
-      sla_metadata::update_metadata(
-        avatar_metadata, 
-        combine_authority,
-        metadata_program,
-        metadata_uri,
-        Some(new_name),
-      )?;
+      sla_handlers::change_alias(ctx.accounts.alias_accounts(), MetadataSigner::Program, metadata_uri, new_name)
+    }
 
-      // Burn the trait token
-      msg!("Burning ID Card token");
-      sla_token::burn_trait(
-        ctx.accounts.id_card_ata.to_account_info(), 
-        ctx.accounts.id_card_mint.to_account_info(), 
-        payer.clone(), 
-        ctx.accounts.token_program.to_account_info()
-      )?;
+    pub fn scan_agent(ctx: Context<ScanAgent>, metadata_uri: Option<String>) -> ProgramResult {
+      assert_not_paused(ctx.accounts.config.paused.alias)?;
 
-      msg!("Instruction finished");
+      let signer = MetadataSigner::CombineAuthority(ctx.accounts.combine_authority.to_account_info());
+      sla_handlers::scan_agent(ctx.accounts.scan_accounts(), signer, metadata_uri)
+    }
 
-      Ok(())
+    // Scans without a new URI do not need the combine authority nor an attestation
+    pub fn scan_agent_attested(
+      ctx: Context<ScanAgentAttested>,
+      metadata_uri: String,
+      nonce: u64,
+      expiry: i64,
+    ) -> ProgramResult {
+      assert_not_paused(ctx.accounts.config.paused.alias)?;
+
+      // Verify the renderer attested the new URI for this agent
+      msg!("Verifying the URI attestation");
+      sla_attestation::verify_attestation(
+        &ctx.accounts.instructions,
+        &ctx.accounts.config.renderer,
+        sla_attestation::AttestedAction::Scan,
+        &ctx.accounts.avatar_mint.key(),
+        &metadata_uri,
+        nonce,
+        expiry,
+      )?;
+      ctx.accounts.avatar_v2.use_nonce(nonce)?;
+
+      sla_handlers::scan_agent(ctx.accounts.scan_accounts(), MetadataSigner::Program, Some(metadata_uri))
     }
 
-    pub fn scan_agent(ctx: Context<ScanAgent>, metadata_uri: Option<String>) -> ProgramResult {
+    // Scan another agent: the metadata of both agents is left untouched
+    pub fn scan_target(ctx: Context<ScanTarget>) -> ProgramResult {
+      assert_not_paused(ctx.accounts.config.paused.alias)?;
 
-      let avatar_metadata = ctx.accounts.avatar_metadata.to_account_info();
-      let metadata_program = ctx.accounts.metadata_program.to_account_info();
       let user = ctx.accounts.user.to_account_info();
-      let combine_authority = ctx.accounts.combine_authority.to_account_info();
+      let avatar_mint = ctx.accounts.avatar_mint.key();
+      let target_mint = ctx.accounts.target_mint.key();
 
-      // Verify that the avatar belongs to the SLA collection
+      // Verify that the scanning avatar belongs to the SLA collection
       msg!("Verifying agent belongs to the right collection");
       verify_avatar(
-        ctx.accounts.avatar_mint.key(),
+        avatar_mint,
         ctx.accounts.avatar_token.clone(),
         user.key(),
-        &avatar_metadata,
+        &ctx.accounts.avatar_metadata.to_account_info(),
+        &ctx.accounts.config,
       )?;
 
-      // Update the metadata URI through the Metaplex program if needed
-      match metadata_uri {
-        Some(uri) => {
-          msg!("Updating agent metadata with new URI {}", uri);
-          sla_metadata::update_metadata(
-            avatar_metadata, 
-            combine_authority,
-            metadata_program,
-            uri,
-            None,
-          )?;
-        },
-        None => {
-          msg!("No need to update the metadata URI");
-        },
-      };
+      // Verify that the target is another agent of the collection
+      msg!("Verifying target {} belongs to the right collection", target_mint);
+      if target_mint == avatar_mint {
+        return Err(SlaErrors::InvalidScanTarget.into());
+      }
+      let target_metadata = verify_target_avatar(
+        target_mint,
+        &ctx.accounts.target_metadata.to_account_info(),
+        &ctx.accounts.config,
+      )?;
+      let target_uri = target_metadata.data.uri.trim_matches('\0').to_string();
 
       // Burn the trait token
       msg!("Burning Scanning Device token");
@@ -259,159 +464,1181 @@ pub mod sla {
         user.clone(), 
         ctx.accounts.token_program.to_account_info()
       )?;
+
+      // Record the scan in the history of the scanning agent
+      msg!("Recording scan {}", ctx.accounts.agent_profile.scans);
+      let scan_index = ctx.accounts.agent_profile.scans;
+      ctx.accounts.scan_record.record(
+        &mut ctx.accounts.agent_profile,
+        avatar_mint,
+        ctx.accounts.scanner_mint.key(),
+        target_uri.clone(),
+        target_uri,
+        Some(target_mint),
+        &Clock::get()?,
+      );
+
+      emit!(AgentScanned {
+        avatar_mint,
+        user: user.key(),
+        scanner_mint: ctx.accounts.scanner_mint.key(),
+        metadata_uri: None,
+        scan_index,
+        target_mint: Some(target_mint),
+      });
       
       Ok(())
     }
 
+    // Legacy signature: the bumps are ignored and derived on-chain
     pub fn merge_badge_v2(
-      ctx: Context<MergeBadgeV2>, 
-      ranking_v1_bump: u8, 
-      ranking_v2_bump: u8, 
+      ctx: Context<MergeBadgeV4>, 
+      _ranking_v1_bump: u8, 
+      _ranking_v2_bump: u8, 
       asset_id: u8,
       metadata_uri: String,
     ) -> ProgramResult {
+      merge_badge_v3(ctx, asset_id, metadata_uri)
+    }
 
-      let avatar_metadata = ctx.accounts.avatar_metadata.to_account_info();
-      let metadata_program = ctx.accounts.metadata_program.to_account_info();
-      let payer = ctx.accounts.payer.to_account_info();
-      let combine_authority = ctx.accounts.combine_authority.to_account_info();
+    pub fn merge_badge_v3(ctx: Context<MergeBadgeV4>, asset_id: u8, metadata_uri: String) -> ProgramResult {
+      merge_badge_v4(ctx, sla_accounts::RankLadder::badge_tier(asset_id)?, metadata_uri)
+    }
+
+    pub fn merge_badge_attested(
+      ctx: Context<MergeBadgeAttested>,
+      asset_id: u8,
+      metadata_uri: String,
+      nonce: u64,
+      expiry: i64,
+    ) -> ProgramResult {
+      assert_not_paused(ctx.accounts.config.paused.ranking)?;
+
+      // Verify the renderer attested the new URI for this agent
+      msg!("Verifying the URI attestation");
+      sla_attestation::verify_attestation(
+        &ctx.accounts.instructions,
+        &ctx.accounts.config.renderer,
+        sla_attestation::AttestedAction::MergeBadge { asset_id },
+        &ctx.accounts.avatar_mint.key(),
+        &metadata_uri,
+        nonce,
+        expiry,
+      )?;
+      ctx.accounts.avatar_v2.use_nonce(nonce)?;
+
+      let tier = sla_accounts::RankLadder::badge_tier(asset_id)?;
+      sla_handlers::merge_badge(ctx.accounts.badge_merge_accounts(), MetadataSigner::Program, tier, metadata_uri)
+    }
+
+    // Legacy signature: the bumps are ignored and derived on-chain
+    pub fn add_badge(
+      ctx: Context<AddBadge>,
+      _ranking_v1_bump: u8,
+      _ranking_v2_bump: u8,
+      asset_id: u8,
+    ) -> ProgramResult {
+      add_badge_v2(ctx, asset_id)
+    }
+
+    // Approved by `threshold` members of the authority set, passed as signers in the remaining accounts
+    pub fn add_badge_v2(ctx: Context<AddBadge>, asset_id: u8) -> ProgramResult {
+      assert_not_paused(ctx.accounts.config.paused.ranking)?;
+      ctx.accounts.authority_set.check_approvals(ctx.remaining_accounts)?;
+
+      let ranking_v2 = &mut ctx.accounts.ranking_v2;
+      let ranking_v3 = &mut ctx.accounts.ranking_v3;
+      sla_handlers::migrate_if_needed(&ctx.accounts.ranking_v1, ranking_v2, &ctx.accounts.avatar_mint.key())?;
+      ranking_v3.from_v2(ranking_v2);
+      ranking_v3.store_bump(&ctx.accounts.avatar_mint.key());
+
+      // Update the Ranking PDA data (no badge is burned)
+      msg!("Updating the Ranking PDA account");
+      let badge = FungibleAsset::try_from(asset_id)?;
+      let tier = sla_accounts::RankLadder::badge_tier(asset_id)?;
+      ctx.accounts.rank_ladder.upgrade(ranking_v2, tier)?;
+      ranking_v3.record_merge(tier, ctx.accounts.combine_authority.key(), Pubkey::default(), &Clock::get()?)?;
+
+      emit!(BadgeAdded {
+        avatar_mint: ctx.accounts.avatar_mint.key(),
+        authority: ctx.accounts.combine_authority.key(),
+        badge,
+        ranking: ranking_v2.ranking,
+      });
+
+      Ok(())
+    }
+
+    // The five badges of the config become the first tiers of the rank ladder, with their supply
+    pub fn init_rank_ladder(ctx: Context<InitRankLadder>) -> ProgramResult {
+      msg!("Initializing the rank ladder from the config badges");
+      let rank_ladder = &mut ctx.accounts.rank_ladder;
+      let badge_supply_counter = &mut ctx.accounts.badge_supply_counter;
+      rank_ladder.init(&ctx.accounts.config, badge_supply_counter);
+      rank_ladder.bump = find_pda(&[sla_constants::PREFIX_RANK_LADDER.as_bytes()]).1;
+
+      // Badges are only minted from the ladder supply from now on
+      msg!("Freezing the badge supply counter");
+      badge_supply_counter.freeze();
+      Ok(())
+    }
+
+    // Updates a tier of the ladder, or adds one above the current tiers
+    pub fn set_rank_tier(ctx: Context<UpdateRankLadder>, index: u8, params: sla_accounts::RankTierParams) -> ProgramResult {
+      msg!("Setting tier {} of the rank ladder", index);
+      ctx.accounts.rank_ladder.set_tier(index, params)?;
+      Ok(())
+    }
+
+    // Discounts on the ID card and scanner prices for the agents at tier `index`
+    pub fn set_rank_discounts(
+      ctx: Context<UpdateRankLadder>,
+      index: u8,
+      rank_discounts: sla_accounts::RankDiscounts,
+    ) -> ProgramResult {
+      msg!("Setting the discounts of tier {} of the rank ladder", index);
+      ctx.accounts.rank_ladder.set_discounts(index, rank_discounts)?;
+      Ok(())
+    }
+
+    pub fn mint_badge_v4(ctx: Context<MintBadgeV4>, tier: u8) -> ProgramResult {
+      assert_not_paused(ctx.accounts.config.paused.fungible)?;
+
+      let user = ctx.accounts.user.to_account_info();
+      let rank_tier = *ctx.accounts.rank_ladder.tier(tier)?;
+
+      if ctx.accounts.mint.key() != rank_tier.badge_mint {
+        return Err(SlaErrors::InvalidPubkey.into());
+      }
 
       // Verify that the avatar belongs to the SLA collection
       msg!("Verifying agent belongs to the right collection");
       verify_avatar(
         ctx.accounts.avatar_mint.key(),
         ctx.accounts.avatar_token.clone(),
-        payer.key(),
-        &avatar_metadata,
+        user.key(),
+        &ctx.accounts.avatar_metadata.to_account_info(),
+        &ctx.accounts.config,
       )?;
 
-      // Update the metadata URI through the Metaplex program
-      msg!("Updating agent metadata with new URI");
-      sla_metadata::update_metadata(
-        avatar_metadata, 
-        combine_authority,
-        metadata_program,
-        metadata_uri,
-        None,
+      let ranking_v2 = &mut ctx.accounts.ranking_v2;
+      let ranking_v3 = &mut ctx.accounts.ranking_v3;
+      ranking_v3.from_v2(ranking_v2);
+      ranking_v3.store_bump(&ctx.accounts.avatar_mint.key());
+
+      // Check the agent is allowed to mint this badge + take it from the tier supply
+      msg!("Minting the badge + checking the agent is allowed to do so");
+      ctx.accounts.rank_ladder.mint(ranking_v2, tier)?;
+      ranking_v3.record_mint(tier, user.key(), &Clock::get()?)?;
+
+      sla_fungible_token::mint_tier_badge(
+        ctx.accounts.mint.to_account_info(),
+        ctx.accounts.ata.to_account_info(),
+        user.clone(),
+        ctx.accounts.treasury.to_account_info(),
+        ctx.accounts.hay_user_ata.to_account_info(),
+        ctx.accounts.hay_treasury_ata.to_account_info(),
+        ctx.accounts.hay_mint.to_account_info(),
+        ctx.accounts.hay_mint.decimals,
+        ctx.accounts.token_program.to_account_info(),
+        tier,
+        rank_tier.price,
+        ctx.accounts.config.treasury_bump,
       )?;
 
-      // Burn the badge token
-      msg!("Burning Badge token");
-      sla_token::burn_trait(
-        ctx.accounts.badge_ata.to_account_info(), 
-        ctx.accounts.badge_mint.to_account_info(), 
-        payer.clone(), 
-        ctx.accounts.token_program.to_account_info()
+      emit!(TierBadgeMinted {
+        user: user.key(),
+        avatar_mint: ctx.accounts.avatar_mint.key(),
+        mint: ctx.accounts.mint.key(),
+        tier,
+        price: rank_tier.price,
+      });
+
+      Ok(())
+    }
+
+    pub fn merge_badge_v4(ctx: Context<MergeBadgeV4>, tier: u8, metadata_uri: String) -> ProgramResult {
+      assert_not_paused(ctx.accounts.config.paused.ranking)?;
+
+      let signer = MetadataSigner::CombineAuthority(ctx.accounts.combine_authority.to_account_info());
+      sla_handlers::merge_badge(ctx.accounts.badge_merge_accounts(), signer, tier, metadata_uri)
+    }
+
+    // Lowers or clears the ranking of an agent, e.g. when its badges were obtained by cheating
+    // `tier` is the rank ladder tier the agent is lowered to, None clears its rank
+    pub fn revoke_rank(
+      ctx: Context<RevokeRank>,
+      tier: Option<u8>,
+      reason: u8,
+      allow_remint: bool,
+    ) -> ProgramResult {
+      // Revoked rankings stay revoked: `migrate_ranking` does not fold the V1 ranking in again
+      let ranking_v2 = &mut ctx.accounts.ranking_v2;
+      let ranking_v3 = &mut ctx.accounts.ranking_v3;
+      sla_handlers::migrate_if_needed(&ctx.accounts.ranking_v1, ranking_v2, &ctx.accounts.avatar_mint.key())?;
+      ranking_v3.from_v2(ranking_v2);
+      ranking_v3.store_bump(&ctx.accounts.avatar_mint.key());
+
+      msg!("Revoking the ranking of the agent (reason {})", reason);
+      let previous_tier = ranking_v2.tier();
+      ctx.accounts.rank_ladder.revoke(ranking_v2, tier, allow_remint)?;
+      ranking_v3.revoke(tier, allow_remint);
+
+      emit!(RankRevoked {
+        avatar_mint: ctx.accounts.avatar_mint.key(),
+        admin: ctx.accounts.admin.key(),
+        previous_tier,
+        tier,
+        reason,
+        allow_remint,
+      });
+
+      Ok(())
+    }
+
+    // One-time: folds the V1 ranking of the agent into its V2 account and closes the V1 account
+    pub fn migrate_ranking(ctx: Context<MigrateRanking>) -> ProgramResult {
+      assert_not_paused(ctx.accounts.config.paused.ranking)?;
+
+      let payer = ctx.accounts.payer.to_account_info();
+      let avatar_mint = ctx.accounts.avatar_mint.key();
+
+      // Verify that the avatar belongs to the SLA collection
+      msg!("Verifying agent belongs to the right collection");
+      verify_avatar(
+        avatar_mint,
+        ctx.accounts.avatar_token.clone(),
+        payer.key(),
+        &ctx.accounts.avatar_metadata.to_account_info(),
+        &ctx.accounts.config,
       )?;
 
-      // Convert from Ranking V1 to V2
-      msg!("Updating from ranking v1 to v2");
-      let ranking_v1 = &mut ctx.accounts.ranking_v1;
+      // Agents that never minted a badge before V2 have no V1 account
+      let ranking_v1 = &ctx.accounts.ranking_v1;
       let ranking_v2 = &mut ctx.accounts.ranking_v2;
-      ranking_v2.from_v1(ranking_v1);
+      let mut refunded = 0;
+      if let Some(ranking) = load_ranking_v1(ranking_v1)? {
+        // Already folded in by an admin instruction, only the V1 account is left to close
+        if ranking_v2.migrated {
+          msg!("Ranking v1 already folded into v2");
+        } else {
+          msg!("Updating from ranking v1 to v2");
+          ranking_v2.migrate(Some(&ranking), &avatar_mint)?;
+        }
+
+        msg!("Closing the ranking v1 account");
+        refunded = ranking_v1.lamports();
+        close_account(ranking_v1, &payer)?;
+      } else {
+        msg!("No ranking v1 to migrate");
+        ranking_v2.migrate(None, &avatar_mint)?;
+      }
 
-      // Update the Ranking PDA data
-      msg!("Updating the Ranking PDA account");
-      ranking_v2.update_ranking(sla_fungible_token::FungibleAsset::from_u8(asset_id))?;
+      emit!(RankingMigrated {
+        avatar_mint,
+        user: payer.key(),
+        ranking: ranking_v2.ranking,
+        refunded,
+      });
 
-      msg!("Instruction finished");
       Ok(())
     }
 
-    pub fn add_badge(
-      ctx: Context<AddBadge>,
-      ranking_v1_bump: u8,
-      ranking_v2_bump: u8,
-      asset_id: u8,
-    ) -> ProgramResult {
+    // Circuit breakers: each instruction family can be paused independently
+    pub fn set_paused(ctx: Context<UpdateConfig>, paused: sla_accounts::PauseFlags) -> ProgramResult {
+      msg!("Setting the paused instruction families to {}", paused);
+      ctx.accounts.config.paused = paused;
+      Ok(())
+    }
 
-      // Convert from Ranking V1 to V2
-      msg!("Updating from ranking v1 to v2");
-      let ranking_v1 = &mut ctx.accounts.ranking_v1;
-      let ranking_v2 = &mut ctx.accounts.ranking_v2;
-      ranking_v2.from_v1(ranking_v1);
+    pub fn set_renderer(ctx: Context<UpdateConfig>, renderer: Pubkey) -> ProgramResult {
+      msg!("Setting the renderer to {}", renderer);
+      ctx.accounts.config.renderer = renderer;
+      Ok(())
+    }
 
-      // Update the Ranking PDA data
-      msg!("Updating the Ranking PDA account");
-      let ranking_v2 = &mut ctx.accounts.ranking_v2;
-      ranking_v2.update_ranking(sla_fungible_token::FungibleAsset::from_u8(asset_id))?;
+    pub fn set_metadata_base_uri(ctx: Context<UpdateConfig>, base_uri: String) -> ProgramResult {
+      msg!("Setting the metadata base URI to {}", base_uri);
+      ctx.accounts.config.set_metadata_base_uri(base_uri)?;
+      Ok(())
+    }
+
+    pub fn init_badge_supply_counter(
+      ctx: Context<InitBadgeSupplyCounter>,
+      max_bronze: u16, max_silver: u16, max_gold: u16, max_platinum: u16, max_diamond: u16,
+    ) -> ProgramResult {
+      msg!("Initializing the badge supply counter");
+      ctx.accounts.badge_supply_counter.init(max_bronze, max_silver, max_gold, max_platinum, max_diamond);
+      Ok(())
+    }
 
+    // The badge supply counter is frozen by `init_rank_ladder`: the supplies of the badges are the ones of their tier
+    pub fn set_badge_supply(
+      ctx: Context<UpdateRankLadder>,
+      asset_id: u8,
+      max_supply: u16,
+    ) -> ProgramResult {
+      let tier = sla_accounts::RankLadder::badge_tier(asset_id)?;
+      msg!("Setting the max supply of tier {} of the rank ladder to {}", tier, max_supply);
+      ctx.accounts.rank_ladder.set_max_supply(tier, max_supply)?;
       Ok(())
     }
 
-    // pub fn init_badge_supply_counter(
-    //   ctx: Context<InitBadgeSupplyCounter>, badge_supply_counter_bump: u8,
-    //   n_bronze: u16, n_silver: u16, n_gold: u16, n_platinum: u16, n_diamond: u16,
-    // ) -> ProgramResult {
-    //   ctx.accounts.badge_supply_counter.init(n_bronze, n_silver, n_gold, n_platinum, n_diamond);
-    //   Ok(())
-    // }
+    pub fn reset_badge_supply(
+      ctx: Context<UpdateRankLadder>,
+      asset_id: u8,
+    ) -> ProgramResult {
+      let tier = sla_accounts::RankLadder::badge_tier(asset_id)?;
+      msg!("Resetting the minted supply of tier {} of the rank ladder", tier);
+      ctx.accounts.rank_ladder.reset_supply(tier)?;
+      Ok(())
+    }
 }
 
 
 #[derive(Accounts)]
-#[instruction(avatar_bump: u8)]
-pub struct Merge<'info> {
+#[instruction()]
+pub struct InitializeConfig<'info> {
   #[account(
-    init_if_needed,
-    seeds = [sla_constants::PREFIX_LLAMA.as_bytes(), &avatar_mint.key().to_bytes()],
-    bump = avatar_bump,
-    payer = payer, 
-    space = sla_accounts::AvatarAccount::LEN,
+    init,
+    seeds = [sla_constants::PREFIX_CONFIG.as_bytes()],
+    bump,
+    payer = admin,
+    space = sla_accounts::GlobalConfig::LEN,
   )]
-  pub avatar: Account<'info, sla_accounts::AvatarAccount>,
-  
-  pub avatar_mint: Account<'info, anchor_spl::token::Mint>,
-  
+  pub config: Account<'info, sla_accounts::GlobalConfig>,
+
+  // Only the program upgrade authority can create the config
   #[account(mut)]
-  pub trait_mint: Account<'info, anchor_spl::token::Mint>,
+  pub admin: Signer<'info>,
+
+  pub program: Program<'info, crate::program::Sla>,
 
   #[account(
-    associated_token::mint = avatar_mint,
-    associated_token::authority = payer,
+    address = utils::find_program_data_address(&program.key())
+      @ SlaErrors::InvalidPubkey,
+    constraint = program_data.upgrade_authority_address == Some(admin.key())
+      @ SlaErrors::InvalidPubkey
+  )]
+  pub program_data: Account<'info, ProgramData>,
+
+  pub system_program: Program<'info, System>,
+}
+
+
+#[derive(Accounts)]
+#[instruction()]
+pub struct UpdateConfig<'info> {
+  #[account(
+    mut,
+    seeds = [sla_constants::PREFIX_CONFIG.as_bytes()],
+    bump = config.bump,
+    has_one = admin @ SlaErrors::InvalidPubkey,
+  )]
+  pub config: Account<'info, sla_accounts::GlobalConfig>,
+
+  pub admin: Signer<'info>,
+}
+
+
+#[derive(Accounts)]
+#[instruction()]
+pub struct InitAuthoritySet<'info> {
+  #[account(
+    init,
+    seeds = [sla_constants::PREFIX_AUTHORITY_SET.as_bytes()],
+    bump,
+    payer = admin,
+    space = sla_accounts::AuthoritySet::LEN,
+  )]
+  pub authority_set: Account<'info, sla_accounts::AuthoritySet>,
+
+  #[account(
+    seeds = [sla_constants::PREFIX_CONFIG.as_bytes()],
+    bump = config.bump,
+    has_one = admin @ SlaErrors::InvalidPubkey,
+  )]
+  pub config: Box<Account<'info, sla_accounts::GlobalConfig>>,
+
+  #[account(mut)]
+  pub admin: Signer<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+
+#[derive(Accounts)]
+#[instruction()]
+pub struct UpdateAuthoritySet<'info> {
+  #[account(
+    mut,
+    seeds = [sla_constants::PREFIX_AUTHORITY_SET.as_bytes()],
+    bump = authority_set.bump,
+  )]
+  pub authority_set: Account<'info, sla_accounts::AuthoritySet>,
+}
+
+
+#[derive(Accounts)]
+#[instruction()]
+pub struct RotateCombineAuthority<'info> {
+  #[account(
+    seeds = [sla_constants::PREFIX_AUTHORITY_SET.as_bytes()],
+    bump = authority_set.bump,
+  )]
+  pub authority_set: Account<'info, sla_accounts::AuthoritySet>,
+
+  #[account(
+    mut,
+    seeds = [sla_constants::PREFIX_CONFIG.as_bytes()],
+    bump = config.bump,
+  )]
+  pub config: Box<Account<'info, sla_accounts::GlobalConfig>>,
+}
+
+
+#[derive(Accounts)]
+#[instruction()]
+pub struct TransferUpdateAuthority<'info> {
+  #[account(mut)]
+  pub avatar_metadata: AccountInfo<'info>,
+
+  #[account(
+    seeds = [sla_constants::PREFIX_UPDATE_AUTHORITY.as_bytes()],
+    bump = config.metadata_authority_bump,
+  )]
+  pub metadata_authority: AccountInfo<'info>,
+
+  // Current update authority of the avatar
+  #[account(
+    constraint = combine_authority.key() == config.combine_authority @ SlaErrors::InvalidPubkey
+  )]
+  pub combine_authority: Signer<'info>,
+
+  #[account(
+    seeds = [sla_constants::PREFIX_CONFIG.as_bytes()],
+    bump = config.bump,
+  )]
+  pub config: Box<Account<'info, sla_accounts::GlobalConfig>>,
+
+  #[account(address = mpl_token_metadata::ID)]
+  pub metadata_program: AccountInfo<'info>,
+}
+
+
+#[derive(Accounts)]
+#[instruction()]
+pub struct Merge<'info> {
+  // Legacy (V1) Avatar PDA, only read to carry its traits over
+  #[account(
+    constraint = avatar.key() == find_pda(
+      &[sla_constants::PREFIX_LLAMA.as_bytes(), &avatar_mint.key().to_bytes()]
+    ).0 @ SlaErrors::InvalidPubkey
+  )]
+  pub avatar: AccountInfo<'info>,
+
+  #[account(
+    init_if_needed,
+    seeds = [sla_constants::PREFIX_LLAMA_V2.as_bytes(), &avatar_mint.key().to_bytes()],
+    bump,
+    payer = payer, 
+    space = sla_accounts::AvatarAccountV2::LEN,
+  )]
+  pub avatar_v2: Box<Account<'info, sla_accounts::AvatarAccountV2>>,
+  
+  pub avatar_mint: Account<'info, anchor_spl::token::Mint>,
+  
+  #[account(mut)]
+  pub trait_mint: Account<'info, anchor_spl::token::Mint>,
+
+  #[account(
+    associated_token::mint = avatar_mint,
+    associated_token::authority = payer,
+  )]
+  pub avatar_token: Account<'info, anchor_spl::token::TokenAccount>,
+
+  #[account(
+    mut,
+    associated_token::mint = trait_mint,
+    associated_token::authority = payer,
+  )]
+  pub trait_token: Account<'info, anchor_spl::token::TokenAccount>,
+  
+  #[account(mut)]
+  pub avatar_metadata: AccountInfo<'info>,
+
+  #[account(mut)]
+  pub trait_metadata: AccountInfo<'info>,
+
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  #[account(
+    mut,
+    constraint = combine_authority.key() == config.combine_authority @ SlaErrors::InvalidPubkey
+  )]
+  pub combine_authority: Signer<'info>,
+
+  // Update authority of the avatars transferred with `transfer_update_authority`
+  #[account(
+    seeds = [sla_constants::PREFIX_UPDATE_AUTHORITY.as_bytes()],
+    bump = config.metadata_authority_bump,
+  )]
+  pub metadata_authority: AccountInfo<'info>,
+
+  #[account(
+    seeds = [sla_constants::PREFIX_CONFIG.as_bytes()],
+    bump = config.bump,
+  )]
+  pub config: Box<Account<'info, sla_accounts::GlobalConfig>>,
+
+  #[account(address = anchor_spl::token::ID)]
+  pub token_program: AccountInfo<'info>,
+
+  #[account(address = mpl_token_metadata::ID)]
+  pub metadata_program: AccountInfo<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+
+// Same accounts as `Merge`, without the combine authority
+#[derive(Accounts)]
+#[instruction()]
+pub struct MergeV3<'info> {
+  // Legacy (V1) Avatar PDA, only read to carry its traits over
+  #[account(
+    constraint = avatar.key() == find_pda(
+      &[sla_constants::PREFIX_LLAMA.as_bytes(), &avatar_mint.key().to_bytes()]
+    ).0 @ SlaErrors::InvalidPubkey
+  )]
+  pub avatar: AccountInfo<'info>,
+
+  #[account(
+    init_if_needed,
+    seeds = [sla_constants::PREFIX_LLAMA_V2.as_bytes(), &avatar_mint.key().to_bytes()],
+    bump,
+    payer = payer, 
+    space = sla_accounts::AvatarAccountV2::LEN,
+  )]
+  pub avatar_v2: Box<Account<'info, sla_accounts::AvatarAccountV2>>,
+  
+  pub avatar_mint: Account<'info, anchor_spl::token::Mint>,
+  
+  #[account(mut)]
+  pub trait_mint: Account<'info, anchor_spl::token::Mint>,
+
+  #[account(
+    associated_token::mint = avatar_mint,
+    associated_token::authority = payer,
+  )]
+  pub avatar_token: Account<'info, anchor_spl::token::TokenAccount>,
+
+  #[account(
+    mut,
+    associated_token::mint = trait_mint,
+    associated_token::authority = payer,
+  )]
+  pub trait_token: Account<'info, anchor_spl::token::TokenAccount>,
+  
+  #[account(mut)]
+  pub avatar_metadata: AccountInfo<'info>,
+
+  #[account(mut)]
+  pub trait_metadata: AccountInfo<'info>,
+
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  #[account(
+    seeds = [sla_constants::PREFIX_UPDATE_AUTHORITY.as_bytes()],
+    bump = config.metadata_authority_bump,
+  )]
+  pub metadata_authority: AccountInfo<'info>,
+
+  #[account(
+    seeds = [sla_constants::PREFIX_CONFIG.as_bytes()],
+    bump = config.bump,
+  )]
+  pub config: Box<Account<'info, sla_accounts::GlobalConfig>>,
+
+  #[account(address = anchor_spl::token::ID)]
+  pub token_program: AccountInfo<'info>,
+
+  #[account(address = mpl_token_metadata::ID)]
+  pub metadata_program: AccountInfo<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+
+// Same accounts as `MergeV3`, plus the instructions sysvar holding the renderer signature
+#[derive(Accounts)]
+#[instruction()]
+pub struct MergeAttested<'info> {
+  // Legacy (V1) Avatar PDA, only read to carry its traits over
+  #[account(
+    constraint = avatar.key() == find_pda(
+      &[sla_constants::PREFIX_LLAMA.as_bytes(), &avatar_mint.key().to_bytes()]
+    ).0 @ SlaErrors::InvalidPubkey
+  )]
+  pub avatar: AccountInfo<'info>,
+
+  #[account(
+    init_if_needed,
+    seeds = [sla_constants::PREFIX_LLAMA_V2.as_bytes(), &avatar_mint.key().to_bytes()],
+    bump,
+    payer = payer, 
+    space = sla_accounts::AvatarAccountV2::LEN,
+  )]
+  pub avatar_v2: Box<Account<'info, sla_accounts::AvatarAccountV2>>,
+  
+  pub avatar_mint: Account<'info, anchor_spl::token::Mint>,
+  
+  #[account(mut)]
+  pub trait_mint: Account<'info, anchor_spl::token::Mint>,
+
+  #[account(
+    associated_token::mint = avatar_mint,
+    associated_token::authority = payer,
+  )]
+  pub avatar_token: Account<'info, anchor_spl::token::TokenAccount>,
+
+  #[account(
+    mut,
+    associated_token::mint = trait_mint,
+    associated_token::authority = payer,
+  )]
+  pub trait_token: Account<'info, anchor_spl::token::TokenAccount>,
+  
+  #[account(mut)]
+  pub avatar_metadata: AccountInfo<'info>,
+
+  #[account(mut)]
+  pub trait_metadata: AccountInfo<'info>,
+
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  #[account(
+    seeds = [sla_constants::PREFIX_UPDATE_AUTHORITY.as_bytes()],
+    bump = config.metadata_authority_bump,
+  )]
+  pub metadata_authority: AccountInfo<'info>,
+
+  #[account(
+    seeds = [sla_constants::PREFIX_CONFIG.as_bytes()],
+    bump = config.bump,
+  )]
+  pub config: Box<Account<'info, sla_accounts::GlobalConfig>>,
+
+  #[account(address = anchor_spl::token::ID)]
+  pub token_program: AccountInfo<'info>,
+
+  #[account(address = mpl_token_metadata::ID)]
+  pub metadata_program: AccountInfo<'info>,
+
+  #[account(address = solana_program::sysvar::instructions::ID)]
+  pub instructions: AccountInfo<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+
+#[derive(Accounts)]
+#[instruction()]
+pub struct Unmerge<'info> {
+  // Legacy (V1) Avatar PDA, only read to carry its traits over
+  #[account(
+    constraint = avatar.key() == find_pda(
+      &[sla_constants::PREFIX_LLAMA.as_bytes(), &avatar_mint.key().to_bytes()]
+    ).0 @ SlaErrors::InvalidPubkey
+  )]
+  pub avatar: AccountInfo<'info>,
+
+  #[account(
+    init_if_needed,
+    seeds = [sla_constants::PREFIX_LLAMA_V2.as_bytes(), &avatar_mint.key().to_bytes()],
+    bump,
+    payer = payer, 
+    space = sla_accounts::AvatarAccountV2::LEN,
+  )]
+  pub avatar_v2: Box<Account<'info, sla_accounts::AvatarAccountV2>>,
+  
+  pub avatar_mint: Box<Account<'info, anchor_spl::token::Mint>>,
+
+  #[account(
+    associated_token::mint = avatar_mint,
+    associated_token::authority = payer,
+  )]
+  pub avatar_token: Account<'info, anchor_spl::token::TokenAccount>,
+  
+  #[account(mut)]
+  pub avatar_metadata: AccountInfo<'info>,
+
+  #[account(
+    init,
+    payer = payer,
+    mint::decimals = 0,
+    mint::authority = payer,
+  )]
+  pub returned_trait_mint: Box<Account<'info, anchor_spl::token::Mint>>,
+
+  #[account(
+    init,
+    payer = payer,
+    associated_token::mint = returned_trait_mint,
+    associated_token::authority = payer,
+  )]
+  pub returned_trait_token: Box<Account<'info, anchor_spl::token::TokenAccount>>,
+
+  #[account(mut)]
+  pub returned_trait_metadata: AccountInfo<'info>,
+
+  #[account(mut)]
+  pub returned_trait_master_edition: AccountInfo<'info>,
+
+  pub trait_collection_mint: AccountInfo<'info>,
+
+  pub trait_collection_metadata: AccountInfo<'info>,
+
+  pub trait_collection_master_edition: AccountInfo<'info>,
+
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  #[account(
+    mut,
+    constraint = combine_authority.key() == config.combine_authority @ SlaErrors::InvalidPubkey
+  )]
+  pub combine_authority: Signer<'info>,
+
+  // Update authority of the avatars transferred with `transfer_update_authority`
+  #[account(
+    seeds = [sla_constants::PREFIX_UPDATE_AUTHORITY.as_bytes()],
+    bump = config.metadata_authority_bump,
+  )]
+  pub metadata_authority: AccountInfo<'info>,
+
+  #[account(
+    seeds = [sla_constants::PREFIX_CONFIG.as_bytes()],
+    bump = config.bump,
+  )]
+  pub config: Box<Account<'info, sla_accounts::GlobalConfig>>,
+
+  pub rent: Sysvar<'info, Rent>,
+  pub token_program: Program<'info, anchor_spl::token::Token>,
+  pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
+
+  #[account(address = mpl_token_metadata::ID)]
+  pub metadata_program: AccountInfo<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+
+#[derive(Accounts)]
+#[instruction()]
+pub struct SwapTrait<'info> {
+  // Legacy (V1) Avatar PDA, only read to carry its traits over
+  #[account(
+    constraint = avatar.key() == find_pda(
+      &[sla_constants::PREFIX_LLAMA.as_bytes(), &avatar_mint.key().to_bytes()]
+    ).0 @ SlaErrors::InvalidPubkey
+  )]
+  pub avatar: AccountInfo<'info>,
+
+  #[account(
+    init_if_needed,
+    seeds = [sla_constants::PREFIX_LLAMA_V2.as_bytes(), &avatar_mint.key().to_bytes()],
+    bump,
+    payer = payer, 
+    space = sla_accounts::AvatarAccountV2::LEN,
+  )]
+  pub avatar_v2: Box<Account<'info, sla_accounts::AvatarAccountV2>>,
+  
+  pub avatar_mint: Box<Account<'info, anchor_spl::token::Mint>>,
+
+  #[account(
+    associated_token::mint = avatar_mint,
+    associated_token::authority = payer,
+  )]
+  pub avatar_token: Account<'info, anchor_spl::token::TokenAccount>,
+  
+  #[account(mut)]
+  pub avatar_metadata: AccountInfo<'info>,
+
+  #[account(mut)]
+  pub trait_mint: Box<Account<'info, anchor_spl::token::Mint>>,
+
+  #[account(
+    mut,
+    associated_token::mint = trait_mint,
+    associated_token::authority = payer,
+  )]
+  pub trait_token: Account<'info, anchor_spl::token::TokenAccount>,
+
+  #[account(mut)]
+  pub trait_metadata: AccountInfo<'info>,
+
+  #[account(
+    init,
+    payer = payer,
+    mint::decimals = 0,
+    mint::authority = payer,
+  )]
+  pub returned_trait_mint: Box<Account<'info, anchor_spl::token::Mint>>,
+
+  #[account(
+    init,
+    payer = payer,
+    associated_token::mint = returned_trait_mint,
+    associated_token::authority = payer,
+  )]
+  pub returned_trait_token: Box<Account<'info, anchor_spl::token::TokenAccount>>,
+
+  #[account(mut)]
+  pub returned_trait_metadata: AccountInfo<'info>,
+
+  #[account(mut)]
+  pub returned_trait_master_edition: AccountInfo<'info>,
+
+  pub trait_collection_mint: AccountInfo<'info>,
+
+  pub trait_collection_metadata: AccountInfo<'info>,
+
+  pub trait_collection_master_edition: AccountInfo<'info>,
+
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  #[account(
+    mut,
+    constraint = combine_authority.key() == config.combine_authority @ SlaErrors::InvalidPubkey
+  )]
+  pub combine_authority: Signer<'info>,
+
+  // Update authority of the avatars transferred with `transfer_update_authority`
+  #[account(
+    seeds = [sla_constants::PREFIX_UPDATE_AUTHORITY.as_bytes()],
+    bump = config.metadata_authority_bump,
+  )]
+  pub metadata_authority: AccountInfo<'info>,
+
+  #[account(
+    seeds = [sla_constants::PREFIX_CONFIG.as_bytes()],
+    bump = config.bump,
+  )]
+  pub config: Box<Account<'info, sla_accounts::GlobalConfig>>,
+
+  pub rent: Sysvar<'info, Rent>,
+  pub token_program: Program<'info, anchor_spl::token::Token>,
+  pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
+
+  #[account(address = mpl_token_metadata::ID)]
+  pub metadata_program: AccountInfo<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+
+#[derive(Accounts)]
+#[instruction()]
+pub struct MintIdCard<'info> {
+  #[account(
+    mut,
+    constraint = mint.key() == config.id_card_mint @ SlaErrors::InvalidPubkey
+  )]
+  pub mint: Account<'info, anchor_spl::token::Mint>,
+
+  #[account(
+    init_if_needed,
+    payer = user,
+    associated_token::mint = mint,
+    associated_token::authority = user,
+  )]
+  pub ata: Account<'info, anchor_spl::token::TokenAccount>,
+
+  // This is the person who is minting
+  pub user: AccountInfo<'info>,
+
+  // This is the SLA Treasury PDA
+  #[account(
+    seeds = [sla_constants::PREFIX_TREASURY.as_bytes()],
+    bump = config.treasury_bump,
+  )]
+  pub treasury: AccountInfo<'info>,
+
+  #[account(
+    constraint = hay_mint.key() == config.hay_mint @ SlaErrors::InvalidPubkey
+  )]
+  pub hay_mint: Account<'info, anchor_spl::token::Mint>,
+
+  // This is the user's $HAY ATA
+  #[account(
+    mut,
+    associated_token::mint = hay_mint,
+    associated_token::authority = user,
+  )]
+  pub hay_user_ata: Account<'info, anchor_spl::token::TokenAccount>,
+
+  #[account(
+    mut,
+    constraint = hay_treasury_ata.key() == config.hay_treasury_ata @ SlaErrors::InvalidPubkey
+  )]
+  pub hay_treasury_ata: AccountInfo<'info>,
+
+  #[account(
+    seeds = [sla_constants::PREFIX_CONFIG.as_bytes()],
+    bump = config.bump,
+  )]
+  pub config: Box<Account<'info, sla_accounts::GlobalConfig>>,
+
+  pub rent: Sysvar<'info, Rent>,
+  pub token_program: Program<'info, anchor_spl::token::Token>,
+  pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
+  pub system_program: Program<'info, System>,
+}
+
+
+#[derive(Accounts)]
+#[instruction()]
+pub struct MintIdCardV3<'info> {
+  #[account(
+    mut,
+    constraint = mint.key() == config.id_card_mint @ SlaErrors::InvalidPubkey
+  )]
+  pub mint: Account<'info, anchor_spl::token::Mint>,
+
+  #[account(
+    init_if_needed,
+    payer = user,
+    associated_token::mint = mint,
+    associated_token::authority = user,
+  )]
+  pub ata: Account<'info, anchor_spl::token::TokenAccount>,
+
+  // This is the person who is minting
+  pub user: AccountInfo<'info>,
+
+  // Agent of the buyer, its rank sets the discount
+  pub avatar_mint: Box<Account<'info, anchor_spl::token::Mint>>,
+
+  #[account(
+    associated_token::mint = avatar_mint,
+    associated_token::authority = user,
+  )]
+  pub avatar_token: Box<Account<'info, anchor_spl::token::TokenAccount>>,
+
+  pub avatar_metadata: AccountInfo<'info>,
+
+  // May not exist yet, so the bump is derived rather than read from the account
+  #[account(
+    seeds = [sla_constants::PREFIX_RANKING_V2.as_bytes(), &avatar_mint.key().to_bytes()],
+    bump,
+  )]
+  pub ranking_v2: AccountInfo<'info>,
+
+  // Discounts of the tier of the agent, may not exist yet either
+  #[account(
+    seeds = [sla_constants::PREFIX_RANK_LADDER.as_bytes()],
+    bump,
+  )]
+  pub rank_ladder: AccountInfo<'info>,
+
+  // This is the SLA Treasury PDA
+  #[account(
+    seeds = [sla_constants::PREFIX_TREASURY.as_bytes()],
+    bump = config.treasury_bump,
+  )]
+  pub treasury: AccountInfo<'info>,
+
+  #[account(
+    constraint = hay_mint.key() == config.hay_mint @ SlaErrors::InvalidPubkey
+  )]
+  pub hay_mint: Account<'info, anchor_spl::token::Mint>,
+
+  // This is the user's $HAY ATA
+  #[account(
+    mut,
+    associated_token::mint = hay_mint,
+    associated_token::authority = user,
+  )]
+  pub hay_user_ata: Account<'info, anchor_spl::token::TokenAccount>,
+
+  #[account(
+    mut,
+    constraint = hay_treasury_ata.key() == config.hay_treasury_ata @ SlaErrors::InvalidPubkey
+  )]
+  pub hay_treasury_ata: AccountInfo<'info>,
+
+  #[account(
+    seeds = [sla_constants::PREFIX_CONFIG.as_bytes()],
+    bump = config.bump,
+  )]
+  pub config: Box<Account<'info, sla_accounts::GlobalConfig>>,
+
+  pub rent: Sysvar<'info, Rent>,
+  pub token_program: Program<'info, anchor_spl::token::Token>,
+  pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
+  pub system_program: Program<'info, System>,
+}
+
+
+#[derive(Accounts)]
+#[instruction()]
+pub struct MintScanner<'info> {
+  #[account(
+    mut,
+    constraint = mint.key() == config.scanner_mint @ SlaErrors::InvalidPubkey
+  )]
+  pub mint: Box<Account<'info, anchor_spl::token::Mint>>,
+
+  #[account(
+    init_if_needed, 
+    payer = user,
+    associated_token::mint = mint,
+    associated_token::authority = user,
+  )]
+  pub ata: Box<Account<'info, anchor_spl::token::TokenAccount>>,
+
+  // This is the person who is minting
+  pub user: AccountInfo<'info>,
+
+  // Agent the scanner is minted for
+  pub avatar_mint: Box<Account<'info, anchor_spl::token::Mint>>,
+
+  #[account(
+    associated_token::mint = avatar_mint,
+    associated_token::authority = user,
+  )]
+  pub avatar_token: Box<Account<'info, anchor_spl::token::TokenAccount>>,
+
+  pub avatar_metadata: AccountInfo<'info>,
+
+  #[account(
+    init_if_needed,
+    seeds = [sla_constants::PREFIX_SCANNER.as_bytes(), &avatar_mint.key().to_bytes()],
+    bump,
+    payer = user,
+    space = sla_accounts::ScannerRecord::LEN,
+  )]
+  pub scanner_record: Box<Account<'info, sla_accounts::ScannerRecord>>,
+
+  // Rank of the agent for the discount, may not exist yet (so the bump is derived)
+  #[account(
+    seeds = [sla_constants::PREFIX_RANKING_V2.as_bytes(), &avatar_mint.key().to_bytes()],
+    bump,
+  )]
+  pub ranking_v2: AccountInfo<'info>,
+
+  // Discounts of the tier of the agent, may not exist yet either
+  #[account(
+    seeds = [sla_constants::PREFIX_RANK_LADDER.as_bytes()],
+    bump,
   )]
-  pub avatar_token: Account<'info, anchor_spl::token::TokenAccount>,
+  pub rank_ladder: AccountInfo<'info>,
+
+  // This is the SLA Treasury PDA
+  #[account(
+    seeds = [sla_constants::PREFIX_TREASURY.as_bytes()],
+    bump = config.treasury_bump,
+  )]
+  pub treasury: AccountInfo<'info>,
+
+  #[account(
+    constraint = hay_mint.key() == config.hay_mint @ SlaErrors::InvalidPubkey
+  )]
+  pub hay_mint: Box<Account<'info, anchor_spl::token::Mint>>,
 
+  // This is the user's $HAY ATA
   #[account(
     mut,
-    associated_token::mint = trait_mint,
-    associated_token::authority = payer,
+    associated_token::mint = hay_mint,
+    associated_token::authority = user,
   )]
-  pub trait_token: Account<'info, anchor_spl::token::TokenAccount>,
-  
-  #[account(mut)]
-  pub avatar_metadata: AccountInfo<'info>,
+  pub hay_user_ata: Box<Account<'info, anchor_spl::token::TokenAccount>>,
 
-  #[account(mut)]
-  pub trait_metadata: AccountInfo<'info>,
+  #[account(
+    mut,
+    constraint = hay_treasury_ata.key() == config.hay_treasury_ata @ SlaErrors::InvalidPubkey
+  )]
+  pub hay_treasury_ata: AccountInfo<'info>,
+
+  #[account(
+    seeds = [sla_constants::PREFIX_CONFIG.as_bytes()],
+    bump = config.bump,
+  )]
+  pub config: Box<Account<'info, sla_accounts::GlobalConfig>>,
+
+  pub rent: Sysvar<'info, Rent>,
+  pub token_program: Program<'info, anchor_spl::token::Token>,
+  pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
+  pub system_program: Program<'info, System>,
+}
 
-  #[account(mut)]
-  pub payer: Signer<'info>,
 
+#[derive(Accounts)]
+#[instruction()]
+pub struct InitRankLadder<'info> {
+  #[account(
+    init,
+    seeds = [sla_constants::PREFIX_RANK_LADDER.as_bytes()],
+    bump,
+    payer = admin,
+    space = sla_accounts::RankLadder::LEN,
+  )]
+  pub rank_ladder: Box<Account<'info, sla_accounts::RankLadder>>,
+
+  // Frozen once its supplies are copied to the ladder
   #[account(
     mut,
-    constraint = assert_address(combine_authority.key, sla_constants::COMBINE_AUTHORITY_WALLET)
+    seeds = [sla_constants::PREFIX_BADGE_POT.as_bytes()],
+    bump = config.badge_supply_counter_bump,
   )]
-  pub combine_authority: Signer<'info>,
+  pub badge_supply_counter: Account<'info, sla_accounts::BadgeSupplyCounter>,
 
-  #[account(address = anchor_spl::token::ID)]
-  pub token_program: AccountInfo<'info>,
+  #[account(
+    seeds = [sla_constants::PREFIX_CONFIG.as_bytes()],
+    bump = config.bump,
+    has_one = admin @ SlaErrors::InvalidPubkey,
+  )]
+  pub config: Box<Account<'info, sla_accounts::GlobalConfig>>,
 
-  #[account(address = mpl_token_metadata::ID)]
-  pub metadata_program: AccountInfo<'info>,
+  #[account(mut)]
+  pub admin: Signer<'info>,
 
   pub system_program: Program<'info, System>,
 }
 
 
 #[derive(Accounts)]
-#[instruction(treasury_bump: u8, asset_id: u8)]
-pub struct MintIdCard<'info> {
+#[instruction()]
+pub struct UpdateRankLadder<'info> {
   #[account(
     mut,
-    constraint = sla_fungible_token::assert_mint_address(&mint.key(), asset_id) @ SlaErrors::InvalidPubkey
+    seeds = [sla_constants::PREFIX_RANK_LADDER.as_bytes()],
+    bump = rank_ladder.bump,
   )]
-  pub mint: Account<'info, anchor_spl::token::Mint>,
+  pub rank_ladder: Box<Account<'info, sla_accounts::RankLadder>>,
+
+  #[account(
+    seeds = [sla_constants::PREFIX_CONFIG.as_bytes()],
+    bump = config.bump,
+    has_one = admin @ SlaErrors::InvalidPubkey,
+  )]
+  pub config: Box<Account<'info, sla_accounts::GlobalConfig>>,
+
+  pub admin: Signer<'info>,
+}
+
+
+#[derive(Accounts)]
+#[instruction()]
+pub struct MintBadgeV4<'info> {
+  // Checked against the badge of the requested tier in the instruction
+  #[account(mut)]
+  pub mint: Box<Account<'info, anchor_spl::token::Mint>>,
 
   #[account(
     init_if_needed,
@@ -419,23 +1646,22 @@ pub struct MintIdCard<'info> {
     associated_token::mint = mint,
     associated_token::authority = user,
   )]
-  pub ata: Account<'info, anchor_spl::token::TokenAccount>,
+  pub ata: Box<Account<'info, anchor_spl::token::TokenAccount>>,
 
   // This is the person who is minting
-  pub user: AccountInfo<'info>,
+  pub user: Signer<'info>,
 
   // This is the SLA Treasury PDA
   #[account(
     seeds = [sla_constants::PREFIX_TREASURY.as_bytes()],
-    bump = treasury_bump,
+    bump = config.treasury_bump,
   )]
   pub treasury: AccountInfo<'info>,
 
   #[account(
-    constraint = assert_address(&hay_mint.key(), sla_constants::HAY_TOKEN_MINT)
-      @ SlaErrors::InvalidPubkey
+    constraint = hay_mint.key() == config.hay_mint @ SlaErrors::InvalidPubkey
   )]
-  pub hay_mint: Account<'info, anchor_spl::token::Mint>,
+  pub hay_mint: Box<Account<'info, anchor_spl::token::Mint>>,
 
   // This is the user's $HAY ATA
   #[account(
@@ -443,15 +1669,55 @@ pub struct MintIdCard<'info> {
     associated_token::mint = hay_mint,
     associated_token::authority = user,
   )]
-  pub hay_user_ata: Account<'info, anchor_spl::token::TokenAccount>,
+  pub hay_user_ata: Box<Account<'info, anchor_spl::token::TokenAccount>>,
 
   #[account(
     mut,
-    constraint = assert_address(hay_treasury_ata.key, sla_constants::HAY_TREASURY_WALLET_ATA)
-      @ SlaErrors::InvalidPubkey
+    constraint = hay_treasury_ata.key() == config.hay_treasury_ata @ SlaErrors::InvalidPubkey
   )]
   pub hay_treasury_ata: AccountInfo<'info>,
 
+  #[account(
+    seeds = [sla_constants::PREFIX_CONFIG.as_bytes()],
+    bump = config.bump,
+  )]
+  pub config: Box<Account<'info, sla_accounts::GlobalConfig>>,
+  
+  pub avatar_mint: Box<Account<'info, anchor_spl::token::Mint>>,
+
+  #[account(
+    associated_token::mint = avatar_mint,
+    associated_token::authority = user,
+  )]
+  pub avatar_token: Account<'info, anchor_spl::token::TokenAccount>,
+
+  #[account(mut)]
+  pub avatar_metadata: AccountInfo<'info>,
+
+  #[account(
+    mut,
+    constraint = ranking_v2.migrated @ SlaErrors::RankingNotMigrated,
+    seeds = [sla_constants::PREFIX_RANKING_V2.as_bytes(), &avatar_mint.key().to_bytes()],
+    bump = ranking_v2.bump,
+  )]
+  pub ranking_v2: Box<Account<'info, sla_accounts::RankingV2>>,
+
+  #[account(
+    init_if_needed,
+    seeds = [sla_constants::PREFIX_RANKING_V3.as_bytes(), &avatar_mint.key().to_bytes()],
+    bump,
+    payer = user, 
+    space = sla_accounts::RankingV3::LEN,
+  )]
+  pub ranking_v3: Box<Account<'info, sla_accounts::RankingV3>>,
+
+  #[account(
+    mut,
+    seeds = [sla_constants::PREFIX_RANK_LADDER.as_bytes()],
+    bump = rank_ladder.bump,
+  )]
+  pub rank_ladder: Box<Account<'info, sla_accounts::RankLadder>>,
+
   pub rent: Sysvar<'info, Rent>,
   pub token_program: Program<'info, anchor_spl::token::Token>,
   pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
@@ -460,166 +1726,346 @@ pub struct MintIdCard<'info> {
 
 
 #[derive(Accounts)]
-#[instruction(treasury_bump: u8)]
-pub struct MintScanner<'info> {
+#[instruction()]
+pub struct MergeBadgeV4<'info> {  
+  pub avatar_mint: Box<Account<'info, anchor_spl::token::Mint>>,
+
+  #[account(
+    associated_token::mint = avatar_mint,
+    associated_token::authority = payer,
+  )]
+  pub avatar_token: Account<'info, anchor_spl::token::TokenAccount>,
+
+  #[account(mut)]
+  pub avatar_metadata: AccountInfo<'info>,
+
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  // Checked against the badge of the merged tier in the instruction
+  #[account(mut)]
+  pub badge_mint: Box<Account<'info, anchor_spl::token::Mint>>,
+
+  #[account(
+    mut,
+    associated_token::mint = badge_mint,
+    associated_token::authority = payer,
+  )]
+  pub badge_ata: Box<Account<'info, anchor_spl::token::TokenAccount>>,
+
   #[account(
     mut,
-    constraint = assert_address(&mint.key(), sla_constants::SCANNER_MINT) @ SlaErrors::InvalidPubkey
+    constraint = ranking_v2.migrated @ SlaErrors::RankingNotMigrated,
+    seeds = [sla_constants::PREFIX_RANKING_V2.as_bytes(), &avatar_mint.key().to_bytes()],
+    bump = ranking_v2.bump,
   )]
-  pub mint: Box<Account<'info, anchor_spl::token::Mint>>,
+  pub ranking_v2: Box<Account<'info, sla_accounts::RankingV2>>,
 
   #[account(
-    init_if_needed, 
-    payer = user,
-    associated_token::mint = mint,
-    associated_token::authority = user,
+    init_if_needed,
+    seeds = [sla_constants::PREFIX_RANKING_V3.as_bytes(), &avatar_mint.key().to_bytes()],
+    bump,
+    payer = payer, 
+    space = sla_accounts::RankingV3::LEN,
   )]
-  pub ata: Box<Account<'info, anchor_spl::token::TokenAccount>>,
+  pub ranking_v3: Box<Account<'info, sla_accounts::RankingV3>>,
 
-  // This is the person who is minting
-  pub user: AccountInfo<'info>,
+  #[account(
+    seeds = [sla_constants::PREFIX_RANK_LADDER.as_bytes()],
+    bump = rank_ladder.bump,
+  )]
+  pub rank_ladder: Box<Account<'info, sla_accounts::RankLadder>>,
 
-  // This is the SLA Treasury PDA
   #[account(
-    seeds = [sla_constants::PREFIX_TREASURY.as_bytes()],
-    bump = treasury_bump,
+    mut,
+    constraint = combine_authority.key() == config.combine_authority @ SlaErrors::InvalidPubkey
   )]
-  pub treasury: AccountInfo<'info>,
+  pub combine_authority: Signer<'info>,
 
+  // Update authority of the avatars transferred with `transfer_update_authority`
   #[account(
-    constraint = assert_address(&hay_mint.key(), sla_constants::HAY_TOKEN_MINT)
-      @ SlaErrors::InvalidPubkey
+    seeds = [sla_constants::PREFIX_UPDATE_AUTHORITY.as_bytes()],
+    bump = config.metadata_authority_bump,
   )]
-  pub hay_mint: Box<Account<'info, anchor_spl::token::Mint>>,
+  pub metadata_authority: AccountInfo<'info>,
+
+  #[account(
+    seeds = [sla_constants::PREFIX_CONFIG.as_bytes()],
+    bump = config.bump,
+  )]
+  pub config: Box<Account<'info, sla_accounts::GlobalConfig>>,
+
+  #[account(address = anchor_spl::token::ID)]
+  pub token_program: AccountInfo<'info>,
+
+  #[account(address = mpl_token_metadata::ID)]
+  pub metadata_program: AccountInfo<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+
+#[derive(Accounts)]
+#[instruction(metadata_uri: String, new_name: String)]
+pub struct ChangeAlias<'info> {  
+  pub avatar_mint: Account<'info, anchor_spl::token::Mint>,
+
+  #[account(
+    associated_token::mint = avatar_mint,
+    associated_token::authority = payer,
+  )]
+  pub avatar_token: Account<'info, anchor_spl::token::TokenAccount>,
+  
+  #[account(mut)]
+  pub avatar_metadata: AccountInfo<'info>,
 
-  // This is the user's $HAY ATA
   #[account(
     mut,
-    associated_token::mint = hay_mint,
-    associated_token::authority = user,
+    constraint = id_card_mint.key() == config.id_card_mint @ SlaErrors::InvalidPubkey
   )]
-  pub hay_user_ata: Box<Account<'info, anchor_spl::token::TokenAccount>>,
+  pub id_card_mint: Account<'info, anchor_spl::token::Mint>,
 
   #[account(
     mut,
-    constraint = assert_address(hay_treasury_ata.key, sla_constants::HAY_TREASURY_WALLET_ATA)
-      @ SlaErrors::InvalidPubkey
+    associated_token::mint = id_card_mint,
+    associated_token::authority = payer,
   )]
-  pub hay_treasury_ata: AccountInfo<'info>,
+  pub id_card_ata: Account<'info, anchor_spl::token::TokenAccount>,
+
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  #[account(
+    init_if_needed,
+    seeds = [sla_constants::PREFIX_ALIAS.as_bytes(), &sla_accounts::AliasRecord::seed(&new_name)],
+    bump,
+    payer = payer, 
+    space = sla_accounts::AliasRecord::LEN,
+  )]
+  pub alias_record: Box<Account<'info, sla_accounts::AliasRecord>>,
+
+  // Record of the current alias of the agent, closed when the alias changes (unused otherwise)
+  #[account(mut)]
+  pub previous_alias_record: AccountInfo<'info>,
+
+  #[account(
+    init_if_needed,
+    seeds = [sla_constants::PREFIX_PROFILE.as_bytes(), &avatar_mint.key().to_bytes()],
+    bump,
+    payer = payer, 
+    space = sla_accounts::AgentProfile::LEN,
+  )]
+  pub agent_profile: Box<Account<'info, sla_accounts::AgentProfile>>,
+
+  #[account(
+    mut,
+    constraint = combine_authority.key() == config.combine_authority @ SlaErrors::InvalidPubkey
+  )]
+  pub combine_authority: Signer<'info>,
+
+  // Update authority of the avatars transferred with `transfer_update_authority`
+  #[account(
+    seeds = [sla_constants::PREFIX_UPDATE_AUTHORITY.as_bytes()],
+    bump = config.metadata_authority_bump,
+  )]
+  pub metadata_authority: AccountInfo<'info>,
+
+  #[account(
+    seeds = [sla_constants::PREFIX_CONFIG.as_bytes()],
+    bump = config.bump,
+  )]
+  pub config: Box<Account<'info, sla_accounts::GlobalConfig>>,
+
+  #[account(address = anchor_spl::token::ID)]
+  pub token_program: AccountInfo<'info>,
+
+  #[account(address = mpl_token_metadata::ID)]
+  pub metadata_program: AccountInfo<'info>,
 
-  pub rent: Sysvar<'info, Rent>,
-  pub token_program: Program<'info, anchor_spl::token::Token>,
-  pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
   pub system_program: Program<'info, System>,
 }
 
 
+// Same accounts as `ChangeAlias` without the combine authority, plus the agent PDA storing the attestation nonce
 #[derive(Accounts)]
-#[instruction(
-  treasury_bump: u8, 
-  ranking_v1_bump: u8, 
-  ranking_v2_bump: u8,
-  badge_supply_counter_bump: u8,
-  asset_id: u8,
-)]
-pub struct MintBadgeV2<'info> {
+#[instruction(metadata_uri: String, new_name: String)]
+pub struct ChangeAliasAttested<'info> {  
+  pub avatar_mint: Account<'info, anchor_spl::token::Mint>,
+
+  #[account(
+    associated_token::mint = avatar_mint,
+    associated_token::authority = payer,
+  )]
+  pub avatar_token: Account<'info, anchor_spl::token::TokenAccount>,
+  
+  #[account(mut)]
+  pub avatar_metadata: AccountInfo<'info>,
+
   #[account(
     mut,
-    constraint = sla_fungible_token::assert_mint_address(&mint.key(), asset_id) 
-      @ SlaErrors::InvalidPubkey
+    constraint = id_card_mint.key() == config.id_card_mint @ SlaErrors::InvalidPubkey
   )]
-  pub mint: Box<Account<'info, anchor_spl::token::Mint>>,
+  pub id_card_mint: Account<'info, anchor_spl::token::Mint>,
+
+  #[account(
+    mut,
+    associated_token::mint = id_card_mint,
+    associated_token::authority = payer,
+  )]
+  pub id_card_ata: Account<'info, anchor_spl::token::TokenAccount>,
+
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  #[account(
+    init_if_needed,
+    seeds = [sla_constants::PREFIX_ALIAS.as_bytes(), &sla_accounts::AliasRecord::seed(&new_name)],
+    bump,
+    payer = payer, 
+    space = sla_accounts::AliasRecord::LEN,
+  )]
+  pub alias_record: Box<Account<'info, sla_accounts::AliasRecord>>,
+
+  // Record of the current alias of the agent, closed when the alias changes (unused otherwise)
+  #[account(mut)]
+  pub previous_alias_record: AccountInfo<'info>,
 
   #[account(
     init_if_needed,
-    payer = user,
-    associated_token::mint = mint,
-    associated_token::authority = user,
+    seeds = [sla_constants::PREFIX_PROFILE.as_bytes(), &avatar_mint.key().to_bytes()],
+    bump,
+    payer = payer, 
+    space = sla_accounts::AgentProfile::LEN,
   )]
-  pub ata: Box<Account<'info, anchor_spl::token::TokenAccount>>,
+  pub agent_profile: Box<Account<'info, sla_accounts::AgentProfile>>,
 
-  // This is the person who is minting
-  pub user: Signer<'info>,
+  #[account(
+    init_if_needed,
+    seeds = [sla_constants::PREFIX_LLAMA_V2.as_bytes(), &avatar_mint.key().to_bytes()],
+    bump,
+    payer = payer, 
+    space = sla_accounts::AvatarAccountV2::LEN,
+  )]
+  pub avatar_v2: Box<Account<'info, sla_accounts::AvatarAccountV2>>,
 
-  // This is the SLA Treasury PDA
   #[account(
-    seeds = [sla_constants::PREFIX_TREASURY.as_bytes()],
-    bump = treasury_bump,
+    seeds = [sla_constants::PREFIX_UPDATE_AUTHORITY.as_bytes()],
+    bump = config.metadata_authority_bump,
   )]
-  pub treasury: AccountInfo<'info>,
+  pub metadata_authority: AccountInfo<'info>,
 
   #[account(
-    constraint = assert_address(&hay_mint.key(), sla_constants::HAY_TOKEN_MINT)
-      @ SlaErrors::InvalidPubkey
+    seeds = [sla_constants::PREFIX_CONFIG.as_bytes()],
+    bump = config.bump,
   )]
-  pub hay_mint: Box<Account<'info, anchor_spl::token::Mint>>,
+  pub config: Box<Account<'info, sla_accounts::GlobalConfig>>,
+
+  #[account(address = anchor_spl::token::ID)]
+  pub token_program: AccountInfo<'info>,
+
+  #[account(address = mpl_token_metadata::ID)]
+  pub metadata_program: AccountInfo<'info>,
+
+  #[account(address = solana_program::sysvar::instructions::ID)]
+  pub instructions: AccountInfo<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+
+#[derive(Accounts)]
+#[instruction()]
+pub struct ScanAgent<'info> {
+  
+  pub avatar_mint: Account<'info, anchor_spl::token::Mint>,
 
-  // This is the user's $HAY ATA
   #[account(
-    mut,
-    associated_token::mint = hay_mint,
+    associated_token::mint = avatar_mint,
     associated_token::authority = user,
   )]
-  pub hay_user_ata: Box<Account<'info, anchor_spl::token::TokenAccount>>,
+  pub avatar_token: Account<'info, anchor_spl::token::TokenAccount>,
+  
+  #[account(mut)]
+  pub avatar_metadata: AccountInfo<'info>,
 
   #[account(
     mut,
-    constraint = assert_address(hay_treasury_ata.key, sla_constants::HAY_TREASURY_WALLET_ATA)
-      @ SlaErrors::InvalidPubkey
+    constraint = scanner_mint.key() == config.scanner_mint @ SlaErrors::InvalidPubkey
   )]
-  pub hay_treasury_ata: AccountInfo<'info>,
-  
-  pub avatar_mint: Box<Account<'info, anchor_spl::token::Mint>>,
+  pub scanner_mint: Account<'info, anchor_spl::token::Mint>,
 
   #[account(
-    associated_token::mint = avatar_mint,
+    mut,
+    associated_token::mint = scanner_mint,
     associated_token::authority = user,
   )]
-  pub avatar_token: Account<'info, anchor_spl::token::TokenAccount>,
+  pub scanner_ata: Account<'info, anchor_spl::token::TokenAccount>,
 
   #[account(mut)]
-  pub avatar_metadata: AccountInfo<'info>,
+  pub user: Signer<'info>,
 
   #[account(
     init_if_needed,
-    seeds = [sla_constants::PREFIX_RANKING.as_bytes(), &avatar_mint.key().to_bytes()],
-    bump = ranking_v1_bump,
+    seeds = [sla_constants::PREFIX_PROFILE.as_bytes(), &avatar_mint.key().to_bytes()],
+    bump,
     payer = user, 
-    space = sla_accounts::Ranking::LEN,
+    space = sla_accounts::AgentProfile::LEN,
   )]
-  pub ranking_v1: Box<Account<'info, sla_accounts::Ranking>>,
+  pub agent_profile: Box<Account<'info, sla_accounts::AgentProfile>>,
 
+  // Record of this scan, at the next scan index of the agent
   #[account(
-    init_if_needed,
-    seeds = [sla_constants::PREFIX_RANKING_V2.as_bytes(), &avatar_mint.key().to_bytes()],
-    bump = ranking_v2_bump,
+    init,
+    seeds = [
+      sla_constants::PREFIX_SCAN.as_bytes(),
+      &avatar_mint.key().to_bytes(),
+      &sla_accounts::ScanRecord::seed(agent_profile.scans),
+    ],
+    bump,
     payer = user, 
-    space = sla_accounts::RankingV2::LEN,
+    space = sla_accounts::ScanRecord::LEN,
   )]
-  pub ranking_v2: Box<Account<'info, sla_accounts::RankingV2>>,
+  pub scan_record: Box<Account<'info, sla_accounts::ScanRecord>>,
 
   #[account(
     mut,
-    seeds = [sla_constants::PREFIX_BADGE_POT.as_bytes()],
-    bump = badge_supply_counter_bump,
+    constraint = combine_authority.key() == config.combine_authority @ SlaErrors::InvalidPubkey
   )]
-  pub badge_supply_counter: Account<'info, sla_accounts::BadgeSupplyCounter>,
+  pub combine_authority: Signer<'info>,
+
+  // Update authority of the avatars transferred with `transfer_update_authority`
+  #[account(
+    seeds = [sla_constants::PREFIX_UPDATE_AUTHORITY.as_bytes()],
+    bump = config.metadata_authority_bump,
+  )]
+  pub metadata_authority: AccountInfo<'info>,
+
+  #[account(
+    seeds = [sla_constants::PREFIX_CONFIG.as_bytes()],
+    bump = config.bump,
+  )]
+  pub config: Box<Account<'info, sla_accounts::GlobalConfig>>,
+
+  #[account(address = anchor_spl::token::ID)]
+  pub token_program: AccountInfo<'info>,
+
+  #[account(address = mpl_token_metadata::ID)]
+  pub metadata_program: AccountInfo<'info>,
 
-  pub rent: Sysvar<'info, Rent>,
-  pub token_program: Program<'info, anchor_spl::token::Token>,
-  pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
   pub system_program: Program<'info, System>,
 }
 
 
+// Same accounts as `ScanAgent` without the combine authority, plus the agent PDA storing the attestation nonce
 #[derive(Accounts)]
 #[instruction()]
-pub struct ChangeAlias<'info> {  
+pub struct ScanAgentAttested<'info> {
+  
   pub avatar_mint: Account<'info, anchor_spl::token::Mint>,
 
   #[account(
     associated_token::mint = avatar_mint,
-    associated_token::authority = payer,
+    associated_token::authority = user,
   )]
   pub avatar_token: Account<'info, anchor_spl::token::TokenAccount>,
   
@@ -628,26 +2074,63 @@ pub struct ChangeAlias<'info> {
 
   #[account(
     mut,
-    constraint = assert_address(&id_card_mint.key(), sla_constants::ID_CARD_MINT)
-      @ SlaErrors::InvalidPubkey
+    constraint = scanner_mint.key() == config.scanner_mint @ SlaErrors::InvalidPubkey
   )]
-  pub id_card_mint: Account<'info, anchor_spl::token::Mint>,
+  pub scanner_mint: Account<'info, anchor_spl::token::Mint>,
 
   #[account(
     mut,
-    associated_token::mint = id_card_mint,
-    associated_token::authority = payer,
+    associated_token::mint = scanner_mint,
+    associated_token::authority = user,
   )]
-  pub id_card_ata: Account<'info, anchor_spl::token::TokenAccount>,
+  pub scanner_ata: Account<'info, anchor_spl::token::TokenAccount>,
 
   #[account(mut)]
-  pub payer: Signer<'info>,
+  pub user: Signer<'info>,
 
   #[account(
-    mut,
-    constraint = assert_address(combine_authority.key, sla_constants::COMBINE_AUTHORITY_WALLET)
+    init_if_needed,
+    seeds = [sla_constants::PREFIX_PROFILE.as_bytes(), &avatar_mint.key().to_bytes()],
+    bump,
+    payer = user, 
+    space = sla_accounts::AgentProfile::LEN,
   )]
-  pub combine_authority: Signer<'info>,
+  pub agent_profile: Box<Account<'info, sla_accounts::AgentProfile>>,
+
+  // Record of this scan, at the next scan index of the agent
+  #[account(
+    init,
+    seeds = [
+      sla_constants::PREFIX_SCAN.as_bytes(),
+      &avatar_mint.key().to_bytes(),
+      &sla_accounts::ScanRecord::seed(agent_profile.scans),
+    ],
+    bump,
+    payer = user, 
+    space = sla_accounts::ScanRecord::LEN,
+  )]
+  pub scan_record: Box<Account<'info, sla_accounts::ScanRecord>>,
+
+  #[account(
+    init_if_needed,
+    seeds = [sla_constants::PREFIX_LLAMA_V2.as_bytes(), &avatar_mint.key().to_bytes()],
+    bump,
+    payer = user, 
+    space = sla_accounts::AvatarAccountV2::LEN,
+  )]
+  pub avatar_v2: Box<Account<'info, sla_accounts::AvatarAccountV2>>,
+
+  #[account(
+    seeds = [sla_constants::PREFIX_UPDATE_AUTHORITY.as_bytes()],
+    bump = config.metadata_authority_bump,
+  )]
+  pub metadata_authority: AccountInfo<'info>,
+
+  #[account(
+    seeds = [sla_constants::PREFIX_CONFIG.as_bytes()],
+    bump = config.bump,
+  )]
+  pub config: Box<Account<'info, sla_accounts::GlobalConfig>>,
 
   #[account(address = anchor_spl::token::ID)]
   pub token_program: AccountInfo<'info>,
@@ -655,13 +2138,17 @@ pub struct ChangeAlias<'info> {
   #[account(address = mpl_token_metadata::ID)]
   pub metadata_program: AccountInfo<'info>,
 
+  #[account(address = solana_program::sysvar::instructions::ID)]
+  pub instructions: AccountInfo<'info>,
+
   pub system_program: Program<'info, System>,
 }
 
 
+// Same accounts as `ScanAgent` without the metadata update, plus the read-only target agent
 #[derive(Accounts)]
 #[instruction()]
-pub struct ScanAgent<'info> {
+pub struct ScanTarget<'info> {
   
   pub avatar_mint: Account<'info, anchor_spl::token::Mint>,
 
@@ -671,13 +2158,16 @@ pub struct ScanAgent<'info> {
   )]
   pub avatar_token: Account<'info, anchor_spl::token::TokenAccount>,
   
-  #[account(mut)]
   pub avatar_metadata: AccountInfo<'info>,
 
+  pub target_mint: Account<'info, anchor_spl::token::Mint>,
+
+  #[account(owner = mpl_token_metadata::ID)]
+  pub target_metadata: AccountInfo<'info>,
+
   #[account(
     mut,
-    constraint = assert_address(&scanner_mint.key(), sla_constants::SCANNER_MINT)
-      @ SlaErrors::InvalidPubkey
+    constraint = scanner_mint.key() == config.scanner_mint @ SlaErrors::InvalidPubkey
   )]
   pub scanner_mint: Account<'info, anchor_spl::token::Mint>,
 
@@ -692,28 +2182,45 @@ pub struct ScanAgent<'info> {
   pub user: Signer<'info>,
 
   #[account(
-    mut,
-    constraint = assert_address(combine_authority.key, sla_constants::COMBINE_AUTHORITY_WALLET)
+    init_if_needed,
+    seeds = [sla_constants::PREFIX_PROFILE.as_bytes(), &avatar_mint.key().to_bytes()],
+    bump,
+    payer = user, 
+    space = sla_accounts::AgentProfile::LEN,
   )]
-  pub combine_authority: Signer<'info>,
+  pub agent_profile: Box<Account<'info, sla_accounts::AgentProfile>>,
+
+  // Record of this scan, at the next scan index of the scanning agent
+  #[account(
+    init,
+    seeds = [
+      sla_constants::PREFIX_SCAN.as_bytes(),
+      &avatar_mint.key().to_bytes(),
+      &sla_accounts::ScanRecord::seed(agent_profile.scans),
+    ],
+    bump,
+    payer = user, 
+    space = sla_accounts::ScanRecord::LEN,
+  )]
+  pub scan_record: Box<Account<'info, sla_accounts::ScanRecord>>,
+
+  #[account(
+    seeds = [sla_constants::PREFIX_CONFIG.as_bytes()],
+    bump = config.bump,
+  )]
+  pub config: Box<Account<'info, sla_accounts::GlobalConfig>>,
 
   #[account(address = anchor_spl::token::ID)]
   pub token_program: AccountInfo<'info>,
 
-  #[account(address = mpl_token_metadata::ID)]
-  pub metadata_program: AccountInfo<'info>,
-
   pub system_program: Program<'info, System>,
 }
 
 
+// Same accounts as `MergeBadgeV4` without the combine authority, plus the agent PDA storing the attestation nonce
 #[derive(Accounts)]
-#[instruction(
-  ranking_v1_bump: u8,
-  ranking_v2_bump: u8,
-  asset_id: u8,
-)]
-pub struct MergeBadgeV2<'info> {  
+#[instruction()]
+pub struct MergeBadgeAttested<'info> {  
   pub avatar_mint: Box<Account<'info, anchor_spl::token::Mint>>,
 
   #[account(
@@ -728,11 +2235,8 @@ pub struct MergeBadgeV2<'info> {
   #[account(mut)]
   pub payer: Signer<'info>,
 
-  #[account(
-    mut,
-    constraint = sla_fungible_token::assert_mint_address(&badge_mint.key(), asset_id) 
-      @ SlaErrors::InvalidPubkey
-  )]
+  // Checked against the merged badge in the instruction
+  #[account(mut)]
   pub badge_mint: Box<Account<'info, anchor_spl::token::Mint>>,
 
   #[account(
@@ -742,29 +2246,49 @@ pub struct MergeBadgeV2<'info> {
   )]
   pub badge_ata: Box<Account<'info, anchor_spl::token::TokenAccount>>,
 
+  #[account(
+    mut,
+    constraint = ranking_v2.migrated @ SlaErrors::RankingNotMigrated,
+    seeds = [sla_constants::PREFIX_RANKING_V2.as_bytes(), &avatar_mint.key().to_bytes()],
+    bump = ranking_v2.bump,
+  )]
+  pub ranking_v2: Box<Account<'info, sla_accounts::RankingV2>>,
+
   #[account(
     init_if_needed,
-    seeds = [sla_constants::PREFIX_RANKING.as_bytes(), &avatar_mint.key().to_bytes()],
-    bump = ranking_v1_bump,
+    seeds = [sla_constants::PREFIX_RANKING_V3.as_bytes(), &avatar_mint.key().to_bytes()],
+    bump,
     payer = payer, 
-    space = sla_accounts::Ranking::LEN,
+    space = sla_accounts::RankingV3::LEN,
+  )]
+  pub ranking_v3: Box<Account<'info, sla_accounts::RankingV3>>,
+
+  #[account(
+    seeds = [sla_constants::PREFIX_RANK_LADDER.as_bytes()],
+    bump = rank_ladder.bump,
   )]
-  pub ranking_v1: Box<Account<'info, sla_accounts::Ranking>>,
+  pub rank_ladder: Box<Account<'info, sla_accounts::RankLadder>>,
 
   #[account(
     init_if_needed,
-    seeds = [sla_constants::PREFIX_RANKING_V2.as_bytes(), &avatar_mint.key().to_bytes()],
-    bump = ranking_v2_bump,
+    seeds = [sla_constants::PREFIX_LLAMA_V2.as_bytes(), &avatar_mint.key().to_bytes()],
+    bump,
     payer = payer, 
-    space = sla_accounts::RankingV2::LEN,
+    space = sla_accounts::AvatarAccountV2::LEN,
   )]
-  pub ranking_v2: Box<Account<'info, sla_accounts::RankingV2>>,
+  pub avatar_v2: Box<Account<'info, sla_accounts::AvatarAccountV2>>,
 
   #[account(
-    mut,
-    constraint = assert_address(combine_authority.key, sla_constants::COMBINE_AUTHORITY_WALLET)
+    seeds = [sla_constants::PREFIX_UPDATE_AUTHORITY.as_bytes()],
+    bump = config.metadata_authority_bump,
   )]
-  pub combine_authority: Signer<'info>,
+  pub metadata_authority: AccountInfo<'info>,
+
+  #[account(
+    seeds = [sla_constants::PREFIX_CONFIG.as_bytes()],
+    bump = config.bump,
+  )]
+  pub config: Box<Account<'info, sla_accounts::GlobalConfig>>,
 
   #[account(address = anchor_spl::token::ID)]
   pub token_program: AccountInfo<'info>,
@@ -772,43 +2296,68 @@ pub struct MergeBadgeV2<'info> {
   #[account(address = mpl_token_metadata::ID)]
   pub metadata_program: AccountInfo<'info>,
 
+  #[account(address = solana_program::sysvar::instructions::ID)]
+  pub instructions: AccountInfo<'info>,
+
   pub system_program: Program<'info, System>,
 }
 
 
 #[derive(Accounts)]
-#[instruction(
-  ranking_v1_bump: u8,
-  ranking_v2_bump: u8,
-  asset_id: u8,
-)]
+#[instruction()]
 pub struct AddBadge<'info> {  
   pub avatar_mint: Box<Account<'info, anchor_spl::token::Mint>>,
 
+  // Folded in if the ranking is not migrated yet, may not exist
   #[account(
-    init_if_needed,
     seeds = [sla_constants::PREFIX_RANKING.as_bytes(), &avatar_mint.key().to_bytes()],
-    bump = ranking_v1_bump,
-    payer = combine_authority, 
-    space = sla_accounts::Ranking::LEN,
+    bump,
   )]
-  pub ranking_v1: Box<Account<'info, sla_accounts::Ranking>>,
+  pub ranking_v1: AccountInfo<'info>,
 
+  // The holder may not have run `migrate_ranking` (or minted any badge), so the bump is derived
   #[account(
     init_if_needed,
     seeds = [sla_constants::PREFIX_RANKING_V2.as_bytes(), &avatar_mint.key().to_bytes()],
-    bump = ranking_v2_bump,
-    payer = combine_authority, 
+    bump,
+    payer = combine_authority,
     space = sla_accounts::RankingV2::LEN,
   )]
   pub ranking_v2: Box<Account<'info, sla_accounts::RankingV2>>,
 
+  #[account(
+    init_if_needed,
+    seeds = [sla_constants::PREFIX_RANKING_V3.as_bytes(), &avatar_mint.key().to_bytes()],
+    bump,
+    payer = combine_authority, 
+    space = sla_accounts::RankingV3::LEN,
+  )]
+  pub ranking_v3: Box<Account<'info, sla_accounts::RankingV3>>,
+
+  #[account(
+    seeds = [sla_constants::PREFIX_RANK_LADDER.as_bytes()],
+    bump = rank_ladder.bump,
+  )]
+  pub rank_ladder: Box<Account<'info, sla_accounts::RankLadder>>,
+
   #[account(
     mut,
-    constraint = assert_address(combine_authority.key, sla_constants::COMBINE_AUTHORITY_WALLET)
+    constraint = combine_authority.key() == config.combine_authority @ SlaErrors::InvalidPubkey
   )]
   pub combine_authority: Signer<'info>,
 
+  #[account(
+    seeds = [sla_constants::PREFIX_AUTHORITY_SET.as_bytes()],
+    bump = authority_set.bump,
+  )]
+  pub authority_set: Box<Account<'info, sla_accounts::AuthoritySet>>,
+
+  #[account(
+    seeds = [sla_constants::PREFIX_CONFIG.as_bytes()],
+    bump = config.bump,
+  )]
+  pub config: Box<Account<'info, sla_accounts::GlobalConfig>>,
+
   #[account(address = anchor_spl::token::ID)]
   pub token_program: AccountInfo<'info>,
 
@@ -817,3 +2366,123 @@ pub struct AddBadge<'info> {
 
   pub system_program: Program<'info, System>,
 }
+
+
+#[derive(Accounts)]
+#[instruction()]
+pub struct RevokeRank<'info> {
+  pub avatar_mint: Box<Account<'info, anchor_spl::token::Mint>>,
+
+  // Folded in if the ranking is not migrated yet, may not exist
+  #[account(
+    seeds = [sla_constants::PREFIX_RANKING.as_bytes(), &avatar_mint.key().to_bytes()],
+    bump,
+  )]
+  pub ranking_v1: AccountInfo<'info>,
+
+  // May not be migrated yet (stored bump of 0), so the bump is derived
+  #[account(
+    mut,
+    seeds = [sla_constants::PREFIX_RANKING_V2.as_bytes(), &avatar_mint.key().to_bytes()],
+    bump,
+  )]
+  pub ranking_v2: Box<Account<'info, sla_accounts::RankingV2>>,
+
+  #[account(
+    init_if_needed,
+    seeds = [sla_constants::PREFIX_RANKING_V3.as_bytes(), &avatar_mint.key().to_bytes()],
+    bump,
+    payer = admin, 
+    space = sla_accounts::RankingV3::LEN,
+  )]
+  pub ranking_v3: Box<Account<'info, sla_accounts::RankingV3>>,
+
+  #[account(
+    seeds = [sla_constants::PREFIX_RANK_LADDER.as_bytes()],
+    bump = rank_ladder.bump,
+  )]
+  pub rank_ladder: Box<Account<'info, sla_accounts::RankLadder>>,
+
+  #[account(
+    seeds = [sla_constants::PREFIX_CONFIG.as_bytes()],
+    bump = config.bump,
+    has_one = admin @ SlaErrors::InvalidPubkey,
+  )]
+  pub config: Box<Account<'info, sla_accounts::GlobalConfig>>,
+
+  #[account(mut)]
+  pub admin: Signer<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+
+#[derive(Accounts)]
+#[instruction()]
+pub struct MigrateRanking<'info> {
+  pub avatar_mint: Box<Account<'info, anchor_spl::token::Mint>>,
+
+  #[account(
+    associated_token::mint = avatar_mint,
+    associated_token::authority = payer,
+  )]
+  pub avatar_token: Account<'info, anchor_spl::token::TokenAccount>,
+
+  pub avatar_metadata: AccountInfo<'info>,
+
+  // Closed by the instruction if it exists (V1 rankings never stored their bump)
+  #[account(
+    mut,
+    seeds = [sla_constants::PREFIX_RANKING.as_bytes(), &avatar_mint.key().to_bytes()],
+    bump,
+  )]
+  pub ranking_v1: AccountInfo<'info>,
+
+  // Legacy accounts store a 0 bump until migrated, so it is derived here
+  #[account(
+    init_if_needed,
+    seeds = [sla_constants::PREFIX_RANKING_V2.as_bytes(), &avatar_mint.key().to_bytes()],
+    bump,
+    payer = payer, 
+    space = sla_accounts::RankingV2::LEN,
+  )]
+  pub ranking_v2: Box<Account<'info, sla_accounts::RankingV2>>,
+
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  #[account(
+    seeds = [sla_constants::PREFIX_CONFIG.as_bytes()],
+    bump = config.bump,
+  )]
+  pub config: Box<Account<'info, sla_accounts::GlobalConfig>>,
+
+  pub system_program: Program<'info, System>,
+}
+
+
+#[derive(Accounts)]
+#[instruction()]
+pub struct InitBadgeSupplyCounter<'info> {
+  // Comes first so the stored bump is available when creating the counter
+  #[account(
+    seeds = [sla_constants::PREFIX_CONFIG.as_bytes()],
+    bump = config.bump,
+    has_one = admin @ SlaErrors::InvalidPubkey,
+  )]
+  pub config: Box<Account<'info, sla_accounts::GlobalConfig>>,
+
+  #[account(
+    init,
+    seeds = [sla_constants::PREFIX_BADGE_POT.as_bytes()],
+    bump = config.badge_supply_counter_bump,
+    payer = admin,
+    space = sla_accounts::BadgeSupplyCounter::LEN,
+  )]
+  pub badge_supply_counter: Account<'info, sla_accounts::BadgeSupplyCounter>,
+
+  #[account(mut)]
+  pub admin: Signer<'info>,
+
+  pub system_program: Program<'info, System>,
+}
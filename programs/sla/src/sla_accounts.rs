@@ -1,7 +1,9 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+use std::convert::TryFrom;
 use std::fmt;
 
-use crate::{sla_constants, sla_fungible_token::FungibleAsset, SlaErrors};
+use crate::{sla_constants, sla_fungible_token::FungibleAsset, utils::find_pda, SlaErrors};
 
 const DISCRIMINATOR_LENGTH: usize = 8;
 
@@ -14,21 +16,12 @@ pub struct AvatarAccount {
 impl AvatarAccount {
   pub const LEN: usize = DISCRIMINATOR_LENGTH + 2 * AvatarData::LEN;
 
-  pub fn init(&mut self) -> Result<(), SlaErrors> {
-    match self.traits {
-      None => {
-        self.traits = Some(AvatarData::init());
-        Ok(())
-      }
-      Some(_) => Err(SlaErrors::AvatarAlreadyInitialized),
-    }
-  }
-
-  pub fn merge(&mut self, trait_id: u8) -> Result<(), SlaErrors> {
-    match &mut self.traits {
-      Some(traits) => traits.merge(trait_id),
-      None => Err(SlaErrors::AvatarPDANotInitialized),
+  // The V1 account only exists for agents that merged traits before V2, otherwise nothing was merged
+  pub fn load<'info>(account: &AccountInfo<'info>) -> Result<Self, ProgramError> {
+    if account.data_is_empty() {
+      return Ok(Self::default());
     }
+    Ok(*Account::<AvatarAccount>::try_from(account)?)
   }
 }
 
@@ -43,64 +36,97 @@ pub struct AvatarData {
 
 impl AvatarData {
   const LEN: usize = 5;
+}
+
+#[account]
+#[derive(Copy, Default)]
+pub struct AvatarAccountV2 {
+  pub migrated: bool,
+  pub skin: Option<MergedTrait>,
+  pub clothing: Option<MergedTrait>,
+  pub eyes: Option<MergedTrait>,
+  pub hat: Option<MergedTrait>,
+  pub mouth: Option<MergedTrait>,
+  // Next nonce expected in a renderer URI attestation
+  pub nonce: u64,
+}
+
+impl AvatarAccountV2 {
+  pub const LEN: usize = DISCRIMINATOR_LENGTH + 1 + 5 * (1 + MergedTrait::LEN) + 8;
+
+  // Each attestation is signed for the current nonce, so it can only be used once
+  pub fn use_nonce(&mut self, nonce: u64) -> Result<(), SlaErrors> {
+    if nonce != self.nonce {
+      return Err(SlaErrors::InvalidNonce);
+    }
+    self.nonce = self.nonce.wrapping_add(1);
+    Ok(())
+  }
 
-  pub fn init() -> Self {
-    AvatarData {
-      skin: false,
-      clothing: false,
-      eyes: false,
-      hat: false,
-      mouth: false,
+  // Traits merged before V2 are recorded with an unknown (default) mint and slot 0
+  pub fn from_v1(&mut self, avatar_v1: &AvatarAccount) {
+    if self.migrated {
+      return;
     }
+
+    if let Some(traits) = avatar_v1.traits {
+      let legacy = Some(MergedTrait::default());
+      if traits.skin && self.skin.is_none() { self.skin = legacy; }
+      if traits.clothing && self.clothing.is_none() { self.clothing = legacy; }
+      if traits.eyes && self.eyes.is_none() { self.eyes = legacy; }
+      if traits.hat && self.hat.is_none() { self.hat = legacy; }
+      if traits.mouth && self.mouth.is_none() { self.mouth = legacy; }
+    }
+
+    self.migrated = true;
   }
 
-  fn merge(&mut self, trait_id: u8) -> Result<(), SlaErrors> {
+  fn slot_mut(&mut self, trait_id: u8) -> Result<&mut Option<MergedTrait>, SlaErrors> {
     match trait_id {
-      1 => {
-        if self.skin {
-          Err(SlaErrors::MergeCheckFailed)
-        } else {
-          self.skin = true;
-          Ok(())
-        }
-      }
-      2 => {
-        if self.clothing {
-          Err(SlaErrors::MergeCheckFailed)
-        } else {
-          self.clothing = true;
-          Ok(())
-        }
-      }
-      3 => {
-        if self.eyes {
-          Err(SlaErrors::MergeCheckFailed)
-        } else {
-          self.eyes = true;
-          Ok(())
-        }
-      }
-      4 => {
-        if self.hat {
-          Err(SlaErrors::MergeCheckFailed)
-        } else {
-          self.hat = true;
-          Ok(())
-        }
-      }
-      5 => {
-        if self.mouth {
-          Err(SlaErrors::MergeCheckFailed)
-        } else {
-          self.mouth = true;
-          Ok(())
-        }
-      }
+      1 => Ok(&mut self.skin),
+      2 => Ok(&mut self.clothing),
+      3 => Ok(&mut self.eyes),
+      4 => Ok(&mut self.hat),
+      5 => Ok(&mut self.mouth),
       _ => Err(SlaErrors::TraitTypeInvalid),
     }
   }
+
+  pub fn merge(&mut self, trait_id: u8, mint: Pubkey, slot: u64) -> Result<(), SlaErrors> {
+    let merged = self.slot_mut(trait_id)?;
+    if merged.is_some() {
+      return Err(SlaErrors::MergeCheckFailed);
+    }
+    *merged = Some(MergedTrait { mint, slot });
+    Ok(())
+  }
+
+  pub fn unmerge(&mut self, trait_id: u8) -> Result<MergedTrait, SlaErrors> {
+    match self.slot_mut(trait_id)?.take() {
+      Some(merged) => Ok(merged),
+      None => Err(SlaErrors::UnmergeCheckFailed),
+    }
+  }
+
+  // Replacing a trait requires the slot to be already merged (and leaves it merged)
+  pub fn swap(&mut self, trait_id: u8, mint: Pubkey, slot: u64) -> Result<MergedTrait, SlaErrors> {
+    let replaced = self.unmerge(trait_id)?;
+    self.merge(trait_id, mint, slot)?;
+    Ok(replaced)
+  }
+}
+
+#[derive(Clone, Copy, Default, AnchorSerialize, AnchorDeserialize)]
+pub struct MergedTrait {
+  pub mint: Pubkey,
+  pub slot: u64,
 }
 
+impl MergedTrait {
+  const LEN: usize = 32 + 8;
+}
+
+// Legacy ranking, only read by `migrate_ranking`
 #[account]
 #[derive(Copy, Default)]
 pub struct Ranking {
@@ -110,39 +136,6 @@ pub struct Ranking {
 
 impl Ranking {
   pub const LEN: usize = DISCRIMINATOR_LENGTH + 2 * 8;
-
-  pub fn check_upgrade_is_allowed(&self, asset: FungibleAsset) -> Result<(), SlaErrors> {
-    let mint_allowed = match asset {
-      FungibleAsset::BADGE_BRONZE => self.ranking.is_none(),
-      FungibleAsset::BADGE_SILVER => self.ranking == Some(FungibleAsset::BADGE_BRONZE),
-      FungibleAsset::BADGE_GOLD => self.ranking == Some(FungibleAsset::BADGE_SILVER),
-      FungibleAsset::BADGE_PLATINUM => self.ranking == Some(FungibleAsset::BADGE_GOLD),
-      FungibleAsset::BADGE_DIAMOND => self.ranking == Some(FungibleAsset::BADGE_PLATINUM),
-      _ => false,
-    };
-
-    if !mint_allowed {
-      Err(SlaErrors::NotAllowedToMintBadge)
-    } else {
-      Ok(())
-    }
-  }
-
-  pub fn mint_next(&mut self) -> Result<(), SlaErrors> {
-    if self.minted_next {
-      Err(SlaErrors::NextBadgeAlreadyMinted)
-    } else {
-      self.minted_next = true;
-      Ok(())
-    }
-  }
-
-  pub fn update_ranking(&mut self, asset: FungibleAsset) -> Result<(), SlaErrors> {
-    self.check_upgrade_is_allowed(asset)?;
-    self.ranking = Some(asset);
-    self.minted_next = false;
-    Ok(())
-  }
 }
 
 #[account]
@@ -154,124 +147,408 @@ pub struct RankingV2 {
   pub gold_minted: bool,
   pub platinum_minted: bool,
   pub diamond_minted: bool,
+  // Accounts created before bumps were stored read 0 until their next update
+  pub bump: u8,
+  // Set by `migrate_ranking`, required by the badge instructions
+  pub migrated: bool,
+  // Tiers of the rank ladder above Diamond: bit `i` is set once tier `i` is minted, and the current
+  // tier is stored here while it is one of them
+  pub ladder_minted: u32,
+  pub ladder_ranking: Option<u8>,
 }
 
 impl RankingV2 {
   pub const LEN: usize = DISCRIMINATOR_LENGTH + 21;
 
-  pub fn from_v1(&mut self, ranking_v1: &Ranking) {
-    match ranking_v1.ranking {
-      Some(asset) => match asset {
-        FungibleAsset::BADGE_BRONZE => {
-          self.bronze_minted = true;
-        },
-        FungibleAsset::BADGE_SILVER => {
-          self.bronze_minted = true;
-          self.silver_minted = true;
-        },
-        FungibleAsset::BADGE_GOLD => {
-          self.bronze_minted = true;
-          self.silver_minted = true;
-          self.gold_minted = true;
-        },
-        FungibleAsset::BADGE_PLATINUM => {
-          self.bronze_minted = true;
-          self.silver_minted = true;
-          self.gold_minted = true;
-          self.platinum_minted = true;
-        },
-        FungibleAsset::BADGE_DIAMOND => {
-          self.bronze_minted = true;
-          self.silver_minted = true;
-          self.gold_minted = true;
-          self.platinum_minted = true;
-          self.diamond_minted = true;
-        },
-        _ => (),
-      },
-      None => ()
-    };
+  pub fn store_bump(&mut self, avatar_mint: &Pubkey) {
+    if self.bump == 0 {
+      self.bump = find_pda(&[sla_constants::PREFIX_RANKING_V2.as_bytes(), &avatar_mint.to_bytes()]).1;
+    }
+  }
 
-    // Copy the current ranking over if the V2 account has not been initialized yet
-    if self.ranking.is_none() {
-      self.ranking = ranking_v1.ranking;
+  // Folds the V1 ranking of the agent (if it has one) into this account, once
+  pub fn migrate(&mut self, ranking_v1: Option<&Ranking>, avatar_mint: &Pubkey) -> Result<(), SlaErrors> {
+    if self.migrated {
+      return Err(SlaErrors::RankingAlreadyMigrated);
+    }
+    if let Some(ranking) = ranking_v1.and_then(|ranking_v1| ranking_v1.ranking).filter(FungibleAsset::is_badge) {
+      // V1 only kept the current rank: every badge up to it was minted
+      for tier in 0..=RankLadder::legacy_tier(ranking) {
+        self.set_tier_minted(tier, true);
+      }
+      if self.ranking.is_none() {
+        self.ranking = Some(ranking);
+      }
     }
+    self.migrated = true;
+    self.store_bump(avatar_mint);
+    Ok(())
   }
 
-  fn check_mint_is_allowed(&self, asset: FungibleAsset) -> Result<(), SlaErrors> {
-    // Check that this badge has not already been minted
-    let not_minted_yet = match asset {
-      FungibleAsset::BADGE_BRONZE => !self.bronze_minted,
-      FungibleAsset::BADGE_SILVER => !self.silver_minted,
-      FungibleAsset::BADGE_GOLD => !self.gold_minted,
-      FungibleAsset::BADGE_PLATINUM => !self.platinum_minted,
-      FungibleAsset::BADGE_DIAMOND => !self.diamond_minted,
-      _ => false,
-    };
+  // Index of the current tier in the rank ladder (Bronze is 0)
+  pub fn tier(&self) -> Option<u8> {
+    self.ladder_ranking.or(self.ranking.map(RankLadder::legacy_tier))
+  }
 
-    // Check that the current badge is at least the previous level
-    let previous_rank_reached = match asset {
-      FungibleAsset::BADGE_BRONZE => true,
-      FungibleAsset::BADGE_SILVER | FungibleAsset::BADGE_GOLD | FungibleAsset::BADGE_PLATINUM | FungibleAsset::BADGE_DIAMOND => {
-        match self.ranking {
-          Some(ranking) => ranking as u8 >= asset as u8 - 1,
-          None => false,
-        }
+  fn set_tier(&mut self, tier: u8) {
+    match RankLadder::legacy_asset(tier) {
+      Some(asset) => {
+        self.ranking = Some(asset);
+        self.ladder_ranking = None;
       },
-      _ => false,
-    };
+      None => self.ladder_ranking = Some(tier),
+    }
+  }
 
-    if !(not_minted_yet && previous_rank_reached) {
-      Err(SlaErrors::NotAllowedToMintBadge)
-    } else {
-      Ok(())
+  pub fn tier_minted(&self, tier: u8) -> bool {
+    match RankLadder::legacy_asset(tier) {
+      Some(FungibleAsset::BADGE_BRONZE) => self.bronze_minted,
+      Some(FungibleAsset::BADGE_SILVER) => self.silver_minted,
+      Some(FungibleAsset::BADGE_GOLD) => self.gold_minted,
+      Some(FungibleAsset::BADGE_PLATINUM) => self.platinum_minted,
+      Some(_) => self.diamond_minted,
+      None => self.ladder_minted & (1 << tier) != 0,
     }
   }
 
-  pub fn mint(&mut self, asset: FungibleAsset) -> Result<(), SlaErrors> {
-    self.check_mint_is_allowed(asset)?;
-    match asset {
-      FungibleAsset::BADGE_BRONZE => {
-        self.bronze_minted = true;
-        Ok(())
-      }
-      FungibleAsset::BADGE_SILVER => {
-        self.silver_minted = true;
-        Ok(())
-      }
-      FungibleAsset::BADGE_GOLD => {
-        self.gold_minted = true;
-        Ok(())
+  fn set_tier_minted(&mut self, tier: u8, minted: bool) {
+    match RankLadder::legacy_asset(tier) {
+      Some(FungibleAsset::BADGE_BRONZE) => self.bronze_minted = minted,
+      Some(FungibleAsset::BADGE_SILVER) => self.silver_minted = minted,
+      Some(FungibleAsset::BADGE_GOLD) => self.gold_minted = minted,
+      Some(FungibleAsset::BADGE_PLATINUM) => self.platinum_minted = minted,
+      Some(_) => self.diamond_minted = minted,
+      None if minted => self.ladder_minted |= 1 << tier,
+      None => self.ladder_minted &= !(1 << tier),
+    }
+  }
+
+  // Lowers the ranking to a lower tier (None clears it). The revoked tiers can be minted again if `allow_remint`
+  pub fn revoke(&mut self, tier: Option<u8>, allow_remint: bool) -> Result<(), SlaErrors> {
+    // Number of tiers kept, compared to the number of tiers reached
+    let kept = tier.map_or(0, |tier| tier + 1);
+    if kept >= self.tier().map_or(0, |current| current + 1) {
+      return Err(SlaErrors::InvalidRankRevocation);
+    }
+
+    match tier {
+      Some(tier) => self.set_tier(tier),
+      None => {
+        self.ranking = None;
+        self.ladder_ranking = None;
+      },
+    }
+    if allow_remint {
+      for tier in kept..RankLadder::MAX_TIERS as u8 {
+        self.set_tier_minted(tier, false);
       }
-      FungibleAsset::BADGE_PLATINUM => {
-        self.platinum_minted = true;
-        Ok(())
+    }
+    Ok(())
+  }
+}
+
+// Mint and merge of one badge tier. Tiers reached before `RankingV3` existed are flagged without a date
+#[derive(Clone, Copy, Default, AnchorSerialize, AnchorDeserialize)]
+pub struct TierRecord {
+  pub minted: bool,
+  pub minted_slot: u64,
+  pub minted_at: i64,
+  pub minted_by: Pubkey,
+  pub merged: bool,
+  pub merged_slot: u64,
+  pub merged_at: i64,
+  pub merged_by: Pubkey,
+  // Badge burned by the merge (default for badges added by the combine authority)
+  pub badge_mint: Pubkey,
+}
+
+impl TierRecord {
+  pub const LEN: usize = 1 + 8 + 8 + 32 + 1 + 8 + 8 + 32 + 32;
+}
+
+// Provenance of the badges of an agent, indexed by tier of the rank ladder. The current rank stays in `RankingV2`
+#[account]
+#[derive(Default)]
+pub struct RankingV3 {
+  pub tiers: Vec<TierRecord>,
+  pub bump: u8,
+}
+
+impl RankingV3 {
+  pub const LEN: usize = DISCRIMINATOR_LENGTH + 4 + RankLadder::MAX_TIERS * TierRecord::LEN + 1;
+
+  pub fn store_bump(&mut self, avatar_mint: &Pubkey) {
+    if self.bump == 0 {
+      self.bump = find_pda(&[sla_constants::PREFIX_RANKING_V3.as_bytes(), &avatar_mint.to_bytes()]).1;
+    }
+  }
+
+  // Tiers are only stored up to the highest one recorded
+  pub fn tier(&self, index: u8) -> TierRecord {
+    self.tiers.get(usize::from(index)).copied().unwrap_or_default()
+  }
+
+  fn tier_mut(&mut self, index: u8) -> Result<&mut TierRecord, SlaErrors> {
+    let index = usize::from(index);
+    if index >= RankLadder::MAX_TIERS {
+      return Err(SlaErrors::InvalidRankTier);
+    }
+    if index >= self.tiers.len() {
+      self.tiers.resize(index + 1, TierRecord::default());
+    }
+    Ok(&mut self.tiers[index])
+  }
+
+  // Flags the tiers minted or merged in V2 that are not recorded here yet
+  pub fn from_v2(&mut self, ranking_v2: &RankingV2) {
+    // The Bronze to Diamond tiers were merged in order, tiers above Diamond are recorded as they are merged
+    let legacy_merged = ranking_v2.ranking.map_or(0, |ranking| RankLadder::legacy_tier(ranking) + 1);
+
+    for index in 0..RankLadder::MAX_TIERS as u8 {
+      let minted = ranking_v2.tier_minted(index);
+      let merged = index < legacy_merged || ranking_v2.tier() == Some(index);
+      if !(minted || merged) {
+        continue;
       }
-      FungibleAsset::BADGE_DIAMOND => {
-        self.diamond_minted = true;
-        Ok(())
+      if let Ok(tier) = self.tier_mut(index) {
+        tier.minted |= minted;
+        tier.merged |= merged;
       }
-      _ => Err(SlaErrors::NotAllowedToMintBadge),
     }
   }
 
-  fn is_upgrade_allowed(&self, asset: FungibleAsset) -> bool {
-    match asset {
-      FungibleAsset::BADGE_BRONZE => self.ranking.is_none(),
-      FungibleAsset::BADGE_SILVER => self.ranking == Some(FungibleAsset::BADGE_BRONZE),
-      FungibleAsset::BADGE_GOLD => self.ranking == Some(FungibleAsset::BADGE_SILVER),
-      FungibleAsset::BADGE_PLATINUM => self.ranking == Some(FungibleAsset::BADGE_GOLD),
-      FungibleAsset::BADGE_DIAMOND => self.ranking == Some(FungibleAsset::BADGE_PLATINUM),
-      _ => false,
+  pub fn record_mint(&mut self, index: u8, wallet: Pubkey, clock: &Clock) -> Result<(), SlaErrors> {
+    let tier = self.tier_mut(index)?;
+    tier.minted = true;
+    tier.minted_slot = clock.slot;
+    tier.minted_at = clock.unix_timestamp;
+    tier.minted_by = wallet;
+    Ok(())
+  }
+
+  pub fn record_merge(
+    &mut self,
+    index: u8,
+    wallet: Pubkey,
+    badge_mint: Pubkey,
+    clock: &Clock,
+  ) -> Result<(), SlaErrors> {
+    let tier = self.tier_mut(index)?;
+    tier.merged = true;
+    tier.merged_slot = clock.slot;
+    tier.merged_at = clock.unix_timestamp;
+    tier.merged_by = wallet;
+    tier.badge_mint = badge_mint;
+    Ok(())
+  }
+
+  // Forgets the merges of the tiers above `ranking`, and their mints if they can be minted again
+  pub fn revoke(&mut self, tier: Option<u8>, allow_remint: bool) {
+    let kept = tier.map_or(0, |tier| usize::from(tier) + 1);
+    for tier in self.tiers.iter_mut().skip(kept) {
+      *tier = if allow_remint {
+        TierRecord::default()
+      } else {
+        TierRecord {
+          minted: tier.minted,
+          minted_slot: tier.minted_slot,
+          minted_at: tier.minted_at,
+          minted_by: tier.minted_by,
+          ..TierRecord::default()
+        }
+      };
+    }
+  }
+
+  // Date of the Bronze merge, unknown for agents ranked before V3
+  pub fn agent_since(&self) -> Option<i64> {
+    let bronze = self.tier(0);
+    if bronze.merged && bronze.merged_at != 0 {
+      Some(bronze.merged_at)
+    } else {
+      None
+    }
+  }
+}
+
+// Badge tier of the rank ladder, as set by the admin
+#[derive(Clone, Copy, Default, AnchorSerialize, AnchorDeserialize)]
+pub struct RankTierParams {
+  pub badge_mint: Pubkey,
+  // In whole $HAY, like the config prices
+  pub price: u16,
+  pub max_supply: u16,
+  // Tier that must be the current rank to merge this badge, and reached to mint it
+  pub prerequisite: Option<u8>,
+}
+
+#[derive(Clone, Copy, Default, AnchorSerialize, AnchorDeserialize)]
+pub struct RankTier {
+  pub badge_mint: Pubkey,
+  pub price: u16,
+  pub max_supply: u16,
+  pub supply: u16,
+  pub prerequisite: Option<u8>,
+  // On the ID card and scanner prices of the agents at this tier
+  pub discounts: RankDiscounts,
+}
+
+impl RankTier {
+  pub const LEN: usize = 32 + 2 + 2 + 2 + 2 + RankDiscounts::LEN;
+}
+
+// Badge tiers, lowest first. The first five are the Bronze to Diamond badges of the config
+#[account]
+#[derive(Default)]
+pub struct RankLadder {
+  pub tiers: Vec<RankTier>,
+  pub bump: u8,
+}
+
+impl RankLadder {
+  pub const MAX_TIERS: usize = 16;
+  pub const LEN: usize = DISCRIMINATOR_LENGTH + 4 + RankLadder::MAX_TIERS * RankTier::LEN + 1;
+  const LEGACY_BADGES: [FungibleAsset; 5] = [
+    FungibleAsset::BADGE_BRONZE,
+    FungibleAsset::BADGE_SILVER,
+    FungibleAsset::BADGE_GOLD,
+    FungibleAsset::BADGE_PLATINUM,
+    FungibleAsset::BADGE_DIAMOND,
+  ];
+
+  pub fn legacy_tier(asset: FungibleAsset) -> u8 {
+    asset as u8 - FungibleAsset::BADGE_BRONZE as u8
+  }
+
+  pub fn legacy_asset(tier: u8) -> Option<FungibleAsset> {
+    RankLadder::LEGACY_BADGES.get(usize::from(tier)).copied()
+  }
+
+  // Tier of a Bronze to Diamond badge, for the instructions that take an asset ID
+  pub fn badge_tier(asset_id: u8) -> Result<u8, SlaErrors> {
+    let asset = FungibleAsset::try_from(asset_id)?;
+    if !asset.is_badge() {
+      return Err(SlaErrors::AssetIDIsNotBadge);
+    }
+    Ok(RankLadder::legacy_tier(asset))
+  }
+
+  // The badges of the config, each requiring the previous one, with their current supply
+  pub fn init(&mut self, config: &GlobalConfig, counter: &BadgeSupplyCounter) {
+    let supplies = [
+      (counter.bronze, counter.max_bronze),
+      (counter.silver, counter.max_silver),
+      (counter.gold, counter.max_gold),
+      (counter.platinum, counter.max_platinum),
+      (counter.diamond, counter.max_diamond),
+    ];
+    self.tiers = RankLadder::LEGACY_BADGES
+      .iter()
+      .zip(supplies.iter())
+      .map(|(&asset, &(supply, max_supply))| RankTier {
+        badge_mint: config.get_mint(asset),
+        price: config.get_price(asset),
+        max_supply,
+        supply,
+        prerequisite: RankLadder::legacy_tier(asset).checked_sub(1),
+        discounts: RankDiscounts::default(),
+      })
+      .collect();
+  }
+
+  // Updates tier `index`, or appends it when `index` is the number of tiers. The supply minted so far and the
+  // discounts are kept
+  pub fn set_tier(&mut self, index: u8, params: RankTierParams) -> Result<(), SlaErrors> {
+    let index = usize::from(index);
+    if index > self.tiers.len() || index >= RankLadder::MAX_TIERS {
+      return Err(SlaErrors::InvalidRankTier);
     }
+    // Prerequisites are lower tiers, so the ladder has no cycle
+    if params.prerequisite.map_or(false, |prerequisite| usize::from(prerequisite) >= index) {
+      return Err(SlaErrors::InvalidRankTier);
+    }
+
+    let tier = RankTier {
+      badge_mint: params.badge_mint,
+      price: params.price,
+      max_supply: params.max_supply,
+      supply: self.tiers.get(index).map_or(0, |tier| tier.supply),
+      prerequisite: params.prerequisite,
+      discounts: self.tiers.get(index).map_or(RankDiscounts::default(), |tier| tier.discounts),
+    };
+    if index == self.tiers.len() {
+      self.tiers.push(tier);
+    } else {
+      self.tiers[index] = tier;
+    }
+    Ok(())
+  }
+
+  pub fn tier(&self, index: u8) -> Result<&RankTier, SlaErrors> {
+    self.tiers.get(usize::from(index)).ok_or(SlaErrors::InvalidRankTier)
+  }
+
+  fn tier_mut(&mut self, index: u8) -> Result<&mut RankTier, SlaErrors> {
+    self.tiers.get_mut(usize::from(index)).ok_or(SlaErrors::InvalidRankTier)
+  }
+
+  pub fn set_max_supply(&mut self, index: u8, max_supply: u16) -> Result<(), SlaErrors> {
+    self.tier_mut(index)?.max_supply = max_supply;
+    Ok(())
   }
 
-  pub fn update_ranking(&mut self, asset: FungibleAsset) -> Result<(), SlaErrors> {
-    if !self.is_upgrade_allowed(asset) {
-      return Err(SlaErrors::NotAllowedToUpgradeRank)
+  // Sets the supply minted so far back to zero
+  pub fn reset_supply(&mut self, index: u8) -> Result<(), SlaErrors> {
+    self.tier_mut(index)?.supply = 0;
+    Ok(())
+  }
+
+  pub fn set_discounts(&mut self, index: u8, discounts: RankDiscounts) -> Result<(), SlaErrors> {
+    discounts.validate()?;
+    self.tier_mut(index)?.discounts = discounts;
+    Ok(())
+  }
+
+  // Discounts of the current tier of an agent. Unranked agents pay full price
+  pub fn discounts(&self, tier: Option<u8>) -> RankDiscounts {
+    tier
+      .and_then(|tier| self.tiers.get(usize::from(tier)))
+      .map_or(RankDiscounts::default(), |tier| tier.discounts)
+  }
+
+  // Checks the agent can mint the badge of the tier and takes it from the supply
+  pub fn mint(&mut self, ranking: &mut RankingV2, index: u8) -> Result<(), SlaErrors> {
+    let tier = self.tier(index)?;
+    let prerequisite_reached = match tier.prerequisite {
+      Some(prerequisite) => ranking.tier().map_or(false, |current| current >= prerequisite),
+      None => true,
+    };
+    if ranking.tier_minted(index) || !prerequisite_reached {
+      return Err(SlaErrors::NotAllowedToMintBadge);
+    }
+    if tier.supply >= tier.max_supply {
+      return Err(SlaErrors::AssetMaxSupplyReached);
     }
 
-    self.ranking = Some(asset);
+    self.tiers[usize::from(index)].supply += 1;
+    ranking.set_tier_minted(index, true);
+    Ok(())
+  }
+
+  // Lowers the agent to one of the tiers, see `RankingV2::revoke`
+  pub fn revoke(&self, ranking: &mut RankingV2, index: Option<u8>, allow_remint: bool) -> Result<(), SlaErrors> {
+    if let Some(index) = index {
+      self.tier(index)?;
+    }
+    ranking.revoke(index, allow_remint)
+  }
+
+  // Moves the agent up to the tier, from its prerequisite
+  pub fn upgrade(&self, ranking: &mut RankingV2, index: u8) -> Result<(), SlaErrors> {
+    if ranking.tier() != self.tier(index)?.prerequisite {
+      return Err(SlaErrors::NotAllowedToUpgradeRank);
+    }
+    ranking.set_tier(index);
     Ok(())
   }
 }
@@ -284,6 +561,11 @@ pub struct BadgeSupplyCounter {
   pub gold: u16,
   pub platinum: u16,
   pub diamond: u16,
+  pub max_bronze: u16,
+  pub max_silver: u16,
+  pub max_gold: u16,
+  pub max_platinum: u16,
+  pub max_diamond: u16,
 }
 
 impl BadgeSupplyCounter {
@@ -291,59 +573,1080 @@ impl BadgeSupplyCounter {
 
   pub fn init(
     &mut self,
-    n_bronze: u16,
-    n_silver: u16,
-    n_gold: u16,
-    n_platinum: u16,
-    n_diamond: u16,
+    max_bronze: u16,
+    max_silver: u16,
+    max_gold: u16,
+    max_platinum: u16,
+    max_diamond: u16,
   ) {
-    self.bronze = n_bronze;
-    self.silver = n_silver;
-    self.gold = n_gold;
-    self.platinum = n_platinum;
-    self.diamond = n_diamond;
+    self.max_bronze = max_bronze;
+    self.max_silver = max_silver;
+    self.max_gold = max_gold;
+    self.max_platinum = max_platinum;
+    self.max_diamond = max_diamond;
 
     msg!("Total supply of badges: {}", self);
   }
 
-  fn check_supply(&self, asset: FungibleAsset) -> Result<(), SlaErrors> {
-    let ok = match asset {
-      FungibleAsset::BADGE_BRONZE => self.bronze < sla_constants::SUPPLY_BADGE_BRONZE,
-      FungibleAsset::BADGE_SILVER => self.silver < sla_constants::SUPPLY_BADGE_SILVER,
-      FungibleAsset::BADGE_GOLD => self.gold < sla_constants::SUPPLY_BADGE_GOLD,
-      FungibleAsset::BADGE_PLATINUM => self.platinum < sla_constants::SUPPLY_BADGE_PLATINUM,
-      FungibleAsset::BADGE_DIAMOND => self.diamond < sla_constants::SUPPLY_BADGE_DIAMOND,
-      _ => false,
-    };
+  // No more badges can be minted from this counter
+  pub fn freeze(&mut self) {
+    self.max_bronze = self.bronze;
+    self.max_silver = self.silver;
+    self.max_gold = self.gold;
+    self.max_platinum = self.platinum;
+    self.max_diamond = self.diamond;
+  }
+}
 
-    if ok {
-      Ok(())
-    } else {
-      Err(SlaErrors::AssetMaxSupplyReached)
+impl fmt::Display for BadgeSupplyCounter {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(
+      f,
+      "Bronze: {}/{}, silver: {}/{}, gold: {}/{}, platinum: {}/{}, diamond: {}/{}",
+      self.bronze, self.max_bronze,
+      self.silver, self.max_silver,
+      self.gold, self.max_gold,
+      self.platinum, self.max_platinum,
+      self.diamond, self.max_diamond,
+    )
+  }
+}
+
+#[account]
+#[derive(Default)]
+pub struct GlobalConfig {
+  pub admin: Pubkey,
+  pub combine_authority: Pubkey,
+  pub avatar_creator: Pubkey,
+  pub trait_creator: Pubkey,
+  pub hay_mint: Pubkey,
+  pub hay_treasury_ata: Pubkey,
+  pub id_card_mint: Pubkey,
+  pub scanner_mint: Pubkey,
+  pub badge_bronze_mint: Pubkey,
+  pub badge_silver_mint: Pubkey,
+  pub badge_gold_mint: Pubkey,
+  pub badge_platinum_mint: Pubkey,
+  pub badge_diamond_mint: Pubkey,
+  // Prices are in whole $HAY (scaled by the $HAY mint decimals on payment)
+  pub price_id_card: u16,
+  pub price_scanner: u16,
+  // Deprecated: only seed the prices of the Bronze to Diamond tiers in `init_rank_ladder`, `set_rank_tier` changes
+  // them afterwards
+  pub price_badge_bronze: u16,
+  pub price_badge_silver: u16,
+  pub price_badge_gold: u16,
+  pub price_badge_platinum: u16,
+  pub price_badge_diamond: u16,
+  pub bump: u8,
+  // Bumps of the singleton PDAs (the badge supply counter has no spare space to store its own)
+  pub treasury_bump: u8,
+  pub badge_supply_counter_bump: u8,
+  pub paused: PauseFlags,
+  pub metadata_authority_bump: u8,
+  // URIs of the agents updated without the combine authority are derived from it
+  pub metadata_base_uri: String,
+  // Signs the URI attestations of the `*_attested` instructions
+  pub renderer: Pubkey,
+  // Scanners each agent can mint, and the seconds to wait between two of them
+  pub scanner_limit: u16,
+  pub scanner_cooldown: i64,
+}
+
+impl GlobalConfig {
+  pub const MAX_BASE_URI_LEN: usize = 128;
+  pub const LEN: usize =
+    DISCRIMINATOR_LENGTH + 13 * 32 + 7 * 2 + 3 + PauseFlags::LEN + 1 + 4 + GlobalConfig::MAX_BASE_URI_LEN + 32 + 2 + 8;
+
+  pub fn store_bumps(&mut self) {
+    self.bump = find_pda(&[sla_constants::PREFIX_CONFIG.as_bytes()]).1;
+    self.treasury_bump = find_pda(&[sla_constants::PREFIX_TREASURY.as_bytes()]).1;
+    self.badge_supply_counter_bump = find_pda(&[sla_constants::PREFIX_BADGE_POT.as_bytes()]).1;
+    self.metadata_authority_bump = find_pda(&[sla_constants::PREFIX_UPDATE_AUTHORITY.as_bytes()]).1;
+  }
+
+  pub fn set(&mut self, params: ConfigParams) {
+    self.admin = params.admin;
+    self.combine_authority = params.combine_authority;
+    self.avatar_creator = params.avatar_creator;
+    self.trait_creator = params.trait_creator;
+    self.hay_mint = params.hay_mint;
+    self.hay_treasury_ata = params.hay_treasury_ata;
+    self.id_card_mint = params.id_card_mint;
+    self.scanner_mint = params.scanner_mint;
+    self.badge_bronze_mint = params.badge_bronze_mint;
+    self.badge_silver_mint = params.badge_silver_mint;
+    self.badge_gold_mint = params.badge_gold_mint;
+    self.badge_platinum_mint = params.badge_platinum_mint;
+    self.badge_diamond_mint = params.badge_diamond_mint;
+    self.price_id_card = params.price_id_card;
+    self.price_scanner = params.price_scanner;
+    self.price_badge_bronze = params.price_badge_bronze;
+    self.price_badge_silver = params.price_badge_silver;
+    self.price_badge_gold = params.price_badge_gold;
+    self.price_badge_platinum = params.price_badge_platinum;
+    self.price_badge_diamond = params.price_badge_diamond;
+    self.scanner_limit = params.scanner_limit;
+    self.scanner_cooldown = params.scanner_cooldown;
+  }
+
+  pub fn set_metadata_base_uri(&mut self, base_uri: String) -> Result<(), SlaErrors> {
+    if base_uri.len() > GlobalConfig::MAX_BASE_URI_LEN {
+      return Err(SlaErrors::InvalidMetadataBaseUri);
     }
+    self.metadata_base_uri = base_uri;
+    Ok(())
   }
 
-  pub fn increment(&mut self, asset: FungibleAsset) -> Result<(), SlaErrors> {
-    msg!("Incrementing supply of {} (currently: {})", asset, self);
-    self.check_supply(asset)?;
+  // `<base URI>/<agent mint>/<slot>.json`: a new URI for every update, rendered off-chain
+  // from the agent PDA at that slot
+  pub fn metadata_uri(&self, avatar_mint: &Pubkey, slot: u64) -> Result<String, SlaErrors> {
+    if self.metadata_base_uri.is_empty() {
+      return Err(SlaErrors::InvalidMetadataBaseUri);
+    }
+    Ok(format!("{}/{}/{}.json", self.metadata_base_uri.trim_end_matches('/'), avatar_mint, slot))
+  }
+
+  // `<base URI>/<agent mint>/<slot>/<trait ID>.json`: URI of the trait that left the agent at that slot, rendered
+  // off-chain from the agent PDA before it
+  pub fn trait_uri(&self, avatar_mint: &Pubkey, slot: u64, trait_id: u8) -> Result<String, SlaErrors> {
+    if self.metadata_base_uri.is_empty() {
+      return Err(SlaErrors::InvalidMetadataBaseUri);
+    }
+    Ok(format!("{}/{}/{}/{}.json", self.metadata_base_uri.trim_end_matches('/'), avatar_mint, slot, trait_id))
+  }
+
+  pub fn get_mint(&self, asset: FungibleAsset) -> Pubkey {
+    match asset {
+      FungibleAsset::ID_CARD => self.id_card_mint,
+      FungibleAsset::BADGE_BRONZE => self.badge_bronze_mint,
+      FungibleAsset::BADGE_SILVER => self.badge_silver_mint,
+      FungibleAsset::BADGE_GOLD => self.badge_gold_mint,
+      FungibleAsset::BADGE_PLATINUM => self.badge_platinum_mint,
+      FungibleAsset::BADGE_DIAMOND => self.badge_diamond_mint,
+    }
+  }
+
+  pub fn get_price(&self, asset: FungibleAsset) -> u16 {
     match asset {
-      FungibleAsset::BADGE_BRONZE => self.bronze += 1,
-      FungibleAsset::BADGE_SILVER => self.silver += 1,
-      FungibleAsset::BADGE_GOLD => self.gold += 1,
-      FungibleAsset::BADGE_PLATINUM => self.platinum += 1,
-      FungibleAsset::BADGE_DIAMOND => self.diamond += 1,
-      _ => panic!("Cannot increment supply counter: asset is not a badge"),
+      FungibleAsset::ID_CARD => self.price_id_card,
+      FungibleAsset::BADGE_BRONZE => self.price_badge_bronze,
+      FungibleAsset::BADGE_SILVER => self.price_badge_silver,
+      FungibleAsset::BADGE_GOLD => self.price_badge_gold,
+      FungibleAsset::BADGE_PLATINUM => self.price_badge_platinum,
+      FungibleAsset::BADGE_DIAMOND => self.price_badge_diamond,
+    }
+  }
+}
+
+// M-of-N set of keys approving the sensitive instructions (rotations, `add_badge`)
+#[account]
+#[derive(Default)]
+pub struct AuthoritySet {
+  pub members: Vec<Pubkey>,
+  pub threshold: u8,
+  pub bump: u8,
+}
+
+impl AuthoritySet {
+  pub const MAX_MEMBERS: usize = 10;
+  pub const LEN: usize = DISCRIMINATOR_LENGTH + 4 + AuthoritySet::MAX_MEMBERS * 32 + 1 + 1;
+
+  pub fn set(&mut self, members: Vec<Pubkey>, threshold: u8) -> Result<(), SlaErrors> {
+    let has_duplicates = members.iter().enumerate().any(|(i, member)| members[..i].contains(member));
+
+    if members.len() > AuthoritySet::MAX_MEMBERS
+      || has_duplicates
+      || threshold == 0
+      || usize::from(threshold) > members.len()
+    {
+      return Err(SlaErrors::InvalidAuthoritySet);
+    }
+
+    self.members = members;
+    self.threshold = threshold;
+    Ok(())
+  }
+
+  // Approvals are the distinct members among the signers
+  pub fn check_approvals(&self, signers: &[AccountInfo]) -> Result<(), SlaErrors> {
+    let mut approvals: Vec<&Pubkey> = Vec::new();
+    for signer in signers.iter().filter(|account| account.is_signer) {
+      if self.members.contains(signer.key) && !approvals.contains(&signer.key) {
+        approvals.push(signer.key);
+      }
+    }
+
+    if approvals.len() < usize::from(self.threshold) {
+      return Err(SlaErrors::NotEnoughApprovals);
     }
     Ok(())
   }
 }
 
-impl fmt::Display for BadgeSupplyCounter {
+// Scanners minted by an agent
+#[account]
+#[derive(Default)]
+pub struct ScannerRecord {
+  pub minted: u16,
+  pub last_minted_at: i64,
+  pub bump: u8,
+}
+
+impl ScannerRecord {
+  pub const LEN: usize = DISCRIMINATOR_LENGTH + 2 + 8 + 1;
+
+  pub fn record_mint(&mut self, now: i64, limit: u16, cooldown: i64) -> Result<(), SlaErrors> {
+    let cooling_down = self.minted > 0 && now < self.last_minted_at.saturating_add(cooldown);
+    if self.minted >= limit || cooling_down {
+      return Err(SlaErrors::ScannerAlreadyMinted);
+    }
+
+    self.minted += 1;
+    self.last_minted_at = now;
+    Ok(())
+  }
+}
+
+// Owner of an alias, at the PDA of the hash of the normalized alias so that it is unique
+#[account]
+#[derive(Default)]
+pub struct AliasRecord {
+  pub avatar_mint: Pubkey,
+  pub bump: u8,
+}
+
+impl AliasRecord {
+  pub const LEN: usize = DISCRIMINATOR_LENGTH + 32 + 1;
+  pub const MAX_ALIAS_LEN: usize = mpl_token_metadata::state::MAX_NAME_LENGTH;
+
+  pub fn validate(alias: &str) -> Result<(), SlaErrors> {
+    if alias.is_empty() || alias.len() > AliasRecord::MAX_ALIAS_LEN {
+      return Err(SlaErrors::InvalidAliasLength);
+    }
+
+    let allowed = |c: char| c.is_ascii_alphanumeric() || c == ' ' || c == '-' || c == '_' || c == '.';
+    if !alias.chars().all(allowed) || alias.starts_with(' ') || alias.ends_with(' ') || alias.contains("  ") {
+      return Err(SlaErrors::InvalidAliasCharacters);
+    }
+    Ok(())
+  }
+
+  // Aliases differing only by case are the same alias
+  pub fn seed(alias: &str) -> [u8; 32] {
+    hash(alias.to_ascii_lowercase().as_bytes()).to_bytes()
+  }
+
+  pub fn claim(&mut self, avatar_mint: &Pubkey) -> Result<(), SlaErrors> {
+    if self.avatar_mint != Pubkey::default() && self.avatar_mint != *avatar_mint {
+      return Err(SlaErrors::AliasAlreadyTaken);
+    }
+    self.avatar_mint = *avatar_mint;
+    Ok(())
+  }
+}
+
+// Current alias of an agent and its previous ones (most recent last), and its number of scans
+#[account]
+#[derive(Default)]
+pub struct AgentProfile {
+  pub alias: Option<String>,
+  pub history: Vec<String>,
+  pub bump: u8,
+  // Index of the next `ScanRecord` of the agent
+  pub scans: u32,
+}
+
+impl AgentProfile {
+  pub const MAX_HISTORY: usize = 5;
+  const ALIAS_LEN: usize = 4 + AliasRecord::MAX_ALIAS_LEN;
+  pub const LEN: usize =
+    DISCRIMINATOR_LENGTH + 1 + AgentProfile::ALIAS_LEN + 4 + AgentProfile::MAX_HISTORY * AgentProfile::ALIAS_LEN + 1 + 4;
+
+  pub fn store_bump(&mut self, avatar_mint: &Pubkey) {
+    if self.bump == 0 {
+      self.bump = find_pda(&[sla_constants::PREFIX_PROFILE.as_bytes(), &avatar_mint.to_bytes()]).1;
+    }
+  }
+
+  // The oldest alias is dropped once the history is full
+  pub fn set_alias(&mut self, alias: String) {
+    if let Some(previous) = self.alias.replace(alias) {
+      if self.history.len() == AgentProfile::MAX_HISTORY {
+        self.history.remove(0);
+      }
+      self.history.push(previous);
+    }
+  }
+}
+
+// One scan of an agent, at the PDA of the agent mint + the scan index (little endian)
+#[account]
+#[derive(Default)]
+pub struct ScanRecord {
+  pub avatar_mint: Pubkey,
+  pub index: u32,
+  pub timestamp: i64,
+  pub slot: u64,
+  pub scanner_mint: Pubkey,
+  pub uri_before: String,
+  pub uri_after: String,
+  // Agent scanned with `scan_target` (the URIs are then the ones of the target)
+  pub target: Option<Pubkey>,
+  pub bump: u8,
+}
+
+impl ScanRecord {
+  const URI_LEN: usize = 4 + mpl_token_metadata::state::MAX_URI_LENGTH;
+  pub const LEN: usize = DISCRIMINATOR_LENGTH + 32 + 4 + 8 + 8 + 32 + 2 * ScanRecord::URI_LEN + 1 + 32 + 1;
+
+  pub fn seed(index: u32) -> [u8; 4] {
+    index.to_le_bytes()
+  }
+
+  // Fills the record of the next scan of the agent
+  pub fn record(
+    &mut self,
+    agent_profile: &mut AgentProfile,
+    avatar_mint: Pubkey,
+    scanner_mint: Pubkey,
+    uri_before: String,
+    uri_after: String,
+    target: Option<Pubkey>,
+    clock: &Clock,
+  ) {
+    self.avatar_mint = avatar_mint;
+    self.index = agent_profile.scans;
+    self.timestamp = clock.unix_timestamp;
+    self.slot = clock.slot;
+    self.scanner_mint = scanner_mint;
+    self.uri_before = uri_before;
+    self.uri_after = uri_after;
+    self.target = target;
+    self.bump = find_pda(
+      &[sla_constants::PREFIX_SCAN.as_bytes(), &avatar_mint.to_bytes(), &ScanRecord::seed(self.index)]
+    ).1;
+
+    agent_profile.scans += 1;
+    agent_profile.store_bump(&avatar_mint);
+  }
+}
+
+// Instruction families that can be paused by the admin
+#[derive(Clone, Copy, Default, AnchorSerialize, AnchorDeserialize)]
+pub struct PauseFlags {
+  // merge, unmerge, swap_trait
+  pub merge: bool,
+  // mint_id_card, mint_scanner, mint_badge
+  pub fungible: bool,
+  // merge_badge, add_badge
+  pub ranking: bool,
+  // change_alias, scan_agent
+  pub alias: bool,
+}
+
+impl PauseFlags {
+  const LEN: usize = 4;
+}
+
+// Discounts of a rank tier, in basis points
+#[derive(Clone, Copy, Default, AnchorSerialize, AnchorDeserialize)]
+pub struct RankDiscounts {
+  pub id_card: u16,
+  pub scanner: u16,
+}
+
+impl RankDiscounts {
+  const LEN: usize = 2 * 2;
+  pub const MAX_BPS: u16 = 10_000;
+
+  pub fn validate(&self) -> Result<(), SlaErrors> {
+    if self.id_card > RankDiscounts::MAX_BPS || self.scanner > RankDiscounts::MAX_BPS {
+      return Err(SlaErrors::InvalidDiscount);
+    }
+    Ok(())
+  }
+
+  pub fn id_card_price(&self, price: u16) -> u16 {
+    RankDiscounts::apply(self.id_card, price)
+  }
+
+  pub fn scanner_price(&self, price: u16) -> u16 {
+    RankDiscounts::apply(self.scanner, price)
+  }
+
+  // Rounded down, so a discount never makes the price higher
+  fn apply(bps: u16, price: u16) -> u16 {
+    let bps = bps.min(RankDiscounts::MAX_BPS);
+    let price = u32::from(price) * u32::from(RankDiscounts::MAX_BPS - bps) / u32::from(RankDiscounts::MAX_BPS);
+    price as u16
+  }
+}
+
+impl fmt::Display for PauseFlags {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     write!(
       f,
-      "Bronze: {}, silver: {}, gold: {}, platinum: {}, diamond: {}",
-      self.bronze, self.silver, self.gold, self.platinum, self.diamond
+      "merge: {}, fungible: {}, ranking: {}, alias: {}",
+      self.merge, self.fungible, self.ranking, self.alias,
     )
   }
 }
+
+// Everything in the GlobalConfig that can be set by the admin
+#[derive(Clone, Copy, Default, AnchorSerialize, AnchorDeserialize)]
+pub struct ConfigParams {
+  pub admin: Pubkey,
+  pub combine_authority: Pubkey,
+  pub avatar_creator: Pubkey,
+  pub trait_creator: Pubkey,
+  pub hay_mint: Pubkey,
+  pub hay_treasury_ata: Pubkey,
+  pub id_card_mint: Pubkey,
+  pub scanner_mint: Pubkey,
+  pub badge_bronze_mint: Pubkey,
+  pub badge_silver_mint: Pubkey,
+  pub badge_gold_mint: Pubkey,
+  pub badge_platinum_mint: Pubkey,
+  pub badge_diamond_mint: Pubkey,
+  // Prices are in whole $HAY (scaled by the $HAY mint decimals on payment)
+  pub price_id_card: u16,
+  pub price_scanner: u16,
+  pub price_badge_bronze: u16,
+  pub price_badge_silver: u16,
+  pub price_badge_gold: u16,
+  pub price_badge_platinum: u16,
+  pub price_badge_diamond: u16,
+  pub scanner_limit: u16,
+  pub scanner_cooldown: i64,
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use proptest::prelude::*;
+
+  const BADGES: [FungibleAsset; 5] = [
+    FungibleAsset::BADGE_BRONZE,
+    FungibleAsset::BADGE_SILVER,
+    FungibleAsset::BADGE_GOLD,
+    FungibleAsset::BADGE_PLATINUM,
+    FungibleAsset::BADGE_DIAMOND,
+  ];
+
+  fn any_ranking() -> impl Strategy<Value = Option<FungibleAsset>> {
+    prop::option::of(prop::sample::select(BADGES.to_vec()))
+  }
+
+  // Current ranking + minted flags (RankingV2 itself is not Debug, which proptest values must be)
+  fn any_ranking_v2() -> impl Strategy<Value = (Option<FungibleAsset>, [bool; 5])> {
+    (any_ranking(), any::<[bool; 5]>())
+  }
+
+  fn ranking_v2((ranking, minted): (Option<FungibleAsset>, [bool; 5])) -> RankingV2 {
+    RankingV2 {
+      ranking,
+      bronze_minted: minted[0],
+      silver_minted: minted[1],
+      gold_minted: minted[2],
+      platinum_minted: minted[3],
+      diamond_minted: minted[4],
+      bump: 0,
+      migrated: false,
+      ladder_minted: 0,
+      ladder_ranking: None,
+    }
+  }
+
+  fn minted(ranking: &RankingV2) -> [bool; 5] {
+    [
+      ranking.bronze_minted,
+      ranking.silver_minted,
+      ranking.gold_minted,
+      ranking.platinum_minted,
+      ranking.diamond_minted,
+    ]
+  }
+
+  // Merged traits of each slot (MergedTrait is not Debug either)
+  fn merged(avatar: &AvatarAccountV2) -> [Option<(Pubkey, u64)>; 5] {
+    let slot = |merged: Option<MergedTrait>| merged.map(|merged| (merged.mint, merged.slot));
+    [slot(avatar.skin), slot(avatar.clothing), slot(avatar.eyes), slot(avatar.hat), slot(avatar.mouth)]
+  }
+
+  fn avatar_v1(traits: [bool; 5]) -> AvatarAccount {
+    AvatarAccount {
+      traits: Some(AvatarData {
+        skin: traits[0],
+        clothing: traits[1],
+        eyes: traits[2],
+        hat: traits[3],
+        mouth: traits[4],
+      }),
+    }
+  }
+
+  fn ranking_v1(ranking: Option<FungibleAsset>) -> Ranking {
+    Ranking { ranking, minted_next: false }
+  }
+
+  #[test]
+  fn migrate_marks_every_badge_up_to_the_v1_rank_as_minted() {
+    let mut ranking = RankingV2::default();
+    ranking.migrate(Some(&ranking_v1(Some(FungibleAsset::BADGE_GOLD))), &Pubkey::new_unique()).unwrap();
+
+    assert_eq!(ranking.ranking, Some(FungibleAsset::BADGE_GOLD));
+    assert_eq!(minted(&ranking), [true, true, true, false, false]);
+  }
+
+  #[test]
+  fn migrate_keeps_the_v2_ranking() {
+    let mut ranking = RankingV2 { ranking: Some(FungibleAsset::BADGE_SILVER), ..RankingV2::default() };
+    ranking.migrate(Some(&ranking_v1(Some(FungibleAsset::BADGE_BRONZE))), &Pubkey::new_unique()).unwrap();
+
+    assert_eq!(ranking.ranking, Some(FungibleAsset::BADGE_SILVER));
+  }
+
+  #[test]
+  fn rankings_are_migrated_once() {
+    let avatar_mint = Pubkey::new_unique();
+    let mut ranking = RankingV2::default();
+
+    ranking.migrate(Some(&ranking_v1(Some(FungibleAsset::BADGE_GOLD))), &avatar_mint).unwrap();
+    assert!(ranking.migrated);
+    assert_eq!(ranking.ranking, Some(FungibleAsset::BADGE_GOLD));
+    assert_eq!(minted(&ranking), [true, true, true, false, false]);
+    assert!(matches!(ranking.migrate(None, &avatar_mint), Err(SlaErrors::RankingAlreadyMigrated)));
+  }
+
+  #[test]
+  fn ranking_v3_keeps_the_v2_tiers_and_records_new_ones() {
+    let mut ranking = RankingV3::default();
+    ranking.from_v2(&ranking_v2((Some(FungibleAsset::BADGE_BRONZE), [true, true, false, false, false])));
+    assert!(ranking.tiers[0].merged && ranking.tiers[1].minted && !ranking.tiers[1].merged);
+    assert_eq!(ranking.agent_since(), None);
+
+    let (wallet, badge_mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let clock = Clock { slot: 7, unix_timestamp: 1_000, ..Clock::default() };
+    ranking.record_merge(1, wallet, badge_mint, &clock).unwrap();
+    assert_eq!((ranking.tiers[1].merged_at, ranking.tiers[1].merged_by), (1_000, wallet));
+    assert_eq!(ranking.tiers[1].badge_mint, badge_mint);
+    let max_tiers = RankLadder::MAX_TIERS as u8;
+    assert!(matches!(ranking.record_mint(max_tiers, wallet, &clock), Err(SlaErrors::InvalidRankTier)));
+
+    // Later conversions never clear what was recorded
+    ranking.from_v2(&RankingV2::default());
+    assert!(ranking.tiers[1].merged);
+  }
+
+  #[test]
+  fn ranking_v3_records_the_tiers_above_diamond() {
+    let mut ranking = RankingV3::default();
+    let clock = Clock { slot: 7, unix_timestamp: 1_000, ..Clock::default() };
+    ranking.record_mint(5, Pubkey::new_unique(), &clock).unwrap();
+    assert!(ranking.tier(5).minted && !ranking.tier(4).minted);
+    assert!(!ranking.tier(6).minted);
+
+    // The current tier above Diamond is flagged as merged
+    let mut ranking_v2 = ranking_v2((Some(FungibleAsset::BADGE_DIAMOND), [true; 5]));
+    ranking_v2.ladder_ranking = Some(5);
+    ranking.from_v2(&ranking_v2);
+    assert!(ranking.tier(4).merged && ranking.tier(5).merged && !ranking.tier(6).merged);
+  }
+
+  #[test]
+  fn ranks_can_only_be_revoked_downwards() {
+    let mut ranking = ranking_v2((Some(FungibleAsset::BADGE_GOLD), [true, true, true, false, false]));
+
+    assert!(matches!(ranking.revoke(Some(2), false), Err(SlaErrors::InvalidRankRevocation)));
+    assert!(matches!(ranking.revoke(Some(5), false), Err(SlaErrors::InvalidRankRevocation)));
+
+    ranking.revoke(Some(0), false).unwrap();
+    assert_eq!(ranking.ranking, Some(FungibleAsset::BADGE_BRONZE));
+    assert_eq!(minted(&ranking), [true, true, true, false, false]);
+
+    ranking.revoke(None, true).unwrap();
+    assert_eq!(ranking.ranking, None);
+    assert_eq!(minted(&ranking), [false; 5]);
+    assert!(matches!(ranking.revoke(None, true), Err(SlaErrors::InvalidRankRevocation)));
+  }
+
+  fn master_tier(prerequisite: Option<u8>, max_supply: u16) -> RankTierParams {
+    RankTierParams { badge_mint: Pubkey::new_unique(), price: 500, max_supply, prerequisite }
+  }
+
+  #[test]
+  fn ladder_starts_with_the_config_badges() {
+    let config = GlobalConfig { badge_gold_mint: Pubkey::new_unique(), price_badge_gold: 30, ..GlobalConfig::default() };
+    let counter = BadgeSupplyCounter { gold: 2, max_gold: 10, ..BadgeSupplyCounter::default() };
+    let mut ladder = RankLadder::default();
+    ladder.init(&config, &counter);
+
+    assert_eq!(ladder.tiers.len(), 5);
+    let gold = ladder.tier(2).unwrap();
+    assert_eq!((gold.badge_mint, gold.price), (config.badge_gold_mint, 30));
+    assert_eq!((gold.supply, gold.max_supply), (2, 10));
+    let prerequisites: Vec<_> = ladder.tiers.iter().map(|tier| tier.prerequisite).collect();
+    assert_eq!(prerequisites, vec![None, Some(0), Some(1), Some(2), Some(3)]);
+  }
+
+  #[test]
+  fn ladder_tiers_require_lower_tiers() {
+    let mut ladder = RankLadder::default();
+    ladder.init(&GlobalConfig::default(), &BadgeSupplyCounter::default());
+
+    assert!(matches!(ladder.set_tier(6, master_tier(Some(4), 1)), Err(SlaErrors::InvalidRankTier)));
+    assert!(matches!(ladder.set_tier(5, master_tier(Some(5), 1)), Err(SlaErrors::InvalidRankTier)));
+    assert!(ladder.set_tier(5, master_tier(Some(4), 1)).is_ok());
+    assert!(matches!(ladder.tier(6), Err(SlaErrors::InvalidRankTier)));
+  }
+
+  #[test]
+  fn ladder_tiers_go_above_diamond() {
+    let mut ladder = RankLadder::default();
+    ladder.init(&GlobalConfig::default(), &BadgeSupplyCounter::default());
+    ladder.set_tier(5, master_tier(Some(4), 1)).unwrap();
+    let mut ranking = ranking_v2((Some(FungibleAsset::BADGE_DIAMOND), [true; 5]));
+
+    ladder.mint(&mut ranking, 5).unwrap();
+    assert!(ranking.tier_minted(5));
+    assert!(matches!(ladder.mint(&mut ranking, 5), Err(SlaErrors::NotAllowedToMintBadge)));
+
+    ladder.upgrade(&mut ranking, 5).unwrap();
+    assert_eq!(ranking.tier(), Some(5));
+    assert_eq!(ranking.ranking, Some(FungibleAsset::BADGE_DIAMOND));
+    assert!(matches!(ladder.upgrade(&mut ranking, 5), Err(SlaErrors::NotAllowedToUpgradeRank)));
+
+    // Revocations go down to any tier of the ladder, above Diamond included
+    ladder.set_tier(6, master_tier(Some(5), 1)).unwrap();
+    ladder.mint(&mut ranking, 6).unwrap();
+    ladder.upgrade(&mut ranking, 6).unwrap();
+    assert!(matches!(ladder.revoke(&mut ranking, Some(7), false), Err(SlaErrors::InvalidRankTier)));
+    ladder.revoke(&mut ranking, Some(5), false).unwrap();
+    assert_eq!((ranking.tier(), ranking.ladder_ranking), (Some(5), Some(5)));
+    assert!(ranking.tier_minted(6));
+
+    ladder.revoke(&mut ranking, Some(2), true).unwrap();
+    assert_eq!((ranking.tier(), ranking.ladder_ranking), (Some(2), None));
+    assert_eq!(ranking.ranking, Some(FungibleAsset::BADGE_GOLD));
+    assert!(!ranking.tier_minted(6) && !ranking.tier_minted(5) && !ranking.tier_minted(3));
+
+    // The supply of the tier is exhausted
+    let mut other = ranking_v2((Some(FungibleAsset::BADGE_DIAMOND), [true; 5]));
+    assert!(matches!(ladder.mint(&mut other, 5), Err(SlaErrors::AssetMaxSupplyReached)));
+  }
+
+  // The Bronze to Diamond tiers set up by `init_rank_ladder`, with no supply limit
+  fn legacy_ladder() -> RankLadder {
+    let counter = BadgeSupplyCounter {
+      max_bronze: u16::MAX,
+      max_silver: u16::MAX,
+      max_gold: u16::MAX,
+      max_platinum: u16::MAX,
+      max_diamond: u16::MAX,
+      ..BadgeSupplyCounter::default()
+    };
+    let mut ladder = RankLadder::default();
+    ladder.init(&GlobalConfig::default(), &counter);
+    ladder
+  }
+
+  #[test]
+  fn bronze_can_be_minted_without_a_rank() {
+    let mut ladder = legacy_ladder();
+    let mut ranking = RankingV2::default();
+
+    assert!(ladder.mint(&mut ranking, 0).is_ok());
+    assert!(ranking.bronze_minted);
+    assert_eq!(ranking.ranking, None);
+  }
+
+  #[test]
+  fn next_badge_requires_the_previous_rank() {
+    let mut ladder = legacy_ladder();
+    let mut ranking = RankingV2::default();
+    ladder.mint(&mut ranking, 0).unwrap();
+
+    assert!(matches!(ladder.mint(&mut ranking, 1), Err(SlaErrors::NotAllowedToMintBadge)));
+
+    ladder.upgrade(&mut ranking, 0).unwrap();
+    assert!(ladder.mint(&mut ranking, 1).is_ok());
+  }
+
+  #[test]
+  fn id_card_is_not_a_badge() {
+    assert!(matches!(RankLadder::badge_tier(FungibleAsset::ID_CARD as u8), Err(SlaErrors::AssetIDIsNotBadge)));
+    assert!(matches!(RankLadder::badge_tier(7), Err(SlaErrors::UnknownAssetId)));
+    assert!(matches!(RankLadder::badge_tier(FungibleAsset::BADGE_GOLD as u8), Ok(2)));
+  }
+
+  #[test]
+  fn ranking_cannot_skip_a_tier() {
+    let ladder = legacy_ladder();
+    let mut ranking = RankingV2::default();
+
+    assert!(matches!(ladder.upgrade(&mut ranking, 1), Err(SlaErrors::NotAllowedToUpgradeRank)));
+    assert!(ladder.upgrade(&mut ranking, 0).is_ok());
+    assert!(ladder.upgrade(&mut ranking, 0).is_err());
+    assert!(ladder.upgrade(&mut ranking, 2).is_err());
+    assert_eq!(ranking.ranking, Some(FungibleAsset::BADGE_BRONZE));
+  }
+
+  #[test]
+  fn merge_fills_each_slot_once() {
+    let mut avatar = AvatarAccountV2::default();
+    let hat = Pubkey::new_unique();
+
+    assert!(avatar.merge(4, hat, 10).is_ok());
+    assert_eq!(merged(&avatar)[3], Some((hat, 10)));
+    assert!(matches!(avatar.merge(4, Pubkey::new_unique(), 11), Err(SlaErrors::MergeCheckFailed)));
+    assert!(matches!(avatar.merge(0, hat, 10), Err(SlaErrors::TraitTypeInvalid)));
+    assert!(matches!(avatar.merge(6, hat, 10), Err(SlaErrors::TraitTypeInvalid)));
+  }
+
+  #[test]
+  fn unmerge_empties_the_slot() {
+    let mut avatar = AvatarAccountV2::default();
+    let hat = Pubkey::new_unique();
+
+    assert!(matches!(avatar.unmerge(4), Err(SlaErrors::UnmergeCheckFailed)));
+    avatar.merge(4, hat, 10).unwrap();
+    let unmerged = avatar.unmerge(4).unwrap();
+    assert_eq!((unmerged.mint, unmerged.slot), (hat, 10));
+    assert!(avatar.hat.is_none());
+    assert!(matches!(avatar.unmerge(4), Err(SlaErrors::UnmergeCheckFailed)));
+  }
+
+  #[test]
+  fn swap_keeps_the_slot_filled() {
+    let mut avatar = AvatarAccountV2::default();
+    let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+    assert!(matches!(avatar.swap(4, first, 10), Err(SlaErrors::UnmergeCheckFailed)));
+    assert!(avatar.hat.is_none());
+
+    avatar.merge(4, first, 10).unwrap();
+    let replaced = avatar.swap(4, second, 20).unwrap();
+    assert_eq!((replaced.mint, replaced.slot), (first, 10));
+    assert_eq!(merged(&avatar)[3], Some((second, 20)));
+  }
+
+  #[test]
+  fn from_v1_carries_the_legacy_traits() {
+    let mut avatar = AvatarAccountV2::default();
+    let hat = Pubkey::new_unique();
+    avatar.merge(4, hat, 10).unwrap();
+
+    avatar.from_v1(&avatar_v1([true, false, false, true, false]));
+    assert!(avatar.migrated);
+    assert_eq!(merged(&avatar)[0], Some((Pubkey::default(), 0)));
+    // Traits merged in V2 are kept over the legacy flags
+    assert_eq!(merged(&avatar)[3], Some((hat, 10)));
+    assert!(avatar.clothing.is_none());
+  }
+
+  #[test]
+  fn scanners_respect_the_limit_and_cooldown() {
+    let mut record = ScannerRecord::default();
+
+    assert!(record.record_mint(100, 2, 60).is_ok());
+    assert!(matches!(record.record_mint(159, 2, 60), Err(SlaErrors::ScannerAlreadyMinted)));
+    assert!(record.record_mint(160, 2, 60).is_ok());
+    assert!(matches!(record.record_mint(1_000, 2, 60), Err(SlaErrors::ScannerAlreadyMinted)));
+    assert_eq!(record.minted, 2);
+    assert_eq!(record.last_minted_at, 160);
+  }
+
+  #[test]
+  fn aliases_are_validated() {
+    assert!(AliasRecord::validate("Agent Smith").is_ok());
+    assert!(AliasRecord::validate("neo_01.v-2").is_ok());
+    assert!(AliasRecord::validate(&"a".repeat(32)).is_ok());
+    assert!(matches!(AliasRecord::validate(""), Err(SlaErrors::InvalidAliasLength)));
+    assert!(matches!(AliasRecord::validate(&"a".repeat(33)), Err(SlaErrors::InvalidAliasLength)));
+    assert!(matches!(AliasRecord::validate("Agent  Smith"), Err(SlaErrors::InvalidAliasCharacters)));
+    assert!(matches!(AliasRecord::validate(" Smith"), Err(SlaErrors::InvalidAliasCharacters)));
+    assert!(matches!(AliasRecord::validate("Smith!"), Err(SlaErrors::InvalidAliasCharacters)));
+    assert!(matches!(AliasRecord::validate("Smïth"), Err(SlaErrors::InvalidAliasCharacters)));
+    assert_eq!(AliasRecord::seed("Agent Smith"), AliasRecord::seed("agent smith"));
+  }
+
+  #[test]
+  fn aliases_are_claimed_by_a_single_agent() {
+    let mut record = AliasRecord::default();
+    let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+    assert!(record.claim(&first).is_ok());
+    assert!(record.claim(&first).is_ok());
+    assert!(matches!(record.claim(&second), Err(SlaErrors::AliasAlreadyTaken)));
+    assert_eq!(record.avatar_mint, first);
+  }
+
+  #[test]
+  fn alias_history_is_bounded() {
+    let mut profile = AgentProfile::default();
+    for i in 0..=AgentProfile::MAX_HISTORY + 1 {
+      profile.set_alias(format!("alias {}", i));
+    }
+
+    assert_eq!(profile.alias.as_deref(), Some("alias 6"));
+    assert_eq!(profile.history, vec!["alias 1", "alias 2", "alias 3", "alias 4", "alias 5"]);
+  }
+
+  #[test]
+  fn scans_are_recorded_at_increasing_indexes() {
+    let mut profile = AgentProfile::default();
+    let (avatar_mint, scanner_mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let clock = Clock { slot: 7, unix_timestamp: 1_000, ..Clock::default() };
+
+    for i in 0..2 {
+      let mut record = ScanRecord::default();
+      record.record(&mut profile, avatar_mint, scanner_mint, "before".to_string(), "after".to_string(), None, &clock);
+      assert_eq!(record.index, i);
+      assert_eq!((record.slot, record.timestamp), (7, 1_000));
+      assert_eq!(record.uri_after, "after");
+    }
+    assert_eq!(profile.scans, 2);
+  }
+
+  #[test]
+  fn attestation_nonces_are_used_once() {
+    let mut avatar = AvatarAccountV2::default();
+
+    assert!(avatar.use_nonce(0).is_ok());
+    assert!(matches!(avatar.use_nonce(0), Err(SlaErrors::InvalidNonce)));
+    assert!(matches!(avatar.use_nonce(2), Err(SlaErrors::InvalidNonce)));
+    assert!(avatar.use_nonce(1).is_ok());
+    assert_eq!(avatar.nonce, 2);
+  }
+
+  #[test]
+  fn metadata_uri_is_derived_from_the_base_uri() {
+    let mut config = GlobalConfig::default();
+    let avatar_mint = Pubkey::new_unique();
+
+    assert!(matches!(config.metadata_uri(&avatar_mint, 42), Err(SlaErrors::InvalidMetadataBaseUri)));
+    assert!(matches!(config.trait_uri(&avatar_mint, 42, 4), Err(SlaErrors::InvalidMetadataBaseUri)));
+    assert!(config.set_metadata_base_uri("a".repeat(GlobalConfig::MAX_BASE_URI_LEN + 1)).is_err());
+
+    config.set_metadata_base_uri("https://meta.sla.xyz/agents/".to_string()).unwrap();
+    assert_eq!(
+      config.metadata_uri(&avatar_mint, 42).unwrap(),
+      format!("https://meta.sla.xyz/agents/{}/42.json", avatar_mint),
+    );
+    assert_eq!(
+      config.trait_uri(&avatar_mint, 42, 4).unwrap(),
+      format!("https://meta.sla.xyz/agents/{}/42/4.json", avatar_mint),
+    );
+  }
+
+  #[test]
+  fn authority_set_rejects_invalid_thresholds_and_members() {
+    let mut authority_set = AuthoritySet::default();
+    let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+    assert!(authority_set.set(vec![first, second], 2).is_ok());
+    assert!(matches!(authority_set.set(vec![first, second], 0), Err(SlaErrors::InvalidAuthoritySet)));
+    assert!(matches!(authority_set.set(vec![first, second], 3), Err(SlaErrors::InvalidAuthoritySet)));
+    assert!(matches!(authority_set.set(vec![first, first], 1), Err(SlaErrors::InvalidAuthoritySet)));
+
+    let too_many = (0..=AuthoritySet::MAX_MEMBERS).map(|_| Pubkey::new_unique()).collect();
+    assert!(matches!(authority_set.set(too_many, 1), Err(SlaErrors::InvalidAuthoritySet)));
+
+    // Rejected updates leave the set untouched
+    assert_eq!(authority_set.members, vec![first, second]);
+    assert_eq!(authority_set.threshold, 2);
+  }
+
+  #[test]
+  fn rank_discounts_apply_to_the_tier_of_the_agent() {
+    let mut ladder = legacy_ladder();
+    ladder.set_tier(5, master_tier(Some(4), 1)).unwrap();
+    ladder.set_discounts(2, RankDiscounts { id_card: 1_000, scanner: 0 }).unwrap();
+    ladder.set_discounts(5, RankDiscounts { id_card: 0, scanner: 5_000 }).unwrap();
+
+    assert_eq!(ladder.discounts(Some(2)).id_card_price(100), 90);
+    assert_eq!(ladder.discounts(Some(4)).id_card_price(100), 100);
+    assert_eq!(ladder.discounts(None).id_card_price(100), 100);
+    assert_eq!(ladder.discounts(Some(5)).scanner_price(25), 12);
+
+    // Updating a tier keeps its discounts
+    ladder.set_tier(5, master_tier(Some(4), 2)).unwrap();
+    assert_eq!(ladder.discounts(Some(5)).scanner, 5_000);
+
+    let too_high = RankDiscounts { id_card: RankDiscounts::MAX_BPS + 1, scanner: 0 };
+    assert!(matches!(ladder.set_discounts(0, too_high), Err(SlaErrors::InvalidDiscount)));
+    let discounts = RankDiscounts::default();
+    assert!(matches!(ladder.set_discounts(6, discounts), Err(SlaErrors::InvalidRankTier)));
+  }
+
+  proptest! {
+    #[test]
+    fn ranking_never_skips_a_tier(upgrades in prop::collection::vec(0u8..6, 0..20)) {
+      let ladder = legacy_ladder();
+      let mut ranking = RankingV2::default();
+
+      for tier in upgrades {
+        let previous = ranking.tier();
+        if ladder.upgrade(&mut ranking, tier).is_ok() {
+          prop_assert_eq!(previous, tier.checked_sub(1));
+          prop_assert_eq!(ranking.tier(), Some(tier));
+        } else {
+          prop_assert_eq!(ranking.tier(), previous);
+        }
+      }
+    }
+
+    #[test]
+    fn rank_discounts_never_raise_the_price(price in any::<u16>(), id_card in any::<u16>(), scanner in any::<u16>()) {
+      let discounts = RankDiscounts { id_card, scanner };
+      prop_assert!(discounts.id_card_price(price) <= price);
+      prop_assert!(discounts.scanner_price(price) <= price);
+    }
+
+    #[test]
+    fn a_badge_is_never_minted_twice(
+      start in any_ranking_v2(),
+      actions in prop::collection::vec((0u8..6, any::<bool>()), 0..40),
+    ) {
+      let mut ladder = legacy_ladder();
+      let mut ranking = ranking_v2(start);
+      let mut successful_mints = [0u8; 5];
+
+      for (tier, upgrade) in actions {
+        if upgrade {
+          let _ = ladder.upgrade(&mut ranking, tier);
+          continue;
+        }
+
+        let before = minted(&ranking);
+        if ladder.mint(&mut ranking, tier).is_ok() {
+          let index = usize::from(tier);
+          prop_assert!(!before[index]);
+          successful_mints[index] += 1;
+        }
+        prop_assert!(successful_mints.iter().all(|count| *count <= 1));
+      }
+    }
+
+    #[test]
+    fn mint_only_sets_the_minted_badge(start in any_ranking_v2(), tier in 0u8..6) {
+      let mut ladder = legacy_ladder();
+      let start = ranking_v2(start);
+      let mut ranking = start;
+      let before = minted(&ranking);
+
+      match ladder.mint(&mut ranking, tier) {
+        Ok(()) => {
+          let after = minted(&ranking);
+          for (index, (after, before)) in after.iter().zip(before.iter()).enumerate() {
+            prop_assert_eq!(*after, *before || index == usize::from(tier));
+          }
+        },
+        Err(_) => prop_assert_eq!(minted(&ranking), before),
+      }
+      prop_assert_eq!(ranking.ranking, start.ranking);
+    }
+
+    #[test]
+    fn migrate_never_unmints_a_badge(start in any_ranking_v2(), v1 in any_ranking()) {
+      let start = ranking_v2(start);
+      let mut ranking = start;
+      ranking.migrate(Some(&ranking_v1(v1)), &Pubkey::new_unique()).unwrap();
+
+      for (after, before) in minted(&ranking).iter().zip(minted(&start).iter()) {
+        prop_assert!(*after || !*before);
+      }
+    }
+
+    #[test]
+    fn merges_are_monotone(trait_ids in prop::collection::vec(0u8..8, 0..30)) {
+      let mut avatar = AvatarAccountV2::default();
+
+      for (slot, trait_id) in trait_ids.into_iter().enumerate() {
+        let mint = Pubkey::new_unique();
+        let before = merged(&avatar);
+        let result = avatar.merge(trait_id, mint, slot as u64);
+        let after = merged(&avatar);
+
+        for (index, (was, is)) in before.iter().zip(after.iter()).enumerate() {
+          // Only the merged slot can change, and only from empty to merged
+          prop_assert!(is.is_some() || was.is_none());
+          if is != was {
+            prop_assert!(result.is_ok());
+            prop_assert_eq!(index + 1, trait_id as usize);
+            prop_assert_eq!(*is, Some((mint, slot as u64)));
+          }
+        }
+        if result.is_ok() {
+          prop_assert!(before[trait_id as usize - 1].is_none());
+        }
+      }
+    }
+
+    #[test]
+    fn swap_keeps_the_slot_filled(
+      operations in prop::collection::vec((0u8..3, 0u8..8), 0..30),
+    ) {
+      let mut avatar = AvatarAccountV2::default();
+
+      for (slot, (operation, trait_id)) in operations.into_iter().enumerate() {
+        let mint = Pubkey::new_unique();
+        let before = merged(&avatar);
+        let result = match operation {
+          0 => avatar.merge(trait_id, mint, slot as u64).map(|_| None),
+          1 => avatar.unmerge(trait_id).map(|unmerged| Some((unmerged.mint, unmerged.slot))),
+          _ => avatar.swap(trait_id, mint, slot as u64).map(|replaced| Some((replaced.mint, replaced.slot))),
+        };
+        let after = merged(&avatar);
+
+        match result {
+          // A failed operation leaves every slot untouched
+          Err(_) => prop_assert_eq!(after, before),
+          Ok(returned) => {
+            let index = trait_id as usize - 1;
+            match operation {
+              0 => prop_assert_eq!(after[index], Some((mint, slot as u64))),
+              1 => {
+                prop_assert_eq!(returned, before[index]);
+                prop_assert_eq!(after[index], None);
+              }
+              _ => {
+                prop_assert_eq!(returned, before[index]);
+                prop_assert_eq!(after[index], Some((mint, slot as u64)));
+              }
+            }
+          }
+        }
+      }
+    }
+
+    #[test]
+    fn avatar_from_v1_is_idempotent(
+      merged_v2 in any::<[bool; 5]>(),
+      traits_v1 in any::<[bool; 5]>(),
+      migrated in any::<bool>(),
+    ) {
+      let mut once = AvatarAccountV2 { migrated, ..AvatarAccountV2::default() };
+      for (index, merged_v2) in merged_v2.iter().enumerate() {
+        if *merged_v2 {
+          once.merge(index as u8 + 1, Pubkey::new_unique(), 1).unwrap();
+        }
+      }
+      let before = merged(&once);
+      once.from_v1(&avatar_v1(traits_v1));
+
+      let mut twice = once;
+      twice.from_v1(&avatar_v1(traits_v1));
+      prop_assert_eq!(merged(&twice), merged(&once));
+      prop_assert!(twice.migrated);
+
+      // Migrating never drops or replaces a trait merged in V2
+      for (after, before) in merged(&once).iter().zip(before.iter()) {
+        if before.is_some() {
+          prop_assert_eq!(after, before);
+        }
+      }
+    }
+  }
+}
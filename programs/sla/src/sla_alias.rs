@@ -0,0 +1,38 @@
+use anchor_lang::prelude::*;
+
+use crate::sla_accounts::{AgentProfile, AliasRecord};
+use crate::sla_constants;
+use crate::utils::{close_account, find_pda};
+use crate::SlaErrors;
+
+// Claims the new alias for the agent, releases its previous one (closing the record and
+// refunding its rent to the payer) and records the change in the agent profile
+pub fn register_alias<'info>(
+  alias_record: &mut Account<'info, AliasRecord>,
+  previous_alias_record: &AccountInfo<'info>,
+  agent_profile: &mut Account<'info, AgentProfile>,
+  avatar_mint: &Pubkey,
+  payer: &AccountInfo<'info>,
+  new_alias: &str,
+) -> ProgramResult {
+  AliasRecord::validate(new_alias)?;
+
+  let seed = AliasRecord::seed(new_alias);
+  alias_record.claim(avatar_mint)?;
+  alias_record.bump = find_pda(&[sla_constants::PREFIX_ALIAS.as_bytes(), &seed]).1;
+
+  if let Some(previous_alias) = &agent_profile.alias {
+    let previous_seed = AliasRecord::seed(previous_alias);
+    if previous_seed != seed {
+      let expected = find_pda(&[sla_constants::PREFIX_ALIAS.as_bytes(), &previous_seed]).0;
+      if previous_alias_record.key() != expected {
+        return Err(SlaErrors::InvalidPubkey.into());
+      }
+      close_account(previous_alias_record, payer)?;
+    }
+  }
+
+  agent_profile.set_alias(new_alias.to_string());
+  agent_profile.store_bump(avatar_mint);
+  Ok(())
+}
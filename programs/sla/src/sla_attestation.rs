@@ -0,0 +1,119 @@
+use anchor_lang::prelude::*;
+use solana_program::sysvar::instructions::{load_current_index_checked, load_instruction_at_checked};
+use std::convert::TryInto;
+
+use crate::sla_constants;
+use crate::utils::assert_address;
+use crate::SlaErrors;
+
+// Ed25519 program instruction data: signature count + padding, then one offsets entry per signature
+const SIGNATURE_OFFSETS_START: usize = 2;
+const SIGNATURE_OFFSETS_LEN: usize = 14;
+const PUBKEY_LEN: usize = 32;
+
+// Prefix of every attestation message, so that renderer signatures are never valid for anything else
+pub const ATTESTATION_DOMAIN: &[u8] = b"sla_uri_attestation";
+
+// Instruction an attestation is signed for: an attestation for one of them is rejected by the others
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AttestedAction<'a> {
+  Merge { trait_mint: Pubkey },
+  ChangeAlias { new_name: &'a str },
+  Scan,
+  MergeBadge { asset_id: u8 },
+}
+
+impl AttestedAction<'_> {
+  fn tag(&self) -> u8 {
+    match self {
+      AttestedAction::Merge { .. } => 1,
+      AttestedAction::ChangeAlias { .. } => 2,
+      AttestedAction::Scan => 3,
+      AttestedAction::MergeBadge { .. } => 4,
+    }
+  }
+}
+
+// Message signed by the renderer: domain, action tag, avatar mint, nonce and expiry (little endian),
+// the merged trait mint, alias (length prefixed) or badge asset ID of the action if any, then the URI
+pub fn attestation_message(
+  action: AttestedAction,
+  avatar_mint: &Pubkey,
+  uri: &str,
+  nonce: u64,
+  expiry: i64,
+) -> Vec<u8> {
+  let mut message = Vec::with_capacity(ATTESTATION_DOMAIN.len() + 1 + 32 + 8 + 8 + uri.len());
+  message.extend_from_slice(ATTESTATION_DOMAIN);
+  message.push(action.tag());
+  message.extend_from_slice(&avatar_mint.to_bytes());
+  message.extend_from_slice(&nonce.to_le_bytes());
+  message.extend_from_slice(&expiry.to_le_bytes());
+  match action {
+    AttestedAction::ChangeAlias { new_name } => {
+      message.extend_from_slice(&(new_name.len() as u32).to_le_bytes());
+      message.extend_from_slice(new_name.as_bytes());
+    }
+    AttestedAction::Merge { trait_mint } => message.extend_from_slice(&trait_mint.to_bytes()),
+    AttestedAction::MergeBadge { asset_id } => message.push(asset_id),
+    AttestedAction::Scan => {}
+  }
+  message.extend_from_slice(uri.as_bytes());
+  message
+}
+
+// The instruction right before this one must be an Ed25519 program instruction verifying a
+// single signature of the renderer over the attestation message
+pub fn verify_attestation(
+  instructions: &AccountInfo,
+  renderer: &Pubkey,
+  action: AttestedAction,
+  avatar_mint: &Pubkey,
+  uri: &str,
+  nonce: u64,
+  expiry: i64,
+) -> Result<(), ProgramError> {
+  if Clock::get()?.unix_timestamp > expiry {
+    return Err(SlaErrors::AttestationExpired.into());
+  }
+  if *renderer == Pubkey::default() {
+    return Err(SlaErrors::InvalidAttestation.into());
+  }
+
+  let current_index = load_current_index_checked(instructions)?;
+  if current_index == 0 {
+    return Err(SlaErrors::InvalidAttestation.into());
+  }
+  let ed25519_instruction = load_instruction_at_checked(usize::from(current_index - 1), instructions)?;
+  if !assert_address(&ed25519_instruction.program_id, sla_constants::ED25519_PROGRAM) {
+    return Err(SlaErrors::InvalidAttestation.into());
+  }
+
+  let expected = attestation_message(action, avatar_mint, uri, nonce, expiry);
+  match signed_message(&ed25519_instruction.data) {
+    Some((pubkey, message)) if pubkey == renderer.as_ref() && message == expected.as_slice() => Ok(()),
+    _ => Err(SlaErrors::InvalidAttestation.into()),
+  }
+}
+
+// Public key + message of the single signature verified by the instruction. Offsets pointing to
+// other instructions are rejected so that both are read from this instruction data
+fn signed_message(data: &[u8]) -> Option<(&[u8], &[u8])> {
+  if data.len() < SIGNATURE_OFFSETS_START + SIGNATURE_OFFSETS_LEN || data[0] != 1 {
+    return None;
+  }
+
+  let offsets = &data[SIGNATURE_OFFSETS_START..SIGNATURE_OFFSETS_START + SIGNATURE_OFFSETS_LEN];
+  let read = |i: usize| usize::from(u16::from_le_bytes(offsets[i..i + 2].try_into().unwrap()));
+  let (signature_index, pubkey_offset, pubkey_index) = (read(2), read(4), read(6));
+  let (message_offset, message_len, message_index) = (read(8), read(10), read(12));
+
+  let this_instruction = usize::from(u16::MAX);
+  if signature_index != this_instruction || pubkey_index != this_instruction || message_index != this_instruction {
+    return None;
+  }
+
+  let pubkey = data.get(pubkey_offset..pubkey_offset.checked_add(PUBKEY_LEN)?)?;
+  let message = data.get(message_offset..message_offset.checked_add(message_len)?)?;
+  Some((pubkey, message))
+}
@@ -7,7 +7,7 @@ use crate::SlaErrors;
 
 
 pub fn check_collection(
-  collection_member: mpl_token_metadata::state::Metadata,
+  collection_member: &mpl_token_metadata::state::Metadata,
   expected: &Pubkey,
 ) -> bool {
   match &collection_member.collection {
@@ -18,6 +18,19 @@ pub fn check_collection(
   }
 }
 
+// Checks that at least one of the expected creators is a verified creator of the NFT
+pub fn check_creators(
+  metadata: &mpl_token_metadata::state::Metadata,
+  expected: &[Pubkey],
+) -> bool {
+  match &metadata.data.creators {
+    Some(creators) => creators
+      .iter()
+      .any(|creator| creator.verified && expected.contains(&creator.address)),
+    None => false,
+  }
+}
+
 // Returns the collection key and the trait ID if the trait is part of one of the trait collections
 pub fn extract_trait_id(trait_metadata: mpl_token_metadata::state::Metadata) -> Result<(Pubkey, u8), SlaErrors> {
 
@@ -41,4 +54,35 @@ pub fn extract_trait_id(trait_metadata: mpl_token_metadata::state::Metadata) ->
     },
     None => Err(SlaErrors::TraitNotInVerifiedCollection)
   }
-}
\ No newline at end of file
+}
+
+// Returns the collection key of the given trait ID (inverse of `extract_trait_id`)
+pub fn get_trait_collection(trait_id: u8) -> Result<Pubkey, SlaErrors> {
+  match trait_id {
+    1 => Ok(str_to_pubkey(sla_constants::SKIN_COLLECTION)),
+    2 => Ok(str_to_pubkey(sla_constants::CLOTHING_COLLECTION)),
+    3 => Ok(str_to_pubkey(sla_constants::EYES_COLLECTION)),
+    4 => Ok(str_to_pubkey(sla_constants::HAT_COLLECTION)),
+    5 => Ok(str_to_pubkey(sla_constants::MOUTH_COLLECTION)),
+    _ => Err(SlaErrors::TraitTypeInvalid),
+  }
+}
+
+// Name of the traits of the given trait ID re-minted by `unmerge` / `swap_trait`
+pub fn get_trait_name(trait_id: u8) -> Result<&'static str, SlaErrors> {
+  match trait_id {
+    1 => Ok("Skin"),
+    2 => Ok("Clothing"),
+    3 => Ok("Eyes"),
+    4 => Ok("Hat"),
+    5 => Ok("Mouth"),
+    _ => Err(SlaErrors::TraitTypeInvalid),
+  }
+}
+
+// Checks the name and symbol are the ones the program gives to the re-minted traits of the given trait ID
+// (Metaplex pads them with null bytes)
+pub fn is_returned_trait(data: &mpl_token_metadata::state::Data, trait_id: u8) -> bool {
+  get_trait_name(trait_id).map_or(false, |name| data.name.trim_end_matches('\0') == name)
+    && data.symbol.trim_end_matches('\0') == sla_constants::TRAIT_SYMBOL
+}
@@ -1,45 +1,26 @@
 
 // PDA SEEDS
 pub const PREFIX_LLAMA: &str = "sla_llama";
+pub const PREFIX_LLAMA_V2: &str = "sla_llama_v2";
 pub const PREFIX_TREASURY: &str = "sla_treasury";
 pub const PREFIX_RANKING: &str = "sla_ranking";
 pub const PREFIX_RANKING_V2: &str = "sla_ranking_v2";
+pub const PREFIX_RANKING_V3: &str = "sla_ranking_v3";
 pub const PREFIX_BADGE_POT: &str = "sla_badge_pot";
+pub const PREFIX_RANK_LADDER: &str = "sla_rank_ladder";
 pub const PREFIX_SCANNER: &str = "sla_scanner";
-
-// PRICES (in $HAY)
-pub const PRICE_ID_CARD: u16 = 60;
-pub const PRICE_BADGE_BRONZE: u16 = 60;
-pub const PRICE_BADGE_SILVER: u16 = 150;
-pub const PRICE_BADGE_GOLD: u16 = 210;
-pub const PRICE_BADGE_PLATINUM: u16 = 405;
-pub const PRICE_BADGE_DIAMOND: u16 = 660;
-
-// TOTAL SUPPLIES
-pub const SUPPLY_BADGE_BRONZE: u16 = 3000;
-pub const SUPPLY_BADGE_SILVER: u16 = 2100;
-pub const SUPPLY_BADGE_GOLD: u16 = 1350;
-pub const SUPPLY_BADGE_PLATINUM: u16 = 750;
-pub const SUPPLY_BADGE_DIAMOND: u16 = 300;
-
-// WALLET PUBKEYS
-pub const COMBINE_AUTHORITY_WALLET: &str = "2Pi1TvYf8Nku8ppq3Pn4ZEHDNo8fFhZjcLaRHi17Au4C";
-
-// HAY TOKEN
-pub const HAY_TOKEN_MINT: &str = "CcaKx6adqp8wDeksiR15x8HMgBzjz8QY6pJCmRZQPXAB";
-pub const HAY_TREASURY_WALLET_ATA: &str = "EranN9BrZUtNypY6byq5zRpBukvhAxfRxTErYFm2syXJ";
-
-// FUNGIBLE ASSETS
-pub const ID_CARD_MINT: &str = "9mxy4zbRkb4CJbWz3cyjgVas3ok9dqdAZPWHQD34o2Jo";
-pub const BADGE_BRONZE_MINT: &str = "AxJohkM2mvtXzkSf6oLy4NjXtrvJUehiMAPjsJ6V1S1u";
-pub const BADGE_SILVER_MINT: &str = "HVXkw7SaH3i1KKhisgVfE8meUpdawyo6BtjX1YvRCEHL";
-pub const BADGE_GOLD_MINT: &str = "6MsxoFAhvfL4DcYniQ4FoMdhaRATgzkG1XwyNdLKeMc2";
-pub const BADGE_PLATINUM_MINT: &str = "HZktv6wRgAaLJSoGsXcEhZbJaH6CsmFnuXLbZ93h8LZt";
-pub const BADGE_DIAMOND_MINT: &str = "gQrMXw5EpWVmiikvapTxcHGuaDH3NYbTJwC9voyqfFG";
-
-// SCANNER
-pub const SCANNER_MINT: &str = "D2RDTgj4HPKq6k6Fqz9cQBHbLv7NHT6gQEtuH3kfNC3m";
-pub const PRICE_SCANNER: u16 = 10;
+pub const PREFIX_CONFIG: &str = "sla_config";
+pub const PREFIX_AUTHORITY_SET: &str = "sla_authority_set";
+pub const PREFIX_UPDATE_AUTHORITY: &str = "sla_update_authority";
+pub const PREFIX_ALIAS: &str = "sla_alias";
+pub const PREFIX_PROFILE: &str = "sla_profile";
+pub const PREFIX_SCAN: &str = "sla_scan";
+
+// Symbol of the traits re-minted by `unmerge` / `swap_trait`
+pub const TRAIT_SYMBOL: &str = "SLA";
+
+// NATIVE PROGRAMS
+pub const ED25519_PROGRAM: &str = "Ed25519SigVerify111111111111111111111111111";
 
 // HARD-CODED COLLECTION KEYS
 pub const LLAMA_COLLECTION: &str = "GqxAoZaqww9zp6y5RddRhExrwTVySfAjLnheaJVjSvag";
@@ -7,7 +7,7 @@ pub enum SlaErrors {
 
   /*** GENERAL ERRROS ***/
   
-  #[msg("Invalid hard-coded pubkey")]
+  #[msg("Pubkey does not match the global config")]
   InvalidPubkey,
 
   /*** CHECK AVATAR OWNERSHIP ***/
@@ -106,4 +106,76 @@ pub enum SlaErrors {
   #[msg("The trait cannot be minted by this avatar")]
   AvatarCannotMintTrait,
 
+  // New variants must be appended below so existing error numbers stay stable: the variants
+  // below are listed in the order they were added, and never reordered or inserted mid-enum
+
+  #[msg("The Avatar does not have a Trait merged in this slot")]
+  UnmergeCheckFailed,
+
+  #[msg("The collection does not match the Trait type")]
+  TraitCollectionMismatch,
+
+  #[msg("Asset ID does not match any fungible asset")]
+  UnknownAssetId,
+
+  #[msg("The Metaplex metadata account could not be parsed")]
+  InvalidMetadataAccount,
+
+  #[msg("Asset ID does not match an ID card")]
+  AssetIsNotIdCard,
+
+  #[msg("The price in $HAY base units does not fit in a u64")]
+  PriceOverflow,
+
+  #[msg("This instruction is paused")]
+  ProgramPaused,
+
+  #[msg("The authority set members or threshold are not valid")]
+  InvalidAuthoritySet,
+
+  #[msg("Not enough members of the authority set approved this instruction")]
+  NotEnoughApprovals,
+
+  #[msg("The metadata base URI is not set or too long")]
+  InvalidMetadataBaseUri,
+
+  #[msg("The update authority of this agent has not been transferred to the program")]
+  UpdateAuthorityNotTransferred,
+
+  #[msg("The URI attestation is missing or not signed by the renderer")]
+  InvalidAttestation,
+
+  #[msg("The URI attestation has expired")]
+  AttestationExpired,
+
+  #[msg("The URI attestation nonce does not match the agent nonce")]
+  InvalidNonce,
+
+  #[msg("The alias must be between 1 and 32 bytes long")]
+  InvalidAliasLength,
+
+  #[msg("The alias can only contain letters, digits, single inner spaces, '-', '_' and '.'")]
+  InvalidAliasCharacters,
+
+  #[msg("This alias is already used by another agent")]
+  AliasAlreadyTaken,
+
+  #[msg("The scan target is not another agent of the collection")]
+  InvalidScanTarget,
+
+  #[msg("The ranking of this agent must be migrated with migrate_ranking first")]
+  RankingNotMigrated,
+
+  #[msg("The ranking of this agent has already been migrated")]
+  RankingAlreadyMigrated,
+
+  #[msg("A revoked ranking must be a badge below the current ranking")]
+  InvalidRankRevocation,
+
+  #[msg("This tier is not in the rank ladder, or its prerequisite is not a lower tier")]
+  InvalidRankTier,
+
+  #[msg("A rank discount cannot be above 10000 basis points")]
+  InvalidDiscount,
+
 }
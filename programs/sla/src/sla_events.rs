@@ -0,0 +1,133 @@
+use anchor_lang::prelude::*;
+
+use crate::sla_fungible_token::FungibleAsset;
+
+
+#[event]
+pub struct TraitMerged {
+  pub avatar_mint: Pubkey,
+  pub user: Pubkey,
+  pub trait_mint: Pubkey,
+  pub trait_id: u8,
+  pub slot: u64,
+  pub metadata_uri: String,
+}
+
+#[event]
+pub struct TraitUnmerged {
+  pub avatar_mint: Pubkey,
+  pub user: Pubkey,
+  pub trait_id: u8,
+  // Mint that was merged into the slot (default pubkey for traits merged before AvatarAccountV2)
+  pub unmerged_mint: Pubkey,
+  pub returned_trait_mint: Pubkey,
+  pub metadata_uri: String,
+}
+
+#[event]
+pub struct TraitSwapped {
+  pub avatar_mint: Pubkey,
+  pub user: Pubkey,
+  pub trait_id: u8,
+  pub replaced_mint: Pubkey,
+  pub trait_mint: Pubkey,
+  pub returned_trait_mint: Pubkey,
+  pub slot: u64,
+  pub metadata_uri: String,
+}
+
+#[event]
+pub struct FungibleAssetMinted {
+  pub user: Pubkey,
+  // Only set for assets that require an agent (badges and scanners)
+  pub avatar_mint: Option<Pubkey>,
+  pub mint: Pubkey,
+  // None for scanning devices
+  pub asset: Option<FungibleAsset>,
+  // Paid after the rank discount of the agent, if any
+  pub price: u16,
+  pub list_price: u16,
+}
+
+#[event]
+pub struct AliasChanged {
+  pub avatar_mint: Pubkey,
+  pub user: Pubkey,
+  pub new_name: String,
+  pub metadata_uri: String,
+}
+
+#[event]
+pub struct AgentScanned {
+  pub avatar_mint: Pubkey,
+  pub user: Pubkey,
+  pub scanner_mint: Pubkey,
+  pub metadata_uri: Option<String>,
+  pub scan_index: u32,
+  pub target_mint: Option<Pubkey>,
+}
+
+#[event]
+pub struct AuthoritySetUpdated {
+  pub members: Vec<Pubkey>,
+  pub threshold: u8,
+}
+
+#[event]
+pub struct CombineAuthorityRotated {
+  pub old_combine_authority: Pubkey,
+  pub new_combine_authority: Pubkey,
+}
+
+#[event]
+pub struct UpdateAuthorityTransferred {
+  pub avatar_mint: Pubkey,
+  pub metadata_authority: Pubkey,
+}
+
+// Badge of a tier of the rank ladder (`mint_badge_v4`)
+#[event]
+pub struct TierBadgeMinted {
+  pub user: Pubkey,
+  pub avatar_mint: Pubkey,
+  pub mint: Pubkey,
+  pub tier: u8,
+  pub price: u16,
+}
+
+#[event]
+pub struct TierMerged {
+  pub avatar_mint: Pubkey,
+  pub user: Pubkey,
+  pub tier: u8,
+  pub metadata_uri: String,
+}
+
+#[event]
+pub struct RankRevoked {
+  pub avatar_mint: Pubkey,
+  pub admin: Pubkey,
+  // Rank ladder tiers, None for an agent without a rank
+  pub previous_tier: Option<u8>,
+  pub tier: Option<u8>,
+  // Off-chain code explaining the revocation
+  pub reason: u8,
+  pub allow_remint: bool,
+}
+
+#[event]
+pub struct RankingMigrated {
+  pub avatar_mint: Pubkey,
+  pub user: Pubkey,
+  pub ranking: Option<FungibleAsset>,
+  // Lamports of the closed V1 account refunded to the user
+  pub refunded: u64,
+}
+
+#[event]
+pub struct BadgeAdded {
+  pub avatar_mint: Pubkey,
+  pub authority: Pubkey,
+  pub badge: FungibleAsset,
+  pub ranking: Option<FungibleAsset>,
+}
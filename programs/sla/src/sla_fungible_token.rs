@@ -1,7 +1,8 @@
+use std::convert::TryFrom;
 use std::fmt;
 use anchor_lang::prelude::*;
 
-use crate::{sla_constants, utils, sla_token};
+use crate::{sla_constants, sla_token, sla_accounts::GlobalConfig, SlaErrors};
 
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, AnchorSerialize, AnchorDeserialize)]
@@ -14,53 +15,23 @@ pub enum FungibleAsset {
   BADGE_DIAMOND = 6,
 }
 
-impl FungibleAsset {
-  pub fn from_u8(value: u8) -> FungibleAsset {
-    match value {
-      1 => FungibleAsset::ID_CARD,
-      2 => FungibleAsset::BADGE_BRONZE,
-      3 => FungibleAsset::BADGE_SILVER,
-      4 => FungibleAsset::BADGE_GOLD,
-      5 => FungibleAsset::BADGE_PLATINUM,
-      6 => FungibleAsset::BADGE_DIAMOND,
-      _ => panic!("Unknown value: {}", value),
-    }
-  }
-
-  pub fn get_mint(&self) -> &str {
-    match self {
-      FungibleAsset::ID_CARD => sla_constants::ID_CARD_MINT,
-      FungibleAsset::BADGE_BRONZE => sla_constants::BADGE_BRONZE_MINT,
-      FungibleAsset::BADGE_SILVER => sla_constants::BADGE_SILVER_MINT,
-      FungibleAsset::BADGE_GOLD => sla_constants::BADGE_GOLD_MINT,
-      FungibleAsset::BADGE_PLATINUM => sla_constants::BADGE_PLATINUM_MINT,
-      FungibleAsset::BADGE_DIAMOND => sla_constants::BADGE_DIAMOND_MINT,
-    }
-  }
+impl TryFrom<u8> for FungibleAsset {
+  type Error = SlaErrors;
 
-  pub fn get_price(&self) -> u16 {
-    match self {
-      FungibleAsset::ID_CARD => sla_constants::PRICE_ID_CARD,
-      FungibleAsset::BADGE_BRONZE => sla_constants::PRICE_BADGE_BRONZE,
-      FungibleAsset::BADGE_SILVER => sla_constants::PRICE_BADGE_SILVER,
-      FungibleAsset::BADGE_GOLD => sla_constants::PRICE_BADGE_GOLD,
-      FungibleAsset::BADGE_PLATINUM => sla_constants::PRICE_BADGE_PLATINUM,
-      FungibleAsset::BADGE_DIAMOND => sla_constants::PRICE_BADGE_DIAMOND,
+  fn try_from(value: u8) -> Result<Self, Self::Error> {
+    match value {
+      1 => Ok(FungibleAsset::ID_CARD),
+      2 => Ok(FungibleAsset::BADGE_BRONZE),
+      3 => Ok(FungibleAsset::BADGE_SILVER),
+      4 => Ok(FungibleAsset::BADGE_GOLD),
+      5 => Ok(FungibleAsset::BADGE_PLATINUM),
+      6 => Ok(FungibleAsset::BADGE_DIAMOND),
+      _ => Err(SlaErrors::UnknownAssetId),
     }
   }
+}
 
-  pub fn get_max_supply(&self) -> u64 {
-    let supply = match self {
-      FungibleAsset::BADGE_BRONZE => sla_constants::SUPPLY_BADGE_BRONZE,
-      FungibleAsset::BADGE_SILVER => sla_constants::SUPPLY_BADGE_SILVER,
-      FungibleAsset::BADGE_GOLD => sla_constants::SUPPLY_BADGE_GOLD,
-      FungibleAsset::BADGE_PLATINUM => sla_constants::SUPPLY_BADGE_PLATINUM,
-      FungibleAsset::BADGE_DIAMOND => sla_constants::SUPPLY_BADGE_DIAMOND,
-      _ => 0,
-    };
-    u64::from(supply)
-  }
-
+impl FungibleAsset {
   pub fn is_badge(&self) -> bool {
     match &self {
       FungibleAsset::BADGE_BRONZE | 
@@ -87,9 +58,20 @@ impl fmt::Display for FungibleAsset {
 }
 
 
-pub fn assert_mint_address<'info>(mint_given: &Pubkey, asset_id: u8) -> bool {
-  let expected = FungibleAsset::from_u8(asset_id);
-  utils::assert_address(mint_given, expected.get_mint())
+pub fn assert_mint_address<'info>(config: &GlobalConfig, mint_given: &Pubkey, asset_id: u8) -> bool {
+  match FungibleAsset::try_from(asset_id) {
+    Ok(expected) => *mint_given == config.get_mint(expected),
+    Err(_) => false,
+  }
+}
+
+
+// Prices are expressed in whole $HAY, transfers are made in base units of the mint
+fn to_base_units(price: u16, decimals: u8) -> Result<u64, SlaErrors> {
+  10u64
+    .checked_pow(u32::from(decimals))
+    .and_then(|unit| unit.checked_mul(u64::from(price)))
+    .ok_or(SlaErrors::PriceOverflow)
 }
 
 
@@ -100,55 +82,91 @@ pub fn mint_fungible_asset<'info>(
   treasury: AccountInfo<'info>,
   hay_user_ata: AccountInfo<'info>,
   hay_treasury_ata: AccountInfo<'info>,
+  hay_mint: AccountInfo<'info>,
+  hay_decimals: u8,
   token_program: AccountInfo<'info>,
   fungible_asset: FungibleAsset,
+  price: u16,
   treasury_bump: u8,
 ) -> ProgramResult {
+  msg!("Minting a new {}", fungible_asset.to_string());
+  mint_for_hay(
+    mint, ata, user, treasury, hay_user_ata, hay_treasury_ata, hay_mint, hay_decimals, token_program, price, treasury_bump,
+  )
+}
 
-  let signer_seeds = &[&[sla_constants::PREFIX_TREASURY.as_bytes(), bytemuck::bytes_of(&treasury_bump)][..]];
-  let price = fungible_asset.get_price();
 
-  msg!("Minting a new {}", fungible_asset.to_string());
-  sla_token::mint_tokens(mint, ata, treasury, token_program.clone(), Some(signer_seeds), 1)?;
+pub fn mint_scanner<'info>(
+  mint: AccountInfo<'info>,
+  ata: AccountInfo<'info>,
+  user: AccountInfo<'info>,
+  treasury: AccountInfo<'info>,
+  hay_user_ata: AccountInfo<'info>,
+  hay_treasury_ata: AccountInfo<'info>,
+  hay_mint: AccountInfo<'info>,
+  hay_decimals: u8,
+  token_program: AccountInfo<'info>,
+  price: u16,
+  treasury_bump: u8,
+) -> ProgramResult {
+  msg!("Minting a new Scanner");
+  mint_for_hay(
+    mint, ata, user, treasury, hay_user_ata, hay_treasury_ata, hay_mint, hay_decimals, token_program, price, treasury_bump,
+  )
+}
 
-  msg!("Transferring {} $HAY to treasury", price);
-  sla_token::transfer_tokens(
-    hay_user_ata, 
-    hay_treasury_ata, 
-    user,
-    token_program, 
-    u64::from(price),
-  )?;
-  
-  Ok(())
+
+// Badge of a tier of the rank ladder
+pub fn mint_tier_badge<'info>(
+  mint: AccountInfo<'info>,
+  ata: AccountInfo<'info>,
+  user: AccountInfo<'info>,
+  treasury: AccountInfo<'info>,
+  hay_user_ata: AccountInfo<'info>,
+  hay_treasury_ata: AccountInfo<'info>,
+  hay_mint: AccountInfo<'info>,
+  hay_decimals: u8,
+  token_program: AccountInfo<'info>,
+  tier: u8,
+  price: u16,
+  treasury_bump: u8,
+) -> ProgramResult {
+  msg!("Minting a new badge of tier {}", tier);
+  mint_for_hay(
+    mint, ata, user, treasury, hay_user_ata, hay_treasury_ata, hay_mint, hay_decimals, token_program, price, treasury_bump,
+  )
 }
 
 
-pub fn mint_scanner<'info>(
+// Mints one token to the user, paid `price` $HAY to the treasury
+fn mint_for_hay<'info>(
   mint: AccountInfo<'info>,
   ata: AccountInfo<'info>,
   user: AccountInfo<'info>,
   treasury: AccountInfo<'info>,
   hay_user_ata: AccountInfo<'info>,
   hay_treasury_ata: AccountInfo<'info>,
+  hay_mint: AccountInfo<'info>,
+  hay_decimals: u8,
   token_program: AccountInfo<'info>,
+  price: u16,
   treasury_bump: u8,
 ) -> ProgramResult {
 
   let signer_seeds = &[&[sla_constants::PREFIX_TREASURY.as_bytes(), bytemuck::bytes_of(&treasury_bump)][..]];
-  let price = sla_constants::PRICE_SCANNER;
 
-  msg!("Minting a new Scanner");
   sla_token::mint_tokens(mint, ata, treasury, token_program.clone(), Some(signer_seeds), 1)?;
 
   msg!("Transferring {} $HAY to treasury", price);
-  sla_token::transfer_tokens(
+  sla_token::transfer_tokens_checked(
     hay_user_ata, 
+    hay_mint,
     hay_treasury_ata, 
     user,
     token_program, 
-    u64::from(price),
+    to_base_units(price, hay_decimals)?,
+    hay_decimals,
   )?;
   
   Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,815 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::TokenAccount;
+
+use crate::sla_accounts::{
+  AgentProfile, AliasRecord, AvatarAccount, AvatarAccountV2, GlobalConfig, RankLadder, RankingV2, RankingV3, ScanRecord,
+};
+use crate::sla_collection::{get_trait_collection, get_trait_name};
+use crate::sla_events::*;
+use crate::sla_metadata::{self, MetadataSigner};
+use crate::utils::{load_ranking_v1, verify_avatar, verify_trait};
+use crate::sla_errors::SlaErrors;
+use crate::{sla_alias, sla_constants, sla_token};
+
+// The merge, alias, scan and badge instructions come in several flavours that only differ in where
+// the new URI comes from and who signs the metadata update. Each entry point resolves the URI (and
+// checks its attestation if any), then runs the shared body of its operation below.
+
+pub struct MergeAccounts<'a, 'info> {
+  pub avatar: &'a AccountInfo<'info>,
+  pub avatar_v2: &'a mut AvatarAccountV2,
+  pub avatar_mint: Pubkey,
+  pub avatar_token: &'a Account<'info, TokenAccount>,
+  pub avatar_metadata: AccountInfo<'info>,
+  pub trait_mint: AccountInfo<'info>,
+  pub trait_token: &'a Account<'info, TokenAccount>,
+  pub trait_metadata: AccountInfo<'info>,
+  pub payer: AccountInfo<'info>,
+  pub metadata_authority: AccountInfo<'info>,
+  pub config: &'a GlobalConfig,
+  pub token_program: AccountInfo<'info>,
+  pub metadata_program: AccountInfo<'info>,
+}
+
+pub fn merge_trait<'info>(
+  mut accounts: MergeAccounts<'_, 'info>,
+  signer: MetadataSigner<'info>,
+  metadata_uri: String,
+) -> ProgramResult {
+  let payer = accounts.payer.clone();
+  let trait_mint = accounts.trait_mint.clone();
+
+  // Verify that the avatar belongs to the SLA collection
+  msg!("Verifying agent belongs to the right collection");
+  verify_avatar(
+    accounts.avatar_mint,
+    accounts.avatar_token.clone(),
+    payer.key(),
+    &accounts.avatar_metadata,
+    accounts.config,
+  )?;
+
+  // Verify that the trait belongs to the SLA collection + extract the trait ID
+  msg!("Verifying trait belongs to the right collection");
+  let trait_id = verify_trait(
+    trait_mint.key(),
+    accounts.trait_token.clone(),
+    payer.key(),
+    &accounts.trait_metadata,
+    accounts.config,
+  )?;
+
+  // Carry over the traits merged into the V1 Avatar account if needed
+  msg!("Migrating agent PDA to V2 if needed");
+  accounts.avatar_v2.from_v1(&AvatarAccount::load(accounts.avatar)?);
+
+  // Update the SLA Avatar data (while checking whether the merge is allowed)
+  msg!("Updating agent PDA");
+  let slot = Clock::get()?.slot;
+  accounts.avatar_v2.merge(trait_id, trait_mint.key(), slot)?;
+
+  // Update the metadata URI through the Metaplex program
+  msg!("Updating agent metadata with new URI");
+  sla_metadata::update_metadata_as(
+    &signer,
+    accounts.avatar_metadata,
+    accounts.metadata_authority,
+    accounts.config.metadata_authority_bump,
+    accounts.metadata_program,
+    metadata_uri.clone(),
+    None,
+  )?;
+
+  // Burn the trait token
+  msg!("Burning trait token");
+  sla_token::burn_trait(
+    accounts.trait_token.to_account_info(),
+    trait_mint.clone(),
+    payer.clone(),
+    accounts.token_program,
+  )?;
+
+  emit!(TraitMerged {
+    avatar_mint: accounts.avatar_mint,
+    user: payer.key(),
+    trait_mint: trait_mint.key(),
+    trait_id,
+    slot,
+    metadata_uri,
+  });
+
+  msg!("Instruction finished");
+
+  Ok(())
+}
+
+// Accounts of the trait NFT re-minted to the owner when a trait leaves an agent
+pub struct ReturnedTraitAccounts<'info> {
+  pub mint: AccountInfo<'info>,
+  pub token: AccountInfo<'info>,
+  pub metadata: AccountInfo<'info>,
+  pub master_edition: AccountInfo<'info>,
+  pub collection_mint: AccountInfo<'info>,
+  pub collection_metadata: AccountInfo<'info>,
+  pub collection_master_edition: AccountInfo<'info>,
+  pub system_program: AccountInfo<'info>,
+  pub rent: AccountInfo<'info>,
+}
+
+pub struct UnmergeAccounts<'a, 'info> {
+  pub avatar: &'a AccountInfo<'info>,
+  pub avatar_v2: &'a mut AvatarAccountV2,
+  pub avatar_mint: Pubkey,
+  pub avatar_token: &'a Account<'info, TokenAccount>,
+  pub avatar_metadata: AccountInfo<'info>,
+  pub returned_trait: ReturnedTraitAccounts<'info>,
+  pub payer: AccountInfo<'info>,
+  pub combine_authority: AccountInfo<'info>,
+  pub metadata_authority: AccountInfo<'info>,
+  pub config: &'a GlobalConfig,
+  pub token_program: AccountInfo<'info>,
+  pub metadata_program: AccountInfo<'info>,
+}
+
+pub fn unmerge_trait<'info>(
+  mut accounts: UnmergeAccounts<'_, 'info>,
+  trait_id: u8,
+  metadata_uri: String,
+) -> ProgramResult {
+  let payer = accounts.payer.clone();
+
+  // Verify that the avatar belongs to the SLA collection
+  msg!("Verifying agent belongs to the right collection");
+  verify_avatar(
+    accounts.avatar_mint,
+    accounts.avatar_token.clone(),
+    payer.key(),
+    &accounts.avatar_metadata,
+    accounts.config,
+  )?;
+
+  // Check the trait is re-minted into the collection of its type
+  if accounts.returned_trait.collection_mint.key() != get_trait_collection(trait_id)? {
+    return Err(SlaErrors::TraitCollectionMismatch.into());
+  }
+
+  // Carry over the traits merged into the V1 Avatar account if needed
+  msg!("Migrating agent PDA to V2 if needed");
+  accounts.avatar_v2.from_v1(&AvatarAccount::load(accounts.avatar)?);
+
+  // Update the SLA Avatar data (while checking the slot is merged)
+  msg!("Updating agent PDA");
+  let slot = Clock::get()?.slot;
+  let unmerged = accounts.avatar_v2.unmerge(trait_id)?;
+  msg!("Unmerged trait {} (merged at slot {})", unmerged.mint, unmerged.slot);
+
+  return_trait(&accounts, trait_id, slot)?;
+
+  // Update the metadata URI through the Metaplex program
+  msg!("Updating agent metadata with new URI");
+  sla_metadata::update_metadata(
+    accounts.avatar_metadata,
+    accounts.combine_authority,
+    accounts.metadata_authority,
+    accounts.config.metadata_authority_bump,
+    accounts.metadata_program,
+    metadata_uri.clone(),
+    None,
+  )?;
+
+  emit!(TraitUnmerged {
+    avatar_mint: accounts.avatar_mint,
+    user: payer.key(),
+    trait_id,
+    unmerged_mint: unmerged.mint,
+    returned_trait_mint: accounts.returned_trait.mint.key(),
+    metadata_uri,
+  });
+
+  msg!("Instruction finished");
+
+  Ok(())
+}
+
+pub struct SwapAccounts<'a, 'info> {
+  pub unmerge: UnmergeAccounts<'a, 'info>,
+  pub trait_mint: AccountInfo<'info>,
+  pub trait_token: &'a Account<'info, TokenAccount>,
+  pub trait_metadata: AccountInfo<'info>,
+}
+
+pub fn swap_trait<'info>(accounts: SwapAccounts<'_, 'info>, metadata_uri: String) -> ProgramResult {
+  let SwapAccounts { unmerge: mut accounts, trait_mint, trait_token, trait_metadata } = accounts;
+  let payer = accounts.payer.clone();
+
+  // Verify that the avatar belongs to the SLA collection
+  msg!("Verifying agent belongs to the right collection");
+  verify_avatar(
+    accounts.avatar_mint,
+    accounts.avatar_token.clone(),
+    payer.key(),
+    &accounts.avatar_metadata,
+    accounts.config,
+  )?;
+
+  // Verify that the new trait belongs to the SLA collection + extract the trait ID
+  msg!("Verifying trait belongs to the right collection");
+  let trait_id = verify_trait(
+    trait_mint.key(),
+    trait_token.clone(),
+    payer.key(),
+    &trait_metadata,
+    accounts.config,
+  )?;
+
+  // The replaced trait is re-minted into the same collection as the new one
+  if accounts.returned_trait.collection_mint.key() != get_trait_collection(trait_id)? {
+    return Err(SlaErrors::TraitCollectionMismatch.into());
+  }
+
+  // Carry over the traits merged into the V1 Avatar account if needed
+  msg!("Migrating agent PDA to V2 if needed");
+  accounts.avatar_v2.from_v1(&AvatarAccount::load(accounts.avatar)?);
+
+  // Update the SLA Avatar data (while checking the slot is merged)
+  msg!("Updating agent PDA");
+  let slot = Clock::get()?.slot;
+  let replaced = accounts.avatar_v2.swap(trait_id, trait_mint.key(), slot)?;
+  msg!("Replaced trait {} (merged at slot {})", replaced.mint, replaced.slot);
+
+  return_trait(&accounts, trait_id, slot)?;
+
+  // Update the metadata URI through the Metaplex program
+  msg!("Updating agent metadata with new URI");
+  sla_metadata::update_metadata(
+    accounts.avatar_metadata,
+    accounts.combine_authority,
+    accounts.metadata_authority,
+    accounts.config.metadata_authority_bump,
+    accounts.metadata_program,
+    metadata_uri.clone(),
+    None,
+  )?;
+
+  // Burn the new trait token
+  msg!("Burning trait token");
+  sla_token::burn_trait(
+    trait_token.to_account_info(),
+    trait_mint.clone(),
+    payer.clone(),
+    accounts.token_program,
+  )?;
+
+  emit!(TraitSwapped {
+    avatar_mint: accounts.avatar_mint,
+    user: payer.key(),
+    trait_id,
+    replaced_mint: replaced.mint,
+    trait_mint: trait_mint.key(),
+    returned_trait_mint: accounts.returned_trait.mint.key(),
+    slot,
+    metadata_uri,
+  });
+
+  msg!("Instruction finished");
+
+  Ok(())
+}
+
+// Mints the trait that left the agent back to its owner. Its metadata is derived from the trait ID and the config
+// base URI, so neither the caller nor the combine authority choose it
+fn return_trait(accounts: &UnmergeAccounts, trait_id: u8, slot: u64) -> ProgramResult {
+  let returned_trait = &accounts.returned_trait;
+  let payer = accounts.payer.clone();
+
+  msg!("Minting trait token back to the agent owner");
+  sla_token::mint_tokens(
+    returned_trait.mint.clone(),
+    returned_trait.token.clone(),
+    payer.clone(),
+    accounts.token_program.clone(),
+    None,
+    1,
+  )?;
+
+  msg!("Creating trait metadata");
+  let avatar_data = mpl_token_metadata::state::Metadata::from_account_info(&accounts.avatar_metadata)
+    .map_err(|_| SlaErrors::InvalidMetadataAccount)?
+    .data;
+  sla_metadata::create_trait_nft(
+    returned_trait.metadata.clone(),
+    returned_trait.master_edition.clone(),
+    returned_trait.mint.clone(),
+    payer.clone(),
+    payer,
+    accounts.combine_authority.clone(),
+    returned_trait.collection_mint.clone(),
+    returned_trait.collection_metadata.clone(),
+    returned_trait.collection_master_edition.clone(),
+    accounts.metadata_program.clone(),
+    accounts.token_program.clone(),
+    returned_trait.system_program.clone(),
+    returned_trait.rent.clone(),
+    get_trait_name(trait_id)?.to_string(),
+    sla_constants::TRAIT_SYMBOL.to_string(),
+    accounts.config.trait_uri(&accounts.avatar_mint, slot, trait_id)?,
+    avatar_data.seller_fee_basis_points,
+  )
+}
+
+pub struct AliasAccounts<'a, 'info> {
+  pub avatar_mint: Pubkey,
+  pub avatar_token: &'a Account<'info, TokenAccount>,
+  pub avatar_metadata: AccountInfo<'info>,
+  pub id_card_mint: AccountInfo<'info>,
+  pub id_card_ata: AccountInfo<'info>,
+  pub payer: AccountInfo<'info>,
+  pub alias_record: &'a mut Account<'info, AliasRecord>,
+  pub previous_alias_record: &'a AccountInfo<'info>,
+  pub agent_profile: &'a mut Account<'info, AgentProfile>,
+  pub metadata_authority: AccountInfo<'info>,
+  pub config: &'a GlobalConfig,
+  pub token_program: AccountInfo<'info>,
+  pub metadata_program: AccountInfo<'info>,
+}
+
+pub fn change_alias<'info>(
+  accounts: AliasAccounts<'_, 'info>,
+  signer: MetadataSigner<'info>,
+  metadata_uri: String,
+  new_name: String,
+) -> ProgramResult {
+  let payer = accounts.payer.clone();
+
+  // Verify that the avatar belongs to the SLA collection
+  msg!("Verifying agent belongs to the right collection");
+  verify_avatar(
+    accounts.avatar_mint,
+    accounts.avatar_token.clone(),
+    payer.key(),
+    &accounts.avatar_metadata,
+    accounts.config,
+  )?;
+
+  // Register the new alias (unique across agents) in the agent profile
+  msg!("Registering the new alias");
+  sla_alias::register_alias(
+    accounts.alias_record,
+    accounts.previous_alias_record,
+    accounts.agent_profile,
+    &accounts.avatar_mint,
+    &payer,
+    &new_name,
+  )?;
+
+  // Update the metadata URI through the Metaplex program
+  msg!("Updating agent metadata with new URI");
+  sla_metadata::update_metadata_as(
+    &signer,
+    accounts.avatar_metadata,
+    accounts.metadata_authority,
+    accounts.config.metadata_authority_bump,
+    accounts.metadata_program,
+    metadata_uri.clone(),
+    Some(new_name.clone()),
+  )?;
+
+  // Burn the trait token
+  msg!("Burning ID Card token");
+  sla_token::burn_trait(
+    accounts.id_card_ata,
+    accounts.id_card_mint,
+    payer.clone(),
+    accounts.token_program,
+  )?;
+
+  emit!(AliasChanged {
+    avatar_mint: accounts.avatar_mint,
+    user: payer.key(),
+    new_name,
+    metadata_uri,
+  });
+
+  msg!("Instruction finished");
+
+  Ok(())
+}
+
+pub struct ScanAccounts<'a, 'info> {
+  pub avatar_mint: Pubkey,
+  pub avatar_token: &'a Account<'info, TokenAccount>,
+  pub avatar_metadata: AccountInfo<'info>,
+  pub scanner_mint: AccountInfo<'info>,
+  pub scanner_ata: AccountInfo<'info>,
+  pub user: AccountInfo<'info>,
+  pub agent_profile: &'a mut AgentProfile,
+  pub scan_record: &'a mut ScanRecord,
+  pub metadata_authority: AccountInfo<'info>,
+  pub config: &'a GlobalConfig,
+  pub token_program: AccountInfo<'info>,
+  pub metadata_program: AccountInfo<'info>,
+}
+
+// Scans without a new URI leave the metadata untouched
+pub fn scan_agent<'info>(
+  mut accounts: ScanAccounts<'_, 'info>,
+  signer: MetadataSigner<'info>,
+  metadata_uri: Option<String>,
+) -> ProgramResult {
+  let user = accounts.user.clone();
+
+  // Verify that the avatar belongs to the SLA collection
+  msg!("Verifying agent belongs to the right collection");
+  verify_avatar(
+    accounts.avatar_mint,
+    accounts.avatar_token.clone(),
+    user.key(),
+    &accounts.avatar_metadata,
+    accounts.config,
+  )?;
+
+  let uri_before = sla_metadata::current_uri(&accounts.avatar_metadata)?;
+
+  // Update the metadata URI through the Metaplex program if needed
+  match metadata_uri.clone() {
+    Some(uri) => {
+      msg!("Updating agent metadata with new URI {}", uri);
+      sla_metadata::update_metadata_as(
+        &signer,
+        accounts.avatar_metadata,
+        accounts.metadata_authority,
+        accounts.config.metadata_authority_bump,
+        accounts.metadata_program,
+        uri,
+        None,
+      )?;
+    },
+    None => {
+      msg!("No need to update the metadata URI");
+    },
+  };
+
+  // Burn the trait token
+  msg!("Burning Scanning Device token");
+  sla_token::burn_trait(
+    accounts.scanner_ata,
+    accounts.scanner_mint.clone(),
+    user.clone(),
+    accounts.token_program,
+  )?;
+
+  // Record the scan in the history of the agent
+  msg!("Recording scan {}", accounts.agent_profile.scans);
+  let scan_index = accounts.agent_profile.scans;
+  accounts.scan_record.record(
+    accounts.agent_profile,
+    accounts.avatar_mint,
+    accounts.scanner_mint.key(),
+    uri_before.clone(),
+    metadata_uri.clone().unwrap_or(uri_before),
+    None,
+    &Clock::get()?,
+  );
+
+  emit!(AgentScanned {
+    avatar_mint: accounts.avatar_mint,
+    user: user.key(),
+    scanner_mint: accounts.scanner_mint.key(),
+    metadata_uri,
+    scan_index,
+    target_mint: None,
+  });
+
+  Ok(())
+}
+
+// The admin instructions cannot wait for the holder to run `migrate_ranking`: the V1 ranking is folded in here,
+// and its account is left for the holder to close with `migrate_ranking`
+pub fn migrate_if_needed(ranking_v1: &AccountInfo, ranking_v2: &mut RankingV2, avatar_mint: &Pubkey) -> ProgramResult {
+  if !ranking_v2.migrated {
+    msg!("Migrating the ranking of the agent");
+    ranking_v2.migrate(load_ranking_v1(ranking_v1)?.as_ref(), avatar_mint)?;
+  }
+  Ok(())
+}
+
+pub struct BadgeMergeAccounts<'a, 'info> {
+  pub avatar_mint: Pubkey,
+  pub avatar_token: &'a Account<'info, TokenAccount>,
+  pub avatar_metadata: AccountInfo<'info>,
+  pub payer: AccountInfo<'info>,
+  pub badge_mint: AccountInfo<'info>,
+  pub badge_ata: AccountInfo<'info>,
+  pub ranking_v2: &'a mut RankingV2,
+  pub ranking_v3: &'a mut RankingV3,
+  pub rank_ladder: &'a RankLadder,
+  pub metadata_authority: AccountInfo<'info>,
+  pub config: &'a GlobalConfig,
+  pub token_program: AccountInfo<'info>,
+  pub metadata_program: AccountInfo<'info>,
+}
+
+pub fn merge_badge<'info>(
+  accounts: BadgeMergeAccounts<'_, 'info>,
+  signer: MetadataSigner<'info>,
+  tier: u8,
+  metadata_uri: String,
+) -> ProgramResult {
+  let payer = accounts.payer.clone();
+
+  if accounts.badge_mint.key() != accounts.rank_ladder.tier(tier)?.badge_mint {
+    return Err(SlaErrors::InvalidPubkey.into());
+  }
+
+  // Verify that the avatar belongs to the SLA collection
+  msg!("Verifying agent belongs to the right collection");
+  verify_avatar(
+    accounts.avatar_mint,
+    accounts.avatar_token.clone(),
+    payer.key(),
+    &accounts.avatar_metadata,
+    accounts.config,
+  )?;
+
+  // Update the metadata URI through the Metaplex program
+  msg!("Updating agent metadata with new URI");
+  sla_metadata::update_metadata_as(
+    &signer,
+    accounts.avatar_metadata,
+    accounts.metadata_authority,
+    accounts.config.metadata_authority_bump,
+    accounts.metadata_program,
+    metadata_uri.clone(),
+    None,
+  )?;
+
+  // Burn the badge token
+  msg!("Burning Badge token");
+  sla_token::burn_trait(
+    accounts.badge_ata,
+    accounts.badge_mint.clone(),
+    payer.clone(),
+    accounts.token_program,
+  )?;
+
+  let ranking_v2 = accounts.ranking_v2;
+  let ranking_v3 = accounts.ranking_v3;
+  ranking_v3.from_v2(ranking_v2);
+  ranking_v3.store_bump(&accounts.avatar_mint);
+
+  // Update the Ranking PDA data
+  msg!("Updating the Ranking PDA account");
+  accounts.rank_ladder.upgrade(ranking_v2, tier)?;
+  ranking_v3.record_merge(tier, payer.key(), accounts.badge_mint.key(), &Clock::get()?)?;
+
+  emit!(TierMerged {
+    avatar_mint: accounts.avatar_mint,
+    user: payer.key(),
+    tier,
+    metadata_uri,
+  });
+
+  msg!("Instruction finished");
+  Ok(())
+}
+
+impl<'info> crate::Merge<'info> {
+  pub fn merge_accounts(&mut self) -> MergeAccounts<'_, 'info> {
+    MergeAccounts {
+      avatar: &self.avatar,
+      avatar_v2: &mut self.avatar_v2,
+      avatar_mint: self.avatar_mint.key(),
+      avatar_token: &self.avatar_token,
+      avatar_metadata: self.avatar_metadata.to_account_info(),
+      trait_mint: self.trait_mint.to_account_info(),
+      trait_token: &self.trait_token,
+      trait_metadata: self.trait_metadata.to_account_info(),
+      payer: self.payer.to_account_info(),
+      metadata_authority: self.metadata_authority.to_account_info(),
+      config: &self.config,
+      token_program: self.token_program.to_account_info(),
+      metadata_program: self.metadata_program.to_account_info(),
+    }
+  }
+}
+
+impl<'info> crate::MergeV3<'info> {
+  pub fn merge_accounts(&mut self) -> MergeAccounts<'_, 'info> {
+    MergeAccounts {
+      avatar: &self.avatar,
+      avatar_v2: &mut self.avatar_v2,
+      avatar_mint: self.avatar_mint.key(),
+      avatar_token: &self.avatar_token,
+      avatar_metadata: self.avatar_metadata.to_account_info(),
+      trait_mint: self.trait_mint.to_account_info(),
+      trait_token: &self.trait_token,
+      trait_metadata: self.trait_metadata.to_account_info(),
+      payer: self.payer.to_account_info(),
+      metadata_authority: self.metadata_authority.to_account_info(),
+      config: &self.config,
+      token_program: self.token_program.to_account_info(),
+      metadata_program: self.metadata_program.to_account_info(),
+    }
+  }
+}
+
+impl<'info> crate::MergeAttested<'info> {
+  pub fn merge_accounts(&mut self) -> MergeAccounts<'_, 'info> {
+    MergeAccounts {
+      avatar: &self.avatar,
+      avatar_v2: &mut self.avatar_v2,
+      avatar_mint: self.avatar_mint.key(),
+      avatar_token: &self.avatar_token,
+      avatar_metadata: self.avatar_metadata.to_account_info(),
+      trait_mint: self.trait_mint.to_account_info(),
+      trait_token: &self.trait_token,
+      trait_metadata: self.trait_metadata.to_account_info(),
+      payer: self.payer.to_account_info(),
+      metadata_authority: self.metadata_authority.to_account_info(),
+      config: &self.config,
+      token_program: self.token_program.to_account_info(),
+      metadata_program: self.metadata_program.to_account_info(),
+    }
+  }
+}
+
+impl<'info> crate::Unmerge<'info> {
+  pub fn unmerge_accounts(&mut self) -> UnmergeAccounts<'_, 'info> {
+    UnmergeAccounts {
+      avatar: &self.avatar,
+      avatar_v2: &mut self.avatar_v2,
+      avatar_mint: self.avatar_mint.key(),
+      avatar_token: &self.avatar_token,
+      avatar_metadata: self.avatar_metadata.to_account_info(),
+      returned_trait: ReturnedTraitAccounts {
+        mint: self.returned_trait_mint.to_account_info(),
+        token: self.returned_trait_token.to_account_info(),
+        metadata: self.returned_trait_metadata.to_account_info(),
+        master_edition: self.returned_trait_master_edition.to_account_info(),
+        collection_mint: self.trait_collection_mint.to_account_info(),
+        collection_metadata: self.trait_collection_metadata.to_account_info(),
+        collection_master_edition: self.trait_collection_master_edition.to_account_info(),
+        system_program: self.system_program.to_account_info(),
+        rent: self.rent.to_account_info(),
+      },
+      payer: self.payer.to_account_info(),
+      combine_authority: self.combine_authority.to_account_info(),
+      metadata_authority: self.metadata_authority.to_account_info(),
+      config: &self.config,
+      token_program: self.token_program.to_account_info(),
+      metadata_program: self.metadata_program.to_account_info(),
+    }
+  }
+}
+
+impl<'info> crate::SwapTrait<'info> {
+  pub fn swap_accounts(&mut self) -> SwapAccounts<'_, 'info> {
+    SwapAccounts {
+      unmerge: UnmergeAccounts {
+        avatar: &self.avatar,
+        avatar_v2: &mut self.avatar_v2,
+        avatar_mint: self.avatar_mint.key(),
+        avatar_token: &self.avatar_token,
+        avatar_metadata: self.avatar_metadata.to_account_info(),
+        returned_trait: ReturnedTraitAccounts {
+          mint: self.returned_trait_mint.to_account_info(),
+          token: self.returned_trait_token.to_account_info(),
+          metadata: self.returned_trait_metadata.to_account_info(),
+          master_edition: self.returned_trait_master_edition.to_account_info(),
+          collection_mint: self.trait_collection_mint.to_account_info(),
+          collection_metadata: self.trait_collection_metadata.to_account_info(),
+          collection_master_edition: self.trait_collection_master_edition.to_account_info(),
+          system_program: self.system_program.to_account_info(),
+          rent: self.rent.to_account_info(),
+        },
+        payer: self.payer.to_account_info(),
+        combine_authority: self.combine_authority.to_account_info(),
+        metadata_authority: self.metadata_authority.to_account_info(),
+        config: &self.config,
+        token_program: self.token_program.to_account_info(),
+        metadata_program: self.metadata_program.to_account_info(),
+      },
+      trait_mint: self.trait_mint.to_account_info(),
+      trait_token: &self.trait_token,
+      trait_metadata: self.trait_metadata.to_account_info(),
+    }
+  }
+}
+
+impl<'info> crate::ChangeAlias<'info> {
+  pub fn alias_accounts(&mut self) -> AliasAccounts<'_, 'info> {
+    AliasAccounts {
+      avatar_mint: self.avatar_mint.key(),
+      avatar_token: &self.avatar_token,
+      avatar_metadata: self.avatar_metadata.to_account_info(),
+      id_card_mint: self.id_card_mint.to_account_info(),
+      id_card_ata: self.id_card_ata.to_account_info(),
+      payer: self.payer.to_account_info(),
+      alias_record: &mut self.alias_record,
+      previous_alias_record: &self.previous_alias_record,
+      agent_profile: &mut self.agent_profile,
+      metadata_authority: self.metadata_authority.to_account_info(),
+      config: &self.config,
+      token_program: self.token_program.to_account_info(),
+      metadata_program: self.metadata_program.to_account_info(),
+    }
+  }
+}
+
+impl<'info> crate::ChangeAliasAttested<'info> {
+  pub fn alias_accounts(&mut self) -> AliasAccounts<'_, 'info> {
+    AliasAccounts {
+      avatar_mint: self.avatar_mint.key(),
+      avatar_token: &self.avatar_token,
+      avatar_metadata: self.avatar_metadata.to_account_info(),
+      id_card_mint: self.id_card_mint.to_account_info(),
+      id_card_ata: self.id_card_ata.to_account_info(),
+      payer: self.payer.to_account_info(),
+      alias_record: &mut self.alias_record,
+      previous_alias_record: &self.previous_alias_record,
+      agent_profile: &mut self.agent_profile,
+      metadata_authority: self.metadata_authority.to_account_info(),
+      config: &self.config,
+      token_program: self.token_program.to_account_info(),
+      metadata_program: self.metadata_program.to_account_info(),
+    }
+  }
+}
+
+impl<'info> crate::ScanAgent<'info> {
+  pub fn scan_accounts(&mut self) -> ScanAccounts<'_, 'info> {
+    ScanAccounts {
+      avatar_mint: self.avatar_mint.key(),
+      avatar_token: &self.avatar_token,
+      avatar_metadata: self.avatar_metadata.to_account_info(),
+      scanner_mint: self.scanner_mint.to_account_info(),
+      scanner_ata: self.scanner_ata.to_account_info(),
+      user: self.user.to_account_info(),
+      agent_profile: &mut self.agent_profile,
+      scan_record: &mut self.scan_record,
+      metadata_authority: self.metadata_authority.to_account_info(),
+      config: &self.config,
+      token_program: self.token_program.to_account_info(),
+      metadata_program: self.metadata_program.to_account_info(),
+    }
+  }
+}
+
+impl<'info> crate::ScanAgentAttested<'info> {
+  pub fn scan_accounts(&mut self) -> ScanAccounts<'_, 'info> {
+    ScanAccounts {
+      avatar_mint: self.avatar_mint.key(),
+      avatar_token: &self.avatar_token,
+      avatar_metadata: self.avatar_metadata.to_account_info(),
+      scanner_mint: self.scanner_mint.to_account_info(),
+      scanner_ata: self.scanner_ata.to_account_info(),
+      user: self.user.to_account_info(),
+      agent_profile: &mut self.agent_profile,
+      scan_record: &mut self.scan_record,
+      metadata_authority: self.metadata_authority.to_account_info(),
+      config: &self.config,
+      token_program: self.token_program.to_account_info(),
+      metadata_program: self.metadata_program.to_account_info(),
+    }
+  }
+}
+
+impl<'info> crate::MergeBadgeV4<'info> {
+  pub fn badge_merge_accounts(&mut self) -> BadgeMergeAccounts<'_, 'info> {
+    BadgeMergeAccounts {
+      avatar_mint: self.avatar_mint.key(),
+      avatar_token: &self.avatar_token,
+      avatar_metadata: self.avatar_metadata.to_account_info(),
+      payer: self.payer.to_account_info(),
+      badge_mint: self.badge_mint.to_account_info(),
+      badge_ata: self.badge_ata.to_account_info(),
+      ranking_v2: &mut self.ranking_v2,
+      ranking_v3: &mut self.ranking_v3,
+      rank_ladder: &self.rank_ladder,
+      metadata_authority: self.metadata_authority.to_account_info(),
+      config: &self.config,
+      token_program: self.token_program.to_account_info(),
+      metadata_program: self.metadata_program.to_account_info(),
+    }
+  }
+}
+
+impl<'info> crate::MergeBadgeAttested<'info> {
+  pub fn badge_merge_accounts(&mut self) -> BadgeMergeAccounts<'_, 'info> {
+    BadgeMergeAccounts {
+      avatar_mint: self.avatar_mint.key(),
+      avatar_token: &self.avatar_token,
+      avatar_metadata: self.avatar_metadata.to_account_info(),
+      payer: self.payer.to_account_info(),
+      badge_mint: self.badge_mint.to_account_info(),
+      badge_ata: self.badge_ata.to_account_info(),
+      ranking_v2: &mut self.ranking_v2,
+      ranking_v3: &mut self.ranking_v3,
+      rank_ladder: &self.rank_ladder,
+      metadata_authority: self.metadata_authority.to_account_info(),
+      config: &self.config,
+      token_program: self.token_program.to_account_info(),
+      metadata_program: self.metadata_program.to_account_info(),
+    }
+  }
+}
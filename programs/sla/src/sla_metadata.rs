@@ -1,22 +1,131 @@
 use anchor_lang::prelude::*;
-use solana_program::program::{invoke};
+use solana_program::program::{invoke, invoke_signed};
+
+use crate::{sla_constants, SlaErrors};
 use mpl_token_metadata::{
-  instruction::{update_metadata_accounts_v2}, 
+  instruction::{
+    create_master_edition_v3, create_metadata_accounts_v2, update_metadata_accounts_v2,
+    verify_collection,
+  }, 
   ID, state
 };
 
+// Avatars are updated by the combine authority, or by the program PDA once their update
+// authority has been transferred with `transfer_update_authority`
 pub fn update_metadata<'info>(
   metadata_account: AccountInfo<'info>, 
-  update_authority: AccountInfo<'info>, 
+  combine_authority: AccountInfo<'info>, 
+  metadata_authority: AccountInfo<'info>,
+  metadata_authority_bump: u8,
   metadata_program: AccountInfo<'info>,
   new_uri: String,
   new_name: Option<String>,
 ) -> ProgramResult {
 
-  let metadata = state::Metadata::from_account_info(&metadata_account)?;
+  let metadata = state::Metadata::from_account_info(&metadata_account)
+    .map_err(|_| SlaErrors::InvalidMetadataAccount)?;
+
+  if metadata.update_authority == metadata_authority.key() {
+    return update_metadata_signed(
+      metadata_account,
+      metadata_authority,
+      metadata_authority_bump,
+      metadata_program,
+      new_uri,
+      new_name,
+    );
+  }
 
-  // Update the URI field in the data
-  let data = state::DataV2 {
+  // Create the Metaplex Metadata instruction to update the metadata of the Avatar
+  let instruction = update_metadata_accounts_v2(
+    ID,
+    metadata_account.key(),
+    combine_authority.key(),
+    None,  // new update_authority
+    Some(updated_data(metadata, new_uri, new_name)),  // new Data
+    None,  // primary_sale_happened
+    None,  // is_mutable
+  );
+
+  // Send the transaction
+  invoke(&instruction, &[metadata_program, metadata_account, combine_authority])
+}
+
+// Who signs the metadata update of an agent: the combine authority co-signing the instruction, or
+// the program PDA alone when the new URI is derived on-chain or attested by the renderer
+pub enum MetadataSigner<'info> {
+  CombineAuthority(AccountInfo<'info>),
+  Program,
+}
+
+pub fn update_metadata_as<'info>(
+  signer: &MetadataSigner<'info>,
+  metadata_account: AccountInfo<'info>,
+  metadata_authority: AccountInfo<'info>,
+  metadata_authority_bump: u8,
+  metadata_program: AccountInfo<'info>,
+  new_uri: String,
+  new_name: Option<String>,
+) -> ProgramResult {
+  match signer {
+    MetadataSigner::CombineAuthority(combine_authority) => update_metadata(
+      metadata_account,
+      combine_authority.clone(),
+      metadata_authority,
+      metadata_authority_bump,
+      metadata_program,
+      new_uri,
+      new_name,
+    ),
+    MetadataSigner::Program => update_metadata_signed(
+      metadata_account,
+      metadata_authority,
+      metadata_authority_bump,
+      metadata_program,
+      new_uri,
+      new_name,
+    ),
+  }
+}
+
+// Signed by the program PDA only: fails for avatars whose update authority was not transferred
+pub fn update_metadata_signed<'info>(
+  metadata_account: AccountInfo<'info>, 
+  metadata_authority: AccountInfo<'info>,
+  metadata_authority_bump: u8,
+  metadata_program: AccountInfo<'info>,
+  new_uri: String,
+  new_name: Option<String>,
+) -> ProgramResult {
+
+  let metadata = state::Metadata::from_account_info(&metadata_account)
+    .map_err(|_| SlaErrors::InvalidMetadataAccount)?;
+
+  if metadata.update_authority != metadata_authority.key() {
+    return Err(SlaErrors::UpdateAuthorityNotTransferred.into());
+  }
+
+  let instruction = update_metadata_accounts_v2(
+    ID,
+    metadata_account.key(),
+    metadata_authority.key(),
+    None,  // new update_authority
+    Some(updated_data(metadata, new_uri, new_name)),  // new Data
+    None,  // primary_sale_happened
+    None,  // is_mutable
+  );
+
+  let seeds = &[sla_constants::PREFIX_UPDATE_AUTHORITY.as_bytes(), &[metadata_authority_bump]];
+  invoke_signed(
+    &instruction,
+    &[metadata_program, metadata_account, metadata_authority],
+    &[&seeds[..]],
+  )
+}
+
+// Current data with the new URI (and name)
+fn updated_data(metadata: state::Metadata, new_uri: String, new_name: Option<String>) -> state::DataV2 {
+  state::DataV2 {
     name: match new_name {
       Some(name) => name,
       None => metadata.data.name,
@@ -27,21 +136,152 @@ pub fn update_metadata<'info>(
     creators: metadata.data.creators,
     collection: metadata.collection,
     uses: metadata.uses
-  };
+  }
+}
 
-  // Create the Metaplex Metadata instruction to update the metadata of the Avatar
+// URI currently stored in the metadata, without the Metaplex padding
+pub fn current_uri(metadata_account: &AccountInfo) -> Result<String, SlaErrors> {
+  let metadata = state::Metadata::from_account_info(metadata_account)
+    .map_err(|_| SlaErrors::InvalidMetadataAccount)?;
+  Ok(metadata.data.uri.trim_matches('\0').to_string())
+}
+
+// Hand the update authority of an avatar over to the program PDA
+pub fn transfer_update_authority<'info>(
+  metadata_account: AccountInfo<'info>,
+  update_authority: AccountInfo<'info>,
+  metadata_authority: AccountInfo<'info>,
+  metadata_program: AccountInfo<'info>,
+) -> ProgramResult {
   let instruction = update_metadata_accounts_v2(
     ID,
     metadata_account.key(),
     update_authority.key(),
-    None,  // new update_authority
-    Some(data),  // new Data
+    Some(metadata_authority.key()),  // new update_authority
+    None,  // new Data
     None,  // primary_sale_happened
     None,  // is_mutable
   );
 
-  let accounts = &[metadata_program, metadata_account, update_authority];
+  invoke(&instruction, &[metadata_program, metadata_account, update_authority])
+}
 
-  // Send the transaction
-  invoke(&instruction, accounts)
+// Create the Metaplex metadata + master edition of a freshly minted trait NFT,
+// and verify it as part of its trait collection
+pub fn create_trait_nft<'info>(
+  metadata_account: AccountInfo<'info>,
+  master_edition: AccountInfo<'info>,
+  mint: AccountInfo<'info>,
+  mint_authority: AccountInfo<'info>,
+  payer: AccountInfo<'info>,
+  update_authority: AccountInfo<'info>,
+  collection_mint: AccountInfo<'info>,
+  collection_metadata: AccountInfo<'info>,
+  collection_master_edition: AccountInfo<'info>,
+  metadata_program: AccountInfo<'info>,
+  token_program: AccountInfo<'info>,
+  system_program: AccountInfo<'info>,
+  rent: AccountInfo<'info>,
+  name: String,
+  symbol: String,
+  uri: String,
+  seller_fee_basis_points: u16,
+) -> ProgramResult {
+
+  // The combine authority is the update authority, so it can be a verified creator
+  let creators = vec![state::Creator {
+    address: update_authority.key(),
+    verified: true,
+    share: 100,
+  }];
+
+  let collection = state::Collection {
+    verified: false,
+    key: collection_mint.key(),
+  };
+
+  let instruction = create_metadata_accounts_v2(
+    ID,
+    metadata_account.key(),
+    mint.key(),
+    mint_authority.key(),
+    payer.key(),
+    update_authority.key(),
+    name,
+    symbol,
+    uri,
+    Some(creators),
+    seller_fee_basis_points,
+    true,  // update_authority_is_signer
+    true,  // is_mutable
+    Some(collection),
+    None,  // uses
+  );
+
+  invoke(
+    &instruction,
+    &[
+      metadata_program.clone(),
+      metadata_account.clone(),
+      mint.clone(),
+      mint_authority.clone(),
+      payer.clone(),
+      update_authority.clone(),
+      system_program.clone(),
+      rent.clone(),
+    ],
+  )?;
+
+  // Create the master edition (max supply of 0 so no prints can be made)
+  let instruction = create_master_edition_v3(
+    ID,
+    master_edition.key(),
+    mint.key(),
+    update_authority.key(),
+    mint_authority.key(),
+    metadata_account.key(),
+    payer.key(),
+    Some(0),
+  );
+
+  invoke(
+    &instruction,
+    &[
+      metadata_program.clone(),
+      master_edition,
+      mint,
+      update_authority.clone(),
+      mint_authority,
+      payer.clone(),
+      metadata_account.clone(),
+      token_program,
+      system_program,
+      rent,
+    ],
+  )?;
+
+  // Verify the trait as part of its collection
+  let instruction = verify_collection(
+    ID,
+    metadata_account.key(),
+    update_authority.key(),
+    payer.key(),
+    collection_mint.key(),
+    collection_metadata.key(),
+    collection_master_edition.key(),
+    None,  // collection_authority_record
+  );
+
+  invoke(
+    &instruction,
+    &[
+      metadata_program,
+      metadata_account,
+      update_authority,
+      payer,
+      collection_mint,
+      collection_metadata,
+      collection_master_edition,
+    ],
+  )
 }
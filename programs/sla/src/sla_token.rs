@@ -1,5 +1,6 @@
 use anchor_lang::prelude::*;
 use anchor_spl;
+use solana_program::program::invoke;
 
 
 pub fn mint_tokens<'info>(
@@ -25,23 +26,29 @@ pub fn mint_tokens<'info>(
   anchor_spl::token::mint_to(cpi_ctx, amount)
 }
 
-pub fn transfer_tokens<'info>(
+// Uses `transfer_checked` so the Token program asserts the mint and its decimals
+pub fn transfer_tokens_checked<'info>(
   from: AccountInfo<'info>,
+  mint: AccountInfo<'info>,
   to: AccountInfo<'info>,
   authority: AccountInfo<'info>,
   token_program: AccountInfo<'info>,
   amount: u64,
+  decimals: u8,
 ) -> ProgramResult {
 
-  let accounts = anchor_spl::token::Transfer {
-    from: from,
-    to: to,
-    authority: authority,
-  };
-
-  let cpi_ctx = CpiContext::new(token_program, accounts);
+  let instruction = spl_token::instruction::transfer_checked(
+    token_program.key,
+    from.key,
+    mint.key,
+    to.key,
+    authority.key,
+    &[],
+    amount,
+    decimals,
+  )?;
 
-  anchor_spl::token::transfer(cpi_ctx, amount)
+  invoke(&instruction, &[from, mint, to, authority, token_program])
 }
 
 
@@ -3,7 +3,8 @@ use anchor_spl;
 use mpl_token_metadata;
 use std::str::FromStr;
 
-use crate::sla_collection::{check_collection, extract_trait_id};
+use crate::sla_accounts::{GlobalConfig, RankDiscounts, RankLadder, Ranking, RankingV2};
+use crate::sla_collection::{check_collection, check_creators, extract_trait_id, is_returned_trait};
 use crate::sla_constants;
 use crate::SlaErrors;
 
@@ -16,12 +17,64 @@ pub fn assert_address<'info>(given: &Pubkey, expected: &str) -> bool {
   given == &str_to_pubkey(expected)
 }
 
+// Address + canonical bump of a PDA of this program
+pub fn find_pda(seeds: &[&[u8]]) -> (Pubkey, u8) {
+  Pubkey::find_program_address(seeds, &crate::ID)
+}
+
+// ProgramData account of a program deployed with the upgradeable BPF loader
+pub fn find_program_data_address(program_id: &Pubkey) -> Pubkey {
+  Pubkey::find_program_address(
+    &[program_id.as_ref()],
+    &anchor_lang::solana_program::bpf_loader_upgradeable::ID,
+  ).0
+}
+
+// Empties a program account, its lamports going to `destination`
+pub fn close_account<'info>(account: &AccountInfo<'info>, destination: &AccountInfo<'info>) -> ProgramResult {
+  let lamports = account.lamports();
+  **destination.try_borrow_mut_lamports()? += lamports;
+  **account.try_borrow_mut_lamports()? = 0;
+  account.try_borrow_mut_data()?.fill(0);
+  Ok(())
+}
+
+// V1 ranking of the agent, None when it never minted a badge before V2 or the account is closed
+pub fn load_ranking_v1<'info>(ranking_v1: &AccountInfo<'info>) -> Result<Option<Ranking>, ProgramError> {
+  if ranking_v1.owner != &crate::ID || ranking_v1.data_is_empty() {
+    return Ok(None);
+  }
+  Ok(Some(*Account::<Ranking>::try_from(ranking_v1)?))
+}
+
+// Discounts of the current tier of the agent whose `RankingV2` PDA this is. Agents that never had a badge, and
+// every agent before the rank ladder is initialized, pay full price
+pub fn agent_discounts<'info>(
+  rank_ladder: &AccountInfo<'info>,
+  ranking_v2: &AccountInfo<'info>,
+) -> Result<RankDiscounts, ProgramError> {
+  let exists = |account: &AccountInfo<'info>| account.owner == &crate::ID && !account.data_is_empty();
+  if !(exists(rank_ladder) && exists(ranking_v2)) {
+    return Ok(RankDiscounts::default());
+  }
+  let tier = Account::<RankingV2>::try_from(ranking_v2)?.tier();
+  Ok(Account::<RankLadder>::try_from(rank_ladder)?.discounts(tier))
+}
+
+pub fn assert_not_paused(paused: bool) -> Result<(), SlaErrors> {
+  if paused {
+    return Err(SlaErrors::ProgramPaused);
+  }
+  Ok(())
+}
+
 fn verify_nft<'info>(
   mint: Pubkey,
   ata: Account<'info, anchor_spl::token::TokenAccount>,
   user: Pubkey,
   avatar_metadata: &AccountInfo<'info>,
   expected_collection: &Pubkey,
+  expected_creators: &[Pubkey],
 ) -> Result<(), SlaErrors> {
   // Check the ATA account contains exactly 1 token
   if !(ata.amount == 1) {
@@ -38,15 +91,60 @@ fn verify_nft<'info>(
     return Err(SlaErrors::TokenPDAMismatch);
   }
 
-  // Check that we are in the list of creators and are verified
-  let metadata = mpl_token_metadata::state::Metadata::from_account_info(avatar_metadata).unwrap();
+  verify_collection(mint, avatar_metadata, expected_collection, expected_creators)?;
+
+  Ok(())
+}
+
+pub fn find_metadata_pda(mint: &Pubkey) -> Pubkey {
+  Pubkey::find_program_address(
+    &[
+      mpl_token_metadata::state::PREFIX.as_bytes(),
+      mpl_token_metadata::ID.as_ref(),
+      mint.as_ref(),
+    ],
+    &mpl_token_metadata::ID,
+  ).0
+}
+
+// Parses the Metaplex metadata of `mint`, rejecting the metadata of any other NFT
+fn load_metadata<'info>(
+  mint: Pubkey,
+  metadata_account: &AccountInfo<'info>,
+) -> Result<mpl_token_metadata::state::Metadata, SlaErrors> {
+  if metadata_account.key() != find_metadata_pda(&mint) || metadata_account.owner != &mpl_token_metadata::ID {
+    return Err(SlaErrors::InvalidMetadataAccount);
+  }
+
+  let metadata = mpl_token_metadata::state::Metadata::from_account_info(metadata_account)
+    .map_err(|_| SlaErrors::InvalidMetadataAccount)?;
+
+  if metadata.mint != mint {
+    return Err(SlaErrors::InvalidMetadataAccount);
+  }
+
+  Ok(metadata)
+}
+
+fn verify_collection<'info>(
+  mint: Pubkey,
+  metadata_account: &AccountInfo<'info>,
+  expected_collection: &Pubkey,
+  expected_creators: &[Pubkey],
+) -> Result<mpl_token_metadata::state::Metadata, SlaErrors> {
+  let metadata = load_metadata(mint, metadata_account)?;
 
   // Check the collection is from the expected collection and that it is verified
-  if !check_collection(metadata, expected_collection) {
+  if !check_collection(&metadata, expected_collection) {
     return Err(SlaErrors::AvatarNotInCollection);
   }
 
-  Ok(())
+  // Check one of the expected creators is in the list of creators and is verified
+  if !check_creators(&metadata, expected_creators) {
+    return Err(SlaErrors::CreatorInvalid);
+  }
+
+  Ok(metadata)
 }
 
 pub fn verify_avatar<'info>(
@@ -54,6 +152,7 @@ pub fn verify_avatar<'info>(
   ata: Account<'info, anchor_spl::token::TokenAccount>,
   user: Pubkey,
   avatar_metadata: &AccountInfo<'info>,
+  config: &GlobalConfig,
 ) -> Result<(), SlaErrors> {
   verify_nft(
     mint,
@@ -61,6 +160,21 @@ pub fn verify_avatar<'info>(
     user,
     avatar_metadata,
     &str_to_pubkey(sla_constants::LLAMA_COLLECTION),
+    &[config.avatar_creator],
+  )
+}
+
+// Agent of the collection that is not held by the user (target of a scan)
+pub fn verify_target_avatar<'info>(
+  mint: Pubkey,
+  avatar_metadata: &AccountInfo<'info>,
+  config: &GlobalConfig,
+) -> Result<mpl_token_metadata::state::Metadata, SlaErrors> {
+  verify_collection(
+    mint,
+    avatar_metadata,
+    &str_to_pubkey(sla_constants::LLAMA_COLLECTION),
+    &[config.avatar_creator],
   )
 }
 
@@ -69,15 +183,31 @@ pub fn verify_trait<'info>(
   ata: Account<'info, anchor_spl::token::TokenAccount>,
   user: Pubkey,
   trait_metadata: &AccountInfo<'info>,
+  config: &GlobalConfig,
 ) -> Result<u8, SlaErrors> {
   // Fetch metadata account
-  let metadata = mpl_token_metadata::state::Metadata::from_account_info(trait_metadata).unwrap();
+  let metadata = load_metadata(mint, trait_metadata)?;
+  let data = metadata.data.clone();
 
   // Check which collection the trait is part of
   let (collection, trait_id) = extract_trait_id(metadata)?;
 
+  // Traits re-minted by `unmerge` / `swap_trait` are created by the combine authority, with the metadata the program
+  // derives for their trait ID. The combine authority is not a valid creator of any other trait
+  let mut creators = vec![config.trait_creator];
+  if is_returned_trait(&data, trait_id) {
+    creators.push(config.combine_authority);
+  }
+
   // Verify the NFT
-  verify_nft(mint, ata, user, trait_metadata, &collection)?;
+  verify_nft(
+    mint,
+    ata,
+    user,
+    trait_metadata,
+    &collection,
+    &creators,
+  )?;
 
   Ok(trait_id)
 }
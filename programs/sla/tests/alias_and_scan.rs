@@ -0,0 +1,303 @@
+mod common;
+
+use solana_sdk::signature::{Keypair, Signer};
+
+use common::{assert_sla_error, process, replace_account, SlaTestBuilder};
+use sla::sla_accounts::{AgentProfile, AliasRecord, ScanRecord};
+use sla::sla_constants;
+use sla::sla_errors::SlaErrors;
+use sla_client::instructions;
+use sla_client::pda::{find_agent_profile_pda, find_alias_record_pda, find_metadata_pda, find_scan_record_pda, get_ata};
+
+const NEW_URI: &str = "https://arweave.net/renamed";
+const NEW_NAME: &str = "Agent Smith";
+
+
+#[tokio::test]
+async fn change_alias_burns_an_id_card_and_renames_the_agent() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let id_card_mint = builder.config.id_card_mint;
+  let id_card_ata = builder.add_user_tokens(&id_card_mint, 1);
+  let mut test = builder.start().await;
+
+  let ix = instructions::change_alias(
+    &test.config,
+    &test.user.pubkey(),
+    &avatar_mint,
+    NEW_URI.to_string(),
+    NEW_NAME.to_string(),
+    None,
+  );
+  process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await.unwrap();
+
+  let metadata = test.metadata(&avatar_mint).await;
+  assert_eq!(metadata.data.name.trim_matches('\0'), NEW_NAME);
+  assert_eq!(metadata.data.uri.trim_matches('\0'), NEW_URI);
+  assert_eq!(test.token_amount(&id_card_ata).await, 0);
+}
+
+#[tokio::test]
+async fn change_alias_fails_with_another_mint() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let scanner_mint = builder.config.scanner_mint;
+  let scanner_ata = builder.add_user_tokens(&scanner_mint, 1);
+  let mut test = builder.start().await;
+
+  let user = test.user.pubkey();
+  let mut ix = instructions::change_alias(&test.config, &user, &avatar_mint, NEW_URI.to_string(), NEW_NAME.to_string(), None);
+  let id_card_mint = test.config.id_card_mint;
+  replace_account(&mut ix, &get_ata(&user, &id_card_mint), &scanner_ata);
+  replace_account(&mut ix, &id_card_mint, &scanner_mint);
+  let result = process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await;
+  assert_sla_error(result, SlaErrors::InvalidPubkey);
+}
+
+#[tokio::test]
+async fn change_alias_fails_without_the_combine_authority() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let id_card_mint = builder.config.id_card_mint;
+  builder.add_user_tokens(&id_card_mint, 1);
+  let mut test = builder.start().await;
+
+  let impostor = Keypair::new();
+  let mut config = test.config.clone();
+  config.combine_authority = impostor.pubkey();
+
+  let ix = instructions::change_alias(&config, &test.user.pubkey(), &avatar_mint, NEW_URI.to_string(), NEW_NAME.to_string(), None);
+  let result = process(&mut test.context, ix, &[&test.user, &impostor]).await;
+  assert_sla_error(result, SlaErrors::InvalidPubkey);
+}
+
+#[tokio::test]
+async fn change_alias_records_the_alias_history() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let id_card_mint = builder.config.id_card_mint;
+  builder.add_user_tokens(&id_card_mint, 2);
+  let mut test = builder.start().await;
+
+  let user = test.user.pubkey();
+  let ix = instructions::change_alias(&test.config, &user, &avatar_mint, NEW_URI.to_string(), NEW_NAME.to_string(), None);
+  process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await.unwrap();
+
+  let ix = instructions::change_alias(
+    &test.config,
+    &user,
+    &avatar_mint,
+    NEW_URI.to_string(),
+    "Neo".to_string(),
+    Some(NEW_NAME),
+  );
+  process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await.unwrap();
+
+  let profile: AgentProfile = test.anchor_account(&find_agent_profile_pda(&avatar_mint).0).await;
+  assert_eq!(profile.alias.as_deref(), Some("Neo"));
+  assert_eq!(profile.history, vec![NEW_NAME.to_string()]);
+
+  // The previous alias is released
+  let record: AliasRecord = test.anchor_account(&find_alias_record_pda("Neo").0).await;
+  assert_eq!(record.avatar_mint, avatar_mint);
+  assert!(test.account(&find_alias_record_pda(NEW_NAME).0).await.is_none());
+}
+
+#[tokio::test]
+async fn change_alias_fails_when_the_alias_is_taken() {
+  let mut builder = SlaTestBuilder::new();
+  let first_agent = builder.add_avatar();
+  let second_agent = builder.add_avatar();
+  let id_card_mint = builder.config.id_card_mint;
+  builder.add_user_tokens(&id_card_mint, 2);
+  let mut test = builder.start().await;
+
+  let user = test.user.pubkey();
+  let ix = instructions::change_alias(&test.config, &user, &first_agent, NEW_URI.to_string(), NEW_NAME.to_string(), None);
+  process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await.unwrap();
+
+  // Aliases are case insensitive
+  let alias = NEW_NAME.to_uppercase();
+  let ix = instructions::change_alias(&test.config, &user, &second_agent, NEW_URI.to_string(), alias, None);
+  let result = process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await;
+  assert_sla_error(result, SlaErrors::AliasAlreadyTaken);
+}
+
+#[tokio::test]
+async fn change_alias_fails_with_invalid_characters() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let id_card_mint = builder.config.id_card_mint;
+  builder.add_user_tokens(&id_card_mint, 1);
+  let mut test = builder.start().await;
+
+  let alias = "Agent <Smith>".to_string();
+  let ix = instructions::change_alias(&test.config, &test.user.pubkey(), &avatar_mint, NEW_URI.to_string(), alias, None);
+  let result = process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await;
+  assert_sla_error(result, SlaErrors::InvalidAliasCharacters);
+}
+
+#[tokio::test]
+async fn scan_agent_burns_a_scanner() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let scanner_mint = builder.config.scanner_mint;
+  let scanner_ata = builder.add_user_tokens(&scanner_mint, 1);
+  let mut test = builder.start().await;
+
+  let uri_before = test.metadata(&avatar_mint).await.data.uri;
+
+  let ix = instructions::scan_agent(&test.config, &test.user.pubkey(), &avatar_mint, 0, None);
+  process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await.unwrap();
+
+  assert_eq!(test.token_amount(&scanner_ata).await, 0);
+  assert_eq!(test.metadata(&avatar_mint).await.data.uri, uri_before);
+}
+
+#[tokio::test]
+async fn scan_agent_updates_the_uri_when_given() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let scanner_mint = builder.config.scanner_mint;
+  builder.add_user_tokens(&scanner_mint, 1);
+  let mut test = builder.start().await;
+
+  let ix = instructions::scan_agent(&test.config, &test.user.pubkey(), &avatar_mint, 0, Some(NEW_URI.to_string()));
+  process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await.unwrap();
+
+  assert_eq!(test.metadata(&avatar_mint).await.data.uri.trim_matches('\0'), NEW_URI);
+}
+
+#[tokio::test]
+async fn scan_agent_fails_with_another_mint() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let id_card_mint = builder.config.id_card_mint;
+  let id_card_ata = builder.add_user_tokens(&id_card_mint, 1);
+  let mut test = builder.start().await;
+
+  let user = test.user.pubkey();
+  let mut ix = instructions::scan_agent(&test.config, &user, &avatar_mint, 0, None);
+  let scanner_mint = test.config.scanner_mint;
+  replace_account(&mut ix, &get_ata(&user, &scanner_mint), &id_card_ata);
+  replace_account(&mut ix, &scanner_mint, &id_card_mint);
+  let result = process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await;
+  assert_sla_error(result, SlaErrors::InvalidPubkey);
+}
+
+#[tokio::test]
+async fn scan_agent_records_each_scan() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let scanner_mint = builder.config.scanner_mint;
+  builder.add_user_tokens(&scanner_mint, 2);
+  let mut test = builder.start().await;
+
+  let uri_before = test.metadata(&avatar_mint).await.data.uri.trim_matches('\0').to_string();
+
+  let ix = instructions::scan_agent(&test.config, &test.user.pubkey(), &avatar_mint, 0, Some(NEW_URI.to_string()));
+  process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await.unwrap();
+  let ix = instructions::scan_agent(&test.config, &test.user.pubkey(), &avatar_mint, 1, None);
+  process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await.unwrap();
+
+  let (scan_record_address, scan_record_bump) = find_scan_record_pda(&avatar_mint, 0);
+  let scan_record: ScanRecord = test.anchor_account(&scan_record_address).await;
+  assert_eq!(scan_record.avatar_mint, avatar_mint);
+  assert_eq!(scan_record.index, 0);
+  assert_eq!(scan_record.scanner_mint, scanner_mint);
+  assert_eq!(scan_record.uri_before, uri_before);
+  assert_eq!(scan_record.uri_after, NEW_URI);
+  assert_eq!(scan_record.target, None);
+  assert_eq!(scan_record.bump, scan_record_bump);
+
+  let scan_record: ScanRecord = test.anchor_account(&find_scan_record_pda(&avatar_mint, 1).0).await;
+  assert_eq!(scan_record.index, 1);
+  assert_eq!(scan_record.uri_before, NEW_URI);
+  assert_eq!(scan_record.uri_after, NEW_URI);
+
+  let agent_profile: AgentProfile = test.anchor_account(&find_agent_profile_pda(&avatar_mint).0).await;
+  assert_eq!(agent_profile.scans, 2);
+}
+
+#[tokio::test]
+async fn scan_agent_fails_with_a_used_index() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let scanner_mint = builder.config.scanner_mint;
+  builder.add_user_tokens(&scanner_mint, 2);
+  let mut test = builder.start().await;
+
+  let ix = instructions::scan_agent(&test.config, &test.user.pubkey(), &avatar_mint, 0, None);
+  process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await.unwrap();
+
+  let ix = instructions::scan_agent(&test.config, &test.user.pubkey(), &avatar_mint, 0, Some(NEW_URI.to_string()));
+  let result = process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await;
+  assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn scan_target_records_the_target() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let target_mint = builder.add_foreign_avatar();
+  let scanner_mint = builder.config.scanner_mint;
+  let scanner_ata = builder.add_user_tokens(&scanner_mint, 1);
+  let mut test = builder.start().await;
+
+  let target_uri = test.metadata(&target_mint).await.data.uri;
+
+  let ix = instructions::scan_target(&test.config, &test.user.pubkey(), &avatar_mint, &target_mint, 0);
+  process(&mut test.context, ix, &[&test.user]).await.unwrap();
+
+  assert_eq!(test.token_amount(&scanner_ata).await, 0);
+  assert_eq!(test.metadata(&target_mint).await.data.uri, target_uri);
+
+  let scan_record: ScanRecord = test.anchor_account(&find_scan_record_pda(&avatar_mint, 0).0).await;
+  assert_eq!(scan_record.avatar_mint, avatar_mint);
+  assert_eq!(scan_record.target, Some(target_mint));
+  assert_eq!(scan_record.uri_before, target_uri.trim_matches('\0'));
+  assert_eq!(scan_record.uri_after, target_uri.trim_matches('\0'));
+}
+
+#[tokio::test]
+async fn scan_target_fails_on_the_scanning_agent() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let scanner_mint = builder.config.scanner_mint;
+  builder.add_user_tokens(&scanner_mint, 1);
+  let mut test = builder.start().await;
+
+  let ix = instructions::scan_target(&test.config, &test.user.pubkey(), &avatar_mint, &avatar_mint, 0);
+  let result = process(&mut test.context, ix, &[&test.user]).await;
+  assert_sla_error(result, SlaErrors::InvalidScanTarget);
+}
+
+#[tokio::test]
+async fn scan_target_fails_with_a_trait_as_target() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let trait_mint = builder.add_trait(sla_constants::HAT_COLLECTION);
+  let scanner_mint = builder.config.scanner_mint;
+  builder.add_user_tokens(&scanner_mint, 1);
+  let mut test = builder.start().await;
+
+  let ix = instructions::scan_target(&test.config, &test.user.pubkey(), &avatar_mint, &trait_mint, 0);
+  let result = process(&mut test.context, ix, &[&test.user]).await;
+  assert_sla_error(result, SlaErrors::AvatarNotInCollection);
+}
+
+#[tokio::test]
+async fn scan_target_fails_with_the_metadata_of_another_agent() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let target_mint = builder.add_foreign_avatar();
+  let other_mint = builder.add_foreign_avatar();
+  let scanner_mint = builder.config.scanner_mint;
+  builder.add_user_tokens(&scanner_mint, 1);
+  let mut test = builder.start().await;
+
+  let mut ix = instructions::scan_target(&test.config, &test.user.pubkey(), &avatar_mint, &target_mint, 0);
+  replace_account(&mut ix, &find_metadata_pda(&target_mint).0, &find_metadata_pda(&other_mint).0);
+  let result = process(&mut test.context, ix, &[&test.user]).await;
+  assert_sla_error(result, SlaErrors::InvalidMetadataAccount);
+}
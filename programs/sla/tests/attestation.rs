@@ -0,0 +1,222 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use std::convert::TryInto;
+
+use common::{assert_sla_error, process, process_all, SlaTest, SlaTestBuilder};
+use sla::sla_accounts::AvatarAccountV2;
+use sla::sla_constants;
+use sla::sla_errors::SlaErrors;
+use sla_client::attestation::{attestation_message, ed25519_instruction, AttestedAction, UriAttestation};
+use sla_client::instructions;
+use sla_client::pda::{find_avatar_v2_pda, get_ata};
+
+const NEW_URI: &str = "https://arweave.net/attested";
+const NEW_NAME: &str = "Agent Smith";
+
+
+// Registers the renderer and hands the agent over to the program
+async fn setup(test: &mut SlaTest, renderer: &Keypair, avatar_mint: &Pubkey) {
+  let ix = instructions::set_renderer(&test.admin.pubkey(), renderer.pubkey());
+  process(&mut test.context, ix, &[&test.admin]).await.unwrap();
+  test.config.renderer = renderer.pubkey();
+
+  let ix = instructions::transfer_update_authority(&test.config, avatar_mint);
+  process(&mut test.context, ix, &[&test.combine_authority]).await.unwrap();
+}
+
+fn attest(signer: &Keypair, action: AttestedAction, avatar_mint: &Pubkey, nonce: u64, expiry: i64) -> UriAttestation {
+  let message = attestation_message(action, avatar_mint, NEW_URI, nonce, expiry);
+  UriAttestation {
+    uri: NEW_URI.to_string(),
+    nonce,
+    expiry,
+    signature: signer.sign_message(&message).as_ref().try_into().unwrap(),
+  }
+}
+
+#[tokio::test]
+async fn change_alias_attested_needs_no_co_signer() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let id_card_mint = builder.config.id_card_mint;
+  builder.add_user_tokens(&id_card_mint, 1);
+  let mut test = builder.start().await;
+  let renderer = Keypair::new();
+  setup(&mut test, &renderer, &avatar_mint).await;
+
+  let action = AttestedAction::ChangeAlias { new_name: NEW_NAME };
+  let attestation = attest(&renderer, action, &avatar_mint, 0, i64::MAX);
+  let ixs = instructions::change_alias_attested(
+    &test.config,
+    &test.user.pubkey(),
+    &avatar_mint,
+    NEW_NAME.to_string(),
+    None,
+    &attestation,
+  );
+  process_all(&mut test.context, &ixs, &[&test.user]).await.unwrap();
+
+  let metadata = test.metadata(&avatar_mint).await;
+  assert_eq!(metadata.data.name.trim_matches('\0'), NEW_NAME);
+  assert_eq!(metadata.data.uri.trim_matches('\0'), NEW_URI);
+
+  let avatar_v2: AvatarAccountV2 = test.anchor_account(&find_avatar_v2_pda(&avatar_mint).0).await;
+  assert_eq!(avatar_v2.nonce, 1);
+}
+
+#[tokio::test]
+async fn attestations_cannot_be_replayed() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let scanner_mint = builder.config.scanner_mint;
+  builder.add_user_tokens(&scanner_mint, 2);
+  let mut test = builder.start().await;
+  let renderer = Keypair::new();
+  setup(&mut test, &renderer, &avatar_mint).await;
+
+  let attestation = attest(&renderer, AttestedAction::Scan, &avatar_mint, 0, i64::MAX);
+  let ixs = instructions::scan_agent_attested(&test.config, &test.user.pubkey(), &avatar_mint, 0, &attestation);
+  process_all(&mut test.context, &ixs, &[&test.user]).await.unwrap();
+
+  let ixs = instructions::scan_agent_attested(&test.config, &test.user.pubkey(), &avatar_mint, 1, &attestation);
+  let result = process_all(&mut test.context, &ixs, &[&test.user]).await;
+  assert_sla_error(result, SlaErrors::InvalidNonce);
+}
+
+#[tokio::test]
+async fn attestations_of_another_key_are_rejected() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let scanner_mint = builder.config.scanner_mint;
+  builder.add_user_tokens(&scanner_mint, 1);
+  let mut test = builder.start().await;
+  setup(&mut test, &Keypair::new(), &avatar_mint).await;
+
+  // Valid Ed25519 signature, but not from the renderer
+  let impostor = Keypair::new();
+  let mut config = test.config.clone();
+  config.renderer = impostor.pubkey();
+
+  let attestation = attest(&impostor, AttestedAction::Scan, &avatar_mint, 0, i64::MAX);
+  let ixs = instructions::scan_agent_attested(&config, &test.user.pubkey(), &avatar_mint, 0, &attestation);
+  let result = process_all(&mut test.context, &ixs, &[&test.user]).await;
+  assert_sla_error(result, SlaErrors::InvalidAttestation);
+}
+
+#[tokio::test]
+async fn attestations_must_come_with_the_ed25519_instruction() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let scanner_mint = builder.config.scanner_mint;
+  builder.add_user_tokens(&scanner_mint, 1);
+  let mut test = builder.start().await;
+  let renderer = Keypair::new();
+  setup(&mut test, &renderer, &avatar_mint).await;
+
+  let attestation = attest(&renderer, AttestedAction::Scan, &avatar_mint, 0, i64::MAX);
+  let mut ixs = instructions::scan_agent_attested(&test.config, &test.user.pubkey(), &avatar_mint, 0, &attestation);
+  let result = process(&mut test.context, ixs.remove(1), &[&test.user]).await;
+  assert_sla_error(result, SlaErrors::InvalidAttestation);
+}
+
+#[tokio::test]
+async fn expired_attestations_are_rejected() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let scanner_mint = builder.config.scanner_mint;
+  builder.add_user_tokens(&scanner_mint, 1);
+  let mut test = builder.start().await;
+  let renderer = Keypair::new();
+  setup(&mut test, &renderer, &avatar_mint).await;
+
+  let attestation = attest(&renderer, AttestedAction::Scan, &avatar_mint, 0, 0);
+  let ixs = instructions::scan_agent_attested(&test.config, &test.user.pubkey(), &avatar_mint, 0, &attestation);
+  let result = process_all(&mut test.context, &ixs, &[&test.user]).await;
+  assert_sla_error(result, SlaErrors::AttestationExpired);
+}
+
+#[tokio::test]
+async fn merge_attested_uses_the_attested_uri() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let trait_mint = builder.add_trait(sla_constants::HAT_COLLECTION);
+  let mut test = builder.start().await;
+  let renderer = Keypair::new();
+  setup(&mut test, &renderer, &avatar_mint).await;
+
+  let action = AttestedAction::Merge { trait_mint };
+  let attestation = attest(&renderer, action, &avatar_mint, 0, i64::MAX);
+  let ixs = instructions::merge_attested(&test.config, &test.user.pubkey(), &avatar_mint, &trait_mint, &attestation);
+  process_all(&mut test.context, &ixs, &[&test.user]).await.unwrap();
+
+  let avatar_v2: AvatarAccountV2 = test.anchor_account(&find_avatar_v2_pda(&avatar_mint).0).await;
+  assert_eq!(avatar_v2.hat.expect("hat slot is empty").mint, trait_mint);
+  assert_eq!(test.metadata(&avatar_mint).await.data.uri.trim_matches('\0'), NEW_URI);
+}
+
+#[tokio::test]
+async fn merge_attestations_are_bound_to_the_trait() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let attested_trait = builder.add_trait(sla_constants::HAT_COLLECTION);
+  let other_trait = builder.add_trait(sla_constants::HAT_COLLECTION);
+  let mut test = builder.start().await;
+  let renderer = Keypair::new();
+  setup(&mut test, &renderer, &avatar_mint).await;
+
+  // Attested for one hat, used to merge another one the user holds
+  let action = AttestedAction::Merge { trait_mint: attested_trait };
+  let attestation = attest(&renderer, action, &avatar_mint, 0, i64::MAX);
+  let mut ixs = instructions::merge_attested(&test.config, &test.user.pubkey(), &avatar_mint, &other_trait, &attestation);
+  ixs[0] = ed25519_instruction(&renderer.pubkey(), action, &avatar_mint, &attestation);
+  let result = process_all(&mut test.context, &ixs, &[&test.user]).await;
+  assert_sla_error(result, SlaErrors::InvalidAttestation);
+
+  assert_eq!(test.token_amount(&get_ata(&test.user.pubkey(), &other_trait)).await, 1);
+}
+
+#[tokio::test]
+async fn attestations_of_another_instruction_are_rejected() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let scanner_mint = builder.config.scanner_mint;
+  builder.add_user_tokens(&scanner_mint, 1);
+  let mut test = builder.start().await;
+  let renderer = Keypair::new();
+  setup(&mut test, &renderer, &avatar_mint).await;
+
+  // Valid renderer signature of a merge URI, sent along with a scan
+  let action = AttestedAction::Merge { trait_mint: Pubkey::new_unique() };
+  let attestation = attest(&renderer, action, &avatar_mint, 0, i64::MAX);
+  let mut ixs = instructions::scan_agent_attested(&test.config, &test.user.pubkey(), &avatar_mint, 0, &attestation);
+  ixs[0] = ed25519_instruction(&renderer.pubkey(), action, &avatar_mint, &attestation);
+  let result = process_all(&mut test.context, &ixs, &[&test.user]).await;
+  assert_sla_error(result, SlaErrors::InvalidAttestation);
+}
+
+#[tokio::test]
+async fn alias_attestations_are_bound_to_the_alias() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let id_card_mint = builder.config.id_card_mint;
+  builder.add_user_tokens(&id_card_mint, 1);
+  let mut test = builder.start().await;
+  let renderer = Keypair::new();
+  setup(&mut test, &renderer, &avatar_mint).await;
+
+  let action = AttestedAction::ChangeAlias { new_name: NEW_NAME };
+  let attestation = attest(&renderer, action, &avatar_mint, 0, i64::MAX);
+  let mut ixs = instructions::change_alias_attested(
+    &test.config,
+    &test.user.pubkey(),
+    &avatar_mint,
+    "Agent Jones".to_string(),
+    None,
+    &attestation,
+  );
+  ixs[0] = ed25519_instruction(&renderer.pubkey(), action, &avatar_mint, &attestation);
+  let result = process_all(&mut test.context, &ixs, &[&test.user]).await;
+  assert_sla_error(result, SlaErrors::InvalidAttestation);
+}
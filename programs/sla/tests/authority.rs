@@ -0,0 +1,189 @@
+mod common;
+
+use solana_sdk::signature::{Keypair, Signer};
+
+use common::{assert_sla_error, process, SlaTest, SlaTestBuilder};
+use sla::sla_accounts::{AuthoritySet, ConfigParams, GlobalConfig, Ranking, RankingV2};
+use sla::sla_errors::SlaErrors;
+use sla::sla_fungible_token::FungibleAsset;
+use sla_client::instructions;
+use sla_client::pda::{find_authority_set_pda, find_config_pda, find_metadata_authority_pda, find_ranking_pda, find_ranking_v2_pda};
+
+const NEW_URI: &str = "https://arweave.net/renamed";
+const NEW_NAME: &str = "Agent Smith";
+
+
+// 2-of-3 authority set
+async fn init_authority_set(test: &mut SlaTest) -> Vec<Keypair> {
+  let members = vec![Keypair::new(), Keypair::new(), Keypair::new()];
+  let ix = instructions::init_authority_set(&test.admin.pubkey(), members.iter().map(|m| m.pubkey()).collect(), 2);
+  process(&mut test.context, ix, &[&test.admin]).await.unwrap();
+  members
+}
+
+#[tokio::test]
+async fn init_authority_set_stores_the_members() {
+  let mut test = SlaTestBuilder::new().start().await;
+  let members = init_authority_set(&mut test).await;
+
+  let (authority_set_address, authority_set_bump) = find_authority_set_pda();
+  let authority_set: AuthoritySet = test.anchor_account(&authority_set_address).await;
+  assert_eq!(authority_set.members, members.iter().map(|m| m.pubkey()).collect::<Vec<_>>());
+  assert_eq!(authority_set.threshold, 2);
+  assert_eq!(authority_set.bump, authority_set_bump);
+}
+
+#[tokio::test]
+async fn init_authority_set_fails_with_a_threshold_above_the_members() {
+  let mut test = SlaTestBuilder::new().start().await;
+
+  let ix = instructions::init_authority_set(&test.admin.pubkey(), vec![Keypair::new().pubkey()], 2);
+  let result = process(&mut test.context, ix, &[&test.admin]).await;
+  assert_sla_error(result, SlaErrors::InvalidAuthoritySet);
+}
+
+#[tokio::test]
+async fn rotate_combine_authority_needs_the_threshold() {
+  let mut test = SlaTestBuilder::new().start().await;
+  let members = init_authority_set(&mut test).await;
+  let new_combine_authority = Keypair::new().pubkey();
+
+  let ix = instructions::rotate_combine_authority(&[members[0].pubkey()], new_combine_authority);
+  let result = process(&mut test.context, ix, &[&members[0]]).await;
+  assert_sla_error(result, SlaErrors::NotEnoughApprovals);
+
+  // The same member signing twice is a single approval
+  let ix = instructions::rotate_combine_authority(&[members[0].pubkey(), members[0].pubkey()], new_combine_authority);
+  let result = process(&mut test.context, ix, &[&members[0]]).await;
+  assert_sla_error(result, SlaErrors::NotEnoughApprovals);
+
+  let ix = instructions::rotate_combine_authority(&[members[0].pubkey(), members[2].pubkey()], new_combine_authority);
+  process(&mut test.context, ix, &[&members[0], &members[2]]).await.unwrap();
+
+  let config: GlobalConfig = test.anchor_account(&find_config_pda().0).await;
+  assert_eq!(config.combine_authority, new_combine_authority);
+}
+
+#[tokio::test]
+async fn update_authority_set_replaces_the_members() {
+  let mut test = SlaTestBuilder::new().start().await;
+  let members = init_authority_set(&mut test).await;
+  let new_member = Keypair::new();
+
+  let approvers = [members[1].pubkey(), members[2].pubkey()];
+  let ix = instructions::update_authority_set(&approvers, vec![new_member.pubkey()], 1);
+  process(&mut test.context, ix, &[&members[1], &members[2]]).await.unwrap();
+
+  // Former members can no longer approve
+  let ix = instructions::rotate_combine_authority(&approvers, Keypair::new().pubkey());
+  let result = process(&mut test.context, ix, &[&members[1], &members[2]]).await;
+  assert_sla_error(result, SlaErrors::NotEnoughApprovals);
+}
+
+#[tokio::test]
+async fn update_config_keeps_the_combine_authority() {
+  let mut test = SlaTestBuilder::new().start().await;
+
+  let config = test.config.clone();
+  let params = ConfigParams {
+    admin: config.admin,
+    combine_authority: Keypair::new().pubkey(),
+    avatar_creator: config.avatar_creator,
+    trait_creator: config.trait_creator,
+    hay_mint: config.hay_mint,
+    hay_treasury_ata: config.hay_treasury_ata,
+    id_card_mint: config.id_card_mint,
+    scanner_mint: config.scanner_mint,
+    badge_bronze_mint: config.badge_bronze_mint,
+    badge_silver_mint: config.badge_silver_mint,
+    badge_gold_mint: config.badge_gold_mint,
+    badge_platinum_mint: config.badge_platinum_mint,
+    badge_diamond_mint: config.badge_diamond_mint,
+    price_id_card: config.price_id_card,
+    price_scanner: config.price_scanner,
+    price_badge_bronze: config.price_badge_bronze,
+    price_badge_silver: config.price_badge_silver,
+    price_badge_gold: config.price_badge_gold,
+    price_badge_platinum: config.price_badge_platinum,
+    price_badge_diamond: config.price_badge_diamond,
+    scanner_limit: config.scanner_limit,
+    scanner_cooldown: config.scanner_cooldown,
+  };
+  let ix = instructions::update_config(&test.admin.pubkey(), params);
+  process(&mut test.context, ix, &[&test.admin]).await.unwrap();
+
+  let config: GlobalConfig = test.anchor_account(&find_config_pda().0).await;
+  assert_eq!(config.combine_authority, test.combine_authority.pubkey());
+}
+
+#[tokio::test]
+async fn add_badge_needs_the_approval_of_the_authority_set() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let mut test = builder.start().await;
+  test.migrate_ranking(&avatar_mint).await;
+  test.init_rank_ladder(10).await;
+  let members = init_authority_set(&mut test).await;
+
+  let ix = instructions::add_badge(&test.config, &avatar_mint, FungibleAsset::BADGE_BRONZE, &[members[0].pubkey()]);
+  let result = process(&mut test.context, ix, &[&test.combine_authority, &members[0]]).await;
+  assert_sla_error(result, SlaErrors::NotEnoughApprovals);
+
+  let approvers = [members[0].pubkey(), members[1].pubkey()];
+  let ix = instructions::add_badge(&test.config, &avatar_mint, FungibleAsset::BADGE_BRONZE, &approvers);
+  process(&mut test.context, ix, &[&test.combine_authority, &members[0], &members[1]]).await.unwrap();
+
+  let ranking_v2: RankingV2 = test.anchor_account(&find_ranking_v2_pda(&avatar_mint).0).await;
+  assert_eq!(ranking_v2.ranking, Some(FungibleAsset::BADGE_BRONZE));
+}
+
+#[tokio::test]
+async fn add_badge_migrates_the_ranking_of_the_agent() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let ranking_v1 = Ranking { ranking: Some(FungibleAsset::BADGE_BRONZE), minted_next: false };
+  builder.add_anchor_account(find_ranking_pda(&avatar_mint).0, &ranking_v1);
+  let mut test = builder.start().await;
+  test.init_rank_ladder(10).await;
+  let members = init_authority_set(&mut test).await;
+
+  let approvers = [members[0].pubkey(), members[1].pubkey()];
+  let ix = instructions::add_badge(&test.config, &avatar_mint, FungibleAsset::BADGE_SILVER, &approvers);
+  process(&mut test.context, ix, &[&test.combine_authority, &members[0], &members[1]]).await.unwrap();
+
+  let ranking_v2: RankingV2 = test.anchor_account(&find_ranking_v2_pda(&avatar_mint).0).await;
+  assert_eq!(ranking_v2.ranking, Some(FungibleAsset::BADGE_SILVER));
+  assert!(ranking_v2.bronze_minted);
+  assert!(ranking_v2.migrated);
+
+  // The holder can still close the V1 ranking
+  test.migrate_ranking(&avatar_mint).await;
+  assert!(test.account(&find_ranking_pda(&avatar_mint).0).await.is_none());
+}
+
+#[tokio::test]
+async fn transferred_agents_are_updated_by_the_program() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let id_card_mint = builder.config.id_card_mint;
+  builder.add_user_tokens(&id_card_mint, 1);
+  let mut test = builder.start().await;
+
+  let ix = instructions::transfer_update_authority(&test.config, &avatar_mint);
+  process(&mut test.context, ix, &[&test.combine_authority]).await.unwrap();
+  assert_eq!(test.metadata(&avatar_mint).await.update_authority, find_metadata_authority_pda().0);
+
+  let ix = instructions::change_alias(
+    &test.config,
+    &test.user.pubkey(),
+    &avatar_mint,
+    NEW_URI.to_string(),
+    NEW_NAME.to_string(),
+    None,
+  );
+  process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await.unwrap();
+
+  let metadata = test.metadata(&avatar_mint).await;
+  assert_eq!(metadata.data.name.trim_matches('\0'), NEW_NAME);
+  assert_eq!(metadata.data.uri.trim_matches('\0'), NEW_URI);
+}
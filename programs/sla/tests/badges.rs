@@ -0,0 +1,597 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::InstructionData;
+use solana_sdk::signature::Signer;
+
+use common::{assert_sla_error, process, SlaTestBuilder, HAY_BALANCE, HAY_DECIMALS, PRICE};
+use sla::sla_accounts::{BadgeSupplyCounter, RankLadder, RankTierParams, Ranking, RankingV2, RankingV3};
+use sla::sla_errors::SlaErrors;
+use sla::sla_fungible_token::FungibleAsset;
+use sla_client::instructions;
+use sla_client::pda::{
+  find_badge_supply_counter_pda, find_rank_ladder_pda, find_ranking_pda, find_ranking_v2_pda, find_ranking_v3_pda,
+  find_treasury_pda, get_ata,
+};
+
+const NEW_URI: &str = "https://arweave.net/ranked";
+
+
+#[tokio::test]
+async fn mint_badge_charges_hay_and_records_the_badge() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let mut test = builder.start().await;
+  test.migrate_ranking(&avatar_mint).await;
+  test.init_rank_ladder(10).await;
+
+  let user = test.user.pubkey();
+  let ix = instructions::mint_badge_v2(&test.config, &user, &avatar_mint, FungibleAsset::BADGE_BRONZE);
+  process(&mut test.context, ix, &[&test.user]).await.unwrap();
+
+  let unit = 10u64.pow(u32::from(HAY_DECIMALS));
+  let price = u64::from(PRICE) * unit;
+  let hay_treasury_ata = test.config.hay_treasury_ata;
+  assert_eq!(test.token_amount(&hay_treasury_ata).await, price);
+  assert_eq!(test.token_amount(&get_ata(&user, &test.config.hay_mint)).await, HAY_BALANCE * unit - price);
+  assert_eq!(test.token_amount(&get_ata(&user, &test.config.badge_bronze_mint)).await, 1);
+
+  let (ranking_v2_address, ranking_v2_bump) = find_ranking_v2_pda(&avatar_mint);
+  let ranking_v2: RankingV2 = test.anchor_account(&ranking_v2_address).await;
+  assert!(ranking_v2.bronze_minted);
+  assert_eq!(ranking_v2.ranking, None);
+  assert_eq!(ranking_v2.bump, ranking_v2_bump);
+
+  let rank_ladder: RankLadder = test.anchor_account(&find_rank_ladder_pda().0).await;
+  assert_eq!(rank_ladder.tiers[0].supply, 1);
+}
+
+#[tokio::test]
+async fn legacy_mint_badge_ignores_the_given_bumps() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let mut test = builder.start().await;
+  test.migrate_ranking(&avatar_mint).await;
+  test.init_rank_ladder(10).await;
+
+  let user = test.user.pubkey();
+  let mut ix = instructions::mint_badge_v2(&test.config, &user, &avatar_mint, FungibleAsset::BADGE_BRONZE);
+  ix.data = sla::instruction::MintBadgeV2 {
+    treasury_bump: 0,
+    ranking_v1_bump: 0,
+    ranking_v2_bump: 0,
+    badge_supply_counter_bump: 0,
+    asset_id: FungibleAsset::BADGE_BRONZE as u8,
+  }.data();
+  process(&mut test.context, ix, &[&test.user]).await.unwrap();
+
+  assert_eq!(test.token_amount(&get_ata(&user, &test.config.badge_bronze_mint)).await, 1);
+}
+
+#[tokio::test]
+async fn mint_badge_fails_when_already_minted() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let mut test = builder.start().await;
+  test.migrate_ranking(&avatar_mint).await;
+  test.init_rank_ladder(10).await;
+
+  let user = test.user.pubkey();
+  let ix = instructions::mint_badge_v2(&test.config, &user, &avatar_mint, FungibleAsset::BADGE_BRONZE);
+  process(&mut test.context, ix.clone(), &[&test.user]).await.unwrap();
+
+  let result = process(&mut test.context, ix, &[&test.user]).await;
+  assert_sla_error(result, SlaErrors::NotAllowedToMintBadge);
+}
+
+#[tokio::test]
+async fn mint_badge_fails_without_the_previous_rank() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let mut test = builder.start().await;
+  test.migrate_ranking(&avatar_mint).await;
+  test.init_rank_ladder(10).await;
+
+  let ix = instructions::mint_badge_v2(&test.config, &test.user.pubkey(), &avatar_mint, FungibleAsset::BADGE_SILVER);
+  let result = process(&mut test.context, ix, &[&test.user]).await;
+  assert_sla_error(result, SlaErrors::NotAllowedToMintBadge);
+}
+
+#[tokio::test]
+async fn mint_badge_fails_when_the_supply_is_exhausted() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let mut test = builder.start().await;
+  test.migrate_ranking(&avatar_mint).await;
+  test.init_rank_ladder(0).await;
+
+  let ix = instructions::mint_badge_v2(&test.config, &test.user.pubkey(), &avatar_mint, FungibleAsset::BADGE_BRONZE);
+  let result = process(&mut test.context, ix, &[&test.user]).await;
+  assert_sla_error(result, SlaErrors::AssetMaxSupplyReached);
+}
+
+#[tokio::test]
+async fn mint_badge_fails_for_an_id_card() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let mut test = builder.start().await;
+  test.migrate_ranking(&avatar_mint).await;
+  test.init_rank_ladder(10).await;
+
+  let ix = instructions::mint_badge_v2(&test.config, &test.user.pubkey(), &avatar_mint, FungibleAsset::ID_CARD);
+  let result = process(&mut test.context, ix, &[&test.user]).await;
+  assert_sla_error(result, SlaErrors::AssetIDIsNotBadge);
+}
+
+#[tokio::test]
+async fn mint_badge_fails_for_an_unknown_asset() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let mut test = builder.start().await;
+  test.migrate_ranking(&avatar_mint).await;
+  test.init_rank_ladder(10).await;
+
+  let mut ix = instructions::mint_badge_v2(&test.config, &test.user.pubkey(), &avatar_mint, FungibleAsset::BADGE_BRONZE);
+  ix.data = sla::instruction::MintBadgeV3 { asset_id: 42 }.data();
+  let result = process(&mut test.context, ix, &[&test.user]).await;
+  assert_sla_error(result, SlaErrors::UnknownAssetId);
+}
+
+#[tokio::test]
+async fn mint_badge_fails_with_the_mint_of_another_badge() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let mut test = builder.start().await;
+  test.migrate_ranking(&avatar_mint).await;
+  test.init_rank_ladder(10).await;
+
+  // Silver badge accounts for a bronze badge
+  let mut ix = instructions::mint_badge_v2(&test.config, &test.user.pubkey(), &avatar_mint, FungibleAsset::BADGE_SILVER);
+  ix.data = sla::instruction::MintBadgeV3 { asset_id: FungibleAsset::BADGE_BRONZE as u8 }.data();
+  let result = process(&mut test.context, ix, &[&test.user]).await;
+  assert_sla_error(result, SlaErrors::InvalidPubkey);
+}
+
+#[tokio::test]
+async fn merge_badge_upgrades_the_rank() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let mut test = builder.start().await;
+  test.migrate_ranking(&avatar_mint).await;
+  test.init_rank_ladder(10).await;
+
+  let user = test.user.pubkey();
+  let ix = instructions::mint_badge_v2(&test.config, &user, &avatar_mint, FungibleAsset::BADGE_BRONZE);
+  process(&mut test.context, ix, &[&test.user]).await.unwrap();
+
+  let ix = instructions::merge_badge_v2(&test.config, &user, &avatar_mint, FungibleAsset::BADGE_BRONZE, NEW_URI.to_string());
+  process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await.unwrap();
+
+  let ranking_v2: RankingV2 = test.anchor_account(&find_ranking_v2_pda(&avatar_mint).0).await;
+  assert_eq!(ranking_v2.ranking, Some(FungibleAsset::BADGE_BRONZE));
+  assert_eq!(test.token_amount(&get_ata(&user, &test.config.badge_bronze_mint)).await, 0);
+  assert_eq!(test.metadata(&avatar_mint).await.data.uri.trim_matches('\0'), NEW_URI);
+}
+
+#[tokio::test]
+async fn badges_record_their_provenance_in_ranking_v3() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let mut test = builder.start().await;
+  test.migrate_ranking(&avatar_mint).await;
+  test.init_rank_ladder(10).await;
+
+  let user = test.user.pubkey();
+  let ix = instructions::mint_badge_v2(&test.config, &user, &avatar_mint, FungibleAsset::BADGE_BRONZE);
+  process(&mut test.context, ix, &[&test.user]).await.unwrap();
+  let ix = instructions::merge_badge_v2(&test.config, &user, &avatar_mint, FungibleAsset::BADGE_BRONZE, NEW_URI.to_string());
+  process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await.unwrap();
+
+  let (ranking_v3_address, ranking_v3_bump) = find_ranking_v3_pda(&avatar_mint);
+  let ranking_v3: RankingV3 = test.anchor_account(&ranking_v3_address).await;
+  let bronze = ranking_v3.tier(0);
+  assert!(bronze.minted && bronze.merged);
+  assert_eq!((bronze.minted_by, bronze.merged_by), (user, user));
+  assert_eq!(bronze.badge_mint, test.config.badge_bronze_mint);
+  assert!(bronze.merged_slot >= bronze.minted_slot);
+  assert_eq!(ranking_v3.agent_since(), Some(bronze.merged_at));
+  assert!(!ranking_v3.tier(1).minted);
+  assert_eq!(ranking_v3.bump, ranking_v3_bump);
+}
+
+#[tokio::test]
+async fn ranking_v3_is_filled_from_ranking_v2() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let silver_mint = builder.config.badge_silver_mint;
+  builder.add_user_tokens(&silver_mint, 1);
+  let (ranking_v2_address, bump) = find_ranking_v2_pda(&avatar_mint);
+  let ranking_v2 = RankingV2 {
+    ranking: Some(FungibleAsset::BADGE_BRONZE),
+    bronze_minted: true,
+    silver_minted: true,
+    bump,
+    migrated: true,
+    ..RankingV2::default()
+  };
+  builder.add_anchor_account(ranking_v2_address, &ranking_v2);
+  let mut test = builder.start().await;
+  test.init_rank_ladder(10).await;
+
+  let user = test.user.pubkey();
+  let ix = instructions::merge_badge_v2(&test.config, &user, &avatar_mint, FungibleAsset::BADGE_SILVER, NEW_URI.to_string());
+  process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await.unwrap();
+
+  // Bronze was reached before V3: flagged without a date
+  let ranking_v3: RankingV3 = test.anchor_account(&find_ranking_v3_pda(&avatar_mint).0).await;
+  assert!(ranking_v3.tier(0).minted && ranking_v3.tier(0).merged);
+  assert_eq!(ranking_v3.tier(0).merged_at, 0);
+  assert_eq!(ranking_v3.agent_since(), None);
+  assert!(ranking_v3.tier(1).merged);
+  assert_eq!(ranking_v3.tier(1).badge_mint, silver_mint);
+}
+
+#[tokio::test]
+async fn merge_badge_fails_without_the_previous_rank() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let silver_mint = builder.config.badge_silver_mint;
+  builder.add_user_tokens(&silver_mint, 1);
+  let mut test = builder.start().await;
+  test.migrate_ranking(&avatar_mint).await;
+  test.init_rank_ladder(10).await;
+
+  let ix = instructions::merge_badge_v2(
+    &test.config,
+    &test.user.pubkey(),
+    &avatar_mint,
+    FungibleAsset::BADGE_SILVER,
+    NEW_URI.to_string(),
+  );
+  let result = process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await;
+  assert_sla_error(result, SlaErrors::NotAllowedToUpgradeRank);
+}
+
+#[tokio::test]
+async fn merge_badge_fails_with_the_mint_of_another_badge() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let silver_mint = builder.config.badge_silver_mint;
+  builder.add_user_tokens(&silver_mint, 1);
+  let mut test = builder.start().await;
+  test.migrate_ranking(&avatar_mint).await;
+  test.init_rank_ladder(10).await;
+
+  let mut ix = instructions::merge_badge_v2(
+    &test.config,
+    &test.user.pubkey(),
+    &avatar_mint,
+    FungibleAsset::BADGE_SILVER,
+    NEW_URI.to_string(),
+  );
+  ix.data = sla::instruction::MergeBadgeV3 {
+    asset_id: FungibleAsset::BADGE_BRONZE as u8,
+    metadata_uri: NEW_URI.to_string(),
+  }.data();
+  let result = process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await;
+  assert_sla_error(result, SlaErrors::InvalidPubkey);
+}
+
+#[tokio::test]
+async fn migrate_ranking_folds_the_v1_ranking_and_closes_it() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let ranking_v1 = Ranking { ranking: Some(FungibleAsset::BADGE_SILVER), minted_next: false };
+  builder.add_anchor_account(find_ranking_pda(&avatar_mint).0, &ranking_v1);
+  let mut test = builder.start().await;
+  test.init_rank_ladder(10).await;
+
+  test.migrate_ranking(&avatar_mint).await;
+
+  assert!(test.account(&find_ranking_pda(&avatar_mint).0).await.is_none());
+  let (ranking_v2_address, ranking_v2_bump) = find_ranking_v2_pda(&avatar_mint);
+  let ranking_v2: RankingV2 = test.anchor_account(&ranking_v2_address).await;
+  assert_eq!(ranking_v2.ranking, Some(FungibleAsset::BADGE_SILVER));
+  assert!(ranking_v2.bronze_minted && ranking_v2.silver_minted && !ranking_v2.gold_minted);
+  assert!(ranking_v2.migrated);
+  assert_eq!(ranking_v2.bump, ranking_v2_bump);
+
+  // Badges of the V1 ranking cannot be minted again
+  let ix = instructions::mint_badge_v2(&test.config, &test.user.pubkey(), &avatar_mint, FungibleAsset::BADGE_BRONZE);
+  let result = process(&mut test.context, ix, &[&test.user]).await;
+  assert_sla_error(result, SlaErrors::NotAllowedToMintBadge);
+}
+
+#[tokio::test]
+async fn migrate_ranking_runs_once() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let mut test = builder.start().await;
+  test.migrate_ranking(&avatar_mint).await;
+
+  let ix = instructions::migrate_ranking(&test.user.pubkey(), &avatar_mint);
+  let result = process(&mut test.context, ix, &[&test.user]).await;
+  assert_sla_error(result, SlaErrors::RankingAlreadyMigrated);
+}
+
+#[tokio::test]
+async fn badges_need_a_migrated_ranking() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let (ranking_v2_address, bump) = find_ranking_v2_pda(&avatar_mint);
+  builder.add_anchor_account(ranking_v2_address, &RankingV2 { bump, ..RankingV2::default() });
+  let mut test = builder.start().await;
+  test.init_rank_ladder(10).await;
+
+  let ix = instructions::mint_badge_v2(&test.config, &test.user.pubkey(), &avatar_mint, FungibleAsset::BADGE_BRONZE);
+  let result = process(&mut test.context, ix, &[&test.user]).await;
+  assert_sla_error(result, SlaErrors::RankingNotMigrated);
+}
+
+// Migrated Silver agent
+fn add_silver_ranking(builder: &mut SlaTestBuilder, avatar_mint: &Pubkey) {
+  let (ranking_v2_address, bump) = find_ranking_v2_pda(avatar_mint);
+  let ranking_v2 = RankingV2 {
+    ranking: Some(FungibleAsset::BADGE_SILVER),
+    bronze_minted: true,
+    silver_minted: true,
+    bump,
+    migrated: true,
+    ..RankingV2::default()
+  };
+  builder.add_anchor_account(ranking_v2_address, &ranking_v2);
+}
+
+#[tokio::test]
+async fn revoke_rank_lowers_the_rank_and_can_allow_reminting() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  add_silver_ranking(&mut builder, &avatar_mint);
+  let mut test = builder.start().await;
+  test.init_rank_ladder(10).await;
+
+  let admin = test.admin.pubkey();
+  let ix = instructions::revoke_rank(&admin, &avatar_mint, Some(0), 1, false);
+  process(&mut test.context, ix, &[&test.admin]).await.unwrap();
+
+  let ranking_v2: RankingV2 = test.anchor_account(&find_ranking_v2_pda(&avatar_mint).0).await;
+  assert_eq!(ranking_v2.ranking, Some(FungibleAsset::BADGE_BRONZE));
+  assert!(ranking_v2.silver_minted);
+  let ranking_v3: RankingV3 = test.anchor_account(&find_ranking_v3_pda(&avatar_mint).0).await;
+  assert!(ranking_v3.tier(0).merged && !ranking_v3.tier(1).merged && ranking_v3.tier(1).minted);
+
+  let ix = instructions::revoke_rank(&admin, &avatar_mint, None, 2, true);
+  process(&mut test.context, ix, &[&test.admin]).await.unwrap();
+
+  let ranking_v2: RankingV2 = test.anchor_account(&find_ranking_v2_pda(&avatar_mint).0).await;
+  assert_eq!(ranking_v2.ranking, None);
+  assert!(!ranking_v2.bronze_minted && !ranking_v2.silver_minted);
+
+  // The revoked Bronze badge can be minted again
+  let ix = instructions::mint_badge_v2(&test.config, &test.user.pubkey(), &avatar_mint, FungibleAsset::BADGE_BRONZE);
+  process(&mut test.context, ix, &[&test.user]).await.unwrap();
+}
+
+#[tokio::test]
+async fn revoke_rank_cannot_raise_the_rank() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  add_silver_ranking(&mut builder, &avatar_mint);
+  let mut test = builder.start().await;
+  test.init_rank_ladder(10).await;
+
+  let ix = instructions::revoke_rank(&test.admin.pubkey(), &avatar_mint, Some(2), 1, false);
+  let result = process(&mut test.context, ix, &[&test.admin]).await;
+  assert_sla_error(result, SlaErrors::InvalidRankRevocation);
+}
+
+#[tokio::test]
+async fn revoke_rank_needs_the_admin() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  add_silver_ranking(&mut builder, &avatar_mint);
+  let mut test = builder.start().await;
+  test.init_rank_ladder(10).await;
+
+  let ix = instructions::revoke_rank(&test.user.pubkey(), &avatar_mint, None, 1, false);
+  let result = process(&mut test.context, ix, &[&test.user]).await;
+  assert_sla_error(result, SlaErrors::InvalidPubkey);
+}
+
+#[tokio::test]
+async fn revoke_rank_works_on_unmigrated_rankings() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let (ranking_v2_address, bump) = find_ranking_v2_pda(&avatar_mint);
+  let ranking_v2 = RankingV2 {
+    ranking: Some(FungibleAsset::BADGE_SILVER),
+    bronze_minted: true,
+    silver_minted: true,
+    ..RankingV2::default()
+  };
+  builder.add_anchor_account(ranking_v2_address, &ranking_v2);
+  let mut test = builder.start().await;
+  test.init_rank_ladder(10).await;
+
+  let ix = instructions::revoke_rank(&test.admin.pubkey(), &avatar_mint, None, 1, false);
+  process(&mut test.context, ix, &[&test.admin]).await.unwrap();
+
+  let ranking_v2: RankingV2 = test.anchor_account(&ranking_v2_address).await;
+  assert_eq!(ranking_v2.ranking, None);
+  assert_eq!(ranking_v2.bump, bump);
+}
+
+#[tokio::test]
+async fn migrate_ranking_keeps_a_revoked_rank_revoked() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let ranking_v1 = Ranking { ranking: Some(FungibleAsset::BADGE_SILVER), minted_next: false };
+  builder.add_anchor_account(find_ranking_pda(&avatar_mint).0, &ranking_v1);
+  let (ranking_v2_address, _) = find_ranking_v2_pda(&avatar_mint);
+  builder.add_anchor_account(ranking_v2_address, &RankingV2::default());
+  let mut test = builder.start().await;
+  test.init_rank_ladder(10).await;
+
+  let ix = instructions::revoke_rank(&test.admin.pubkey(), &avatar_mint, None, 1, true);
+  process(&mut test.context, ix, &[&test.admin]).await.unwrap();
+
+  // The V1 ranking is not folded in again, its account is only closed
+  test.migrate_ranking(&avatar_mint).await;
+  assert!(test.account(&find_ranking_pda(&avatar_mint).0).await.is_none());
+  let ranking_v2: RankingV2 = test.anchor_account(&ranking_v2_address).await;
+  assert_eq!(ranking_v2.ranking, None);
+  assert!(!ranking_v2.bronze_minted && !ranking_v2.silver_minted);
+  assert!(ranking_v2.migrated);
+}
+
+#[tokio::test]
+async fn rank_ladder_takes_over_the_badge_supply() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let mut test = builder.start().await;
+  test.migrate_ranking(&avatar_mint).await;
+  test.init_rank_ladder(10).await;
+
+  let (rank_ladder_address, rank_ladder_bump) = find_rank_ladder_pda();
+  let rank_ladder: RankLadder = test.anchor_account(&rank_ladder_address).await;
+  assert_eq!(rank_ladder.tiers.len(), 5);
+  assert_eq!(rank_ladder.tiers[0].badge_mint, test.config.badge_bronze_mint);
+  assert_eq!((rank_ladder.tiers[0].supply, rank_ladder.tiers[0].max_supply), (0, 10));
+  assert_eq!(rank_ladder.tiers[1].prerequisite, Some(0));
+  assert_eq!(rank_ladder.bump, rank_ladder_bump);
+
+  // The legacy and tier instructions both mint from the ladder supply
+  let user = test.user.pubkey();
+  let ix = instructions::mint_badge_v2(&test.config, &user, &avatar_mint, FungibleAsset::BADGE_BRONZE);
+  process(&mut test.context, ix, &[&test.user]).await.unwrap();
+  let ix = instructions::merge_badge_v2(&test.config, &user, &avatar_mint, FungibleAsset::BADGE_BRONZE, NEW_URI.to_string());
+  process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await.unwrap();
+  let silver_mint = test.config.badge_silver_mint;
+  let ix = instructions::mint_badge_v4(&test.config, &user, &avatar_mint, 1, &silver_mint);
+  process(&mut test.context, ix, &[&test.user]).await.unwrap();
+
+  let ranking_v2: RankingV2 = test.anchor_account(&find_ranking_v2_pda(&avatar_mint).0).await;
+  assert_eq!(ranking_v2.ranking, Some(FungibleAsset::BADGE_BRONZE));
+  assert!(ranking_v2.silver_minted);
+  let ranking_v3: RankingV3 = test.anchor_account(&find_ranking_v3_pda(&avatar_mint).0).await;
+  assert!(ranking_v3.tier(0).merged && ranking_v3.tier(1).minted);
+  let rank_ladder: RankLadder = test.anchor_account(&rank_ladder_address).await;
+  assert_eq!((rank_ladder.tiers[0].supply, rank_ladder.tiers[1].supply), (1, 1));
+
+  // The frozen counter is left untouched
+  let counter: BadgeSupplyCounter = test.anchor_account(&find_badge_supply_counter_pda().0).await;
+  assert_eq!((counter.bronze, counter.max_bronze), (0, 0));
+}
+
+#[tokio::test]
+async fn badge_supply_instructions_write_to_the_rank_ladder() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let mut test = builder.start().await;
+  test.migrate_ranking(&avatar_mint).await;
+  test.init_rank_ladder(1).await;
+
+  let user = test.user.pubkey();
+  let ix = instructions::mint_badge_v2(&test.config, &user, &avatar_mint, FungibleAsset::BADGE_BRONZE);
+  process(&mut test.context, ix, &[&test.user]).await.unwrap();
+
+  let admin = test.admin.pubkey();
+  let ix = instructions::set_badge_supply(&admin, FungibleAsset::BADGE_SILVER, 5);
+  process(&mut test.context, ix, &[&test.admin]).await.unwrap();
+  let ix = instructions::reset_badge_supply(&admin, FungibleAsset::BADGE_BRONZE);
+  process(&mut test.context, ix, &[&test.admin]).await.unwrap();
+
+  let rank_ladder: RankLadder = test.anchor_account(&find_rank_ladder_pda().0).await;
+  assert_eq!((rank_ladder.tiers[0].supply, rank_ladder.tiers[0].max_supply), (0, 1));
+  assert_eq!((rank_ladder.tiers[1].supply, rank_ladder.tiers[1].max_supply), (0, 5));
+
+  let ix = instructions::set_badge_supply(&admin, FungibleAsset::ID_CARD, 5);
+  let result = process(&mut test.context, ix, &[&test.admin]).await;
+  assert_sla_error(result, SlaErrors::AssetIDIsNotBadge);
+}
+
+#[tokio::test]
+async fn rank_ladder_tiers_go_above_diamond() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let master_mint = builder.add_mint(&find_treasury_pda().0, 0);
+  let (ranking_v2_address, bump) = find_ranking_v2_pda(&avatar_mint);
+  let ranking_v2 = RankingV2 {
+    ranking: Some(FungibleAsset::BADGE_DIAMOND),
+    bronze_minted: true,
+    silver_minted: true,
+    gold_minted: true,
+    platinum_minted: true,
+    diamond_minted: true,
+    bump,
+    migrated: true,
+    ..RankingV2::default()
+  };
+  builder.add_anchor_account(ranking_v2_address, &ranking_v2);
+  let mut test = builder.start().await;
+  test.init_rank_ladder(10).await;
+
+  let admin = test.admin.pubkey();
+  let params = RankTierParams { badge_mint: master_mint, price: 2 * PRICE, max_supply: 1, prerequisite: Some(4) };
+  let ix = instructions::set_rank_tier(&admin, 5, params);
+  process(&mut test.context, ix, &[&test.admin]).await.unwrap();
+
+  let user = test.user.pubkey();
+  let ix = instructions::mint_badge_v4(&test.config, &user, &avatar_mint, 5, &master_mint);
+  process(&mut test.context, ix, &[&test.user]).await.unwrap();
+
+  let unit = 10u64.pow(u32::from(HAY_DECIMALS));
+  let hay_treasury_ata = test.config.hay_treasury_ata;
+  assert_eq!(test.token_amount(&hay_treasury_ata).await, u64::from(2 * PRICE) * unit);
+  assert_eq!(test.token_amount(&get_ata(&user, &master_mint)).await, 1);
+
+  let ix = instructions::merge_badge_v4(&test.config, &user, &avatar_mint, 5, &master_mint, NEW_URI.to_string());
+  process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await.unwrap();
+
+  // Diamond stays the legacy ranking of the agent
+  let ranking_v2: RankingV2 = test.anchor_account(&ranking_v2_address).await;
+  assert_eq!(ranking_v2.ranking, Some(FungibleAsset::BADGE_DIAMOND));
+  assert_eq!(ranking_v2.tier(), Some(5));
+  assert!(ranking_v2.tier_minted(5));
+  assert_eq!(test.token_amount(&get_ata(&user, &master_mint)).await, 0);
+
+  // Tiers above Diamond are recorded like the legacy ones
+  let ranking_v3: RankingV3 = test.anchor_account(&find_ranking_v3_pda(&avatar_mint).0).await;
+  assert!(ranking_v3.tier(4).merged);
+  let master = ranking_v3.tier(5);
+  assert!(master.minted && master.merged);
+  assert_eq!((master.minted_by, master.merged_by), (user, user));
+  assert_eq!(master.badge_mint, master_mint);
+
+  // And can be revoked like the legacy tiers
+  let ix = instructions::revoke_rank(&admin, &avatar_mint, Some(4), 1, true);
+  process(&mut test.context, ix, &[&test.admin]).await.unwrap();
+  let ranking_v2: RankingV2 = test.anchor_account(&ranking_v2_address).await;
+  assert_eq!((ranking_v2.tier(), ranking_v2.ladder_ranking), (Some(4), None));
+  assert!(!ranking_v2.tier_minted(5));
+}
+
+#[tokio::test]
+async fn rank_ladder_rejects_unknown_tiers() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let master_mint = builder.add_mint(&find_treasury_pda().0, 0);
+  let mut test = builder.start().await;
+  test.migrate_ranking(&avatar_mint).await;
+  test.init_rank_ladder(10).await;
+
+  let admin = test.admin.pubkey();
+
+  // Tiers are appended one at a time, above their prerequisite
+  let params = RankTierParams { badge_mint: master_mint, price: PRICE, max_supply: 1, prerequisite: Some(4) };
+  let ix = instructions::set_rank_tier(&admin, 6, params);
+  let result = process(&mut test.context, ix, &[&test.admin]).await;
+  assert_sla_error(result, SlaErrors::InvalidRankTier);
+  let ix = instructions::set_rank_tier(&admin, 5, RankTierParams { prerequisite: Some(5), ..params });
+  let result = process(&mut test.context, ix, &[&test.admin]).await;
+  assert_sla_error(result, SlaErrors::InvalidRankTier);
+
+  let ix = instructions::mint_badge_v4(&test.config, &test.user.pubkey(), &avatar_mint, 5, &master_mint);
+  let result = process(&mut test.context, ix, &[&test.user]).await;
+  assert_sla_error(result, SlaErrors::InvalidRankTier);
+}
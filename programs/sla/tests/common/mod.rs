@@ -0,0 +1,429 @@
+// Shared setup for the program-test suites: every test binary only uses part of it
+#![allow(dead_code)]
+
+use anchor_lang::{prelude::*, AccountDeserialize, AccountSerialize, AnchorSerialize};
+use anchor_lang::solana_program::{instruction::Instruction, program_option::COption, program_pack::Pack};
+use mpl_token_metadata::state::{
+  Collection, Creator, Data, Key, MasterEditionV2, Metadata, MAX_MASTER_EDITION_LEN, MAX_METADATA_LEN,
+};
+use solana_program_test::{processor, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+  account::Account,
+  instruction::InstructionError,
+  signature::{Keypair, Signer},
+  transaction::{Transaction, TransactionError},
+  transport::TransportError,
+};
+use std::str::FromStr;
+
+use sla::sla_accounts::{ConfigParams, GlobalConfig};
+use sla::sla_constants;
+use sla::sla_errors::SlaErrors;
+use sla_client::instructions;
+use sla_client::pda::{find_config_pda, find_master_edition_pda, find_metadata_pda, find_treasury_pda, get_ata};
+
+pub const HAY_DECIMALS: u8 = 9;
+pub const HAY_BALANCE: u64 = 10_000;
+pub const PRICE: u16 = 100;
+
+// Errors that cannot be triggered from these suites:
+// - never returned by the program: InvalidCreatorPubkey, UserDoesNotOwnMint, SignerIsNotHayTreasury,
+//   NextBadgeAlreadyMinted, MasterEditionNotRecognised, TokenToMintNotRecognised,
+//   PDAIsNotAnAvatar, PDAIsNotATrait, AvatarAlreadyInitialized, AvatarPDANotInitialized,
+//   MergeCheckFailedBecauseAvatarNotInitialized, ArweaveAccountMismatch, AvatarCannotMintTrait
+// - shadowed by the `associated_token` constraints: MintAndAtaMismatch, TokenPDAMismatch
+
+
+pub fn pubkey(key: &str) -> Pubkey {
+  Pubkey::from_str(key).unwrap()
+}
+
+// What the fake Metaplex metadata of an NFT looks like
+pub struct NftSpec {
+  pub collection: Option<Collection>,
+  pub creator: Option<Creator>,
+  pub amount: u64,
+}
+
+impl NftSpec {
+  pub fn new(collection: &str, creator: &Pubkey) -> Self {
+    NftSpec {
+      collection: Some(Collection { key: pubkey(collection), verified: true }),
+      creator: Some(Creator { address: *creator, verified: true, share: 100 }),
+      amount: 1,
+    }
+  }
+}
+
+// The Token Metadata processor ties all of its lifetimes together, while `processor!` borrows the
+// accounts for less than their data: the accounts (and program ID) are copied into leaked slices
+// living as long as the data, which only costs a few bytes per instruction in a test
+fn process_metadata_instruction<'a, 'info>(
+  program_id: &Pubkey,
+  accounts: &'a [AccountInfo<'info>],
+  input: &[u8],
+) -> ProgramResult {
+  let program_id: &'info Pubkey = Box::leak(Box::new(*program_id));
+  let accounts: &'info [AccountInfo<'info>] = Box::leak(accounts.to_vec().into_boxed_slice());
+  mpl_token_metadata::processor::process_instruction(program_id, accounts, input)
+}
+
+pub struct SlaTestBuilder {
+  pub program_test: ProgramTest,
+  pub config: GlobalConfig,
+  pub admin: Keypair,
+  pub combine_authority: Keypair,
+  pub avatar_creator: Keypair,
+  pub trait_creator: Keypair,
+  pub user: Keypair,
+}
+
+impl SlaTestBuilder {
+  pub fn new() -> Self {
+    Self::with_hay_decimals(HAY_DECIMALS)
+  }
+
+  pub fn with_hay_decimals(hay_decimals: u8) -> Self {
+    // SPL Token and Token Metadata run as native processors next to the SLA program
+    let mut program_test = ProgramTest::new("sla", sla::ID, processor!(sla::entry));
+    program_test.add_program(
+      "spl_token",
+      spl_token::ID,
+      processor!(spl_token::processor::Processor::process),
+    );
+    program_test.add_program(
+      "mpl_token_metadata",
+      mpl_token_metadata::ID,
+      processor!(process_metadata_instruction),
+    );
+
+    let mut builder = SlaTestBuilder {
+      program_test,
+      config: GlobalConfig::default(),
+      admin: Keypair::new(),
+      combine_authority: Keypair::new(),
+      avatar_creator: Keypair::new(),
+      trait_creator: Keypair::new(),
+      user: Keypair::new(),
+    };
+
+    for wallet in [
+      builder.admin.pubkey(),
+      builder.combine_authority.pubkey(),
+      builder.user.pubkey(),
+    ] {
+      builder.add_wallet(&wallet);
+    }
+
+    // $HAY + the fungible assets sold by the treasury
+    let treasury = find_treasury_pda().0;
+    let hay_mint = builder.add_mint(&Pubkey::new_unique(), hay_decimals);
+    let hay_treasury_ata = builder.add_token_account(&treasury, &hay_mint, 0);
+    let hay_balance = 10u64
+      .checked_pow(u32::from(hay_decimals))
+      .and_then(|unit| unit.checked_mul(HAY_BALANCE))
+      .unwrap_or(u64::MAX);
+    builder.add_token_account(&builder.user.pubkey(), &hay_mint, hay_balance);
+
+    let params = ConfigParams {
+      admin: builder.admin.pubkey(),
+      combine_authority: builder.combine_authority.pubkey(),
+      avatar_creator: builder.avatar_creator.pubkey(),
+      trait_creator: builder.trait_creator.pubkey(),
+      hay_mint,
+      hay_treasury_ata,
+      id_card_mint: builder.add_mint(&treasury, 0),
+      scanner_mint: builder.add_mint(&treasury, 0),
+      badge_bronze_mint: builder.add_mint(&treasury, 0),
+      badge_silver_mint: builder.add_mint(&treasury, 0),
+      badge_gold_mint: builder.add_mint(&treasury, 0),
+      badge_platinum_mint: builder.add_mint(&treasury, 0),
+      badge_diamond_mint: builder.add_mint(&treasury, 0),
+      price_id_card: PRICE,
+      price_scanner: PRICE,
+      price_badge_bronze: PRICE,
+      price_badge_silver: PRICE,
+      price_badge_gold: PRICE,
+      price_badge_platinum: PRICE,
+      price_badge_diamond: PRICE,
+      scanner_limit: 1,
+      scanner_cooldown: 0,
+    };
+    builder.config.set(params);
+    builder.config.store_bumps();
+
+    // Allocated like `initialize_config` does, leaving room for the base URI
+    let mut data = Vec::new();
+    builder.config.try_serialize(&mut data).unwrap();
+    data.resize(GlobalConfig::LEN, 0);
+    builder.add_account(find_config_pda().0, data, sla::ID);
+
+    builder
+  }
+
+  pub fn add_account(&mut self, address: Pubkey, data: Vec<u8>, owner: Pubkey) {
+    self.program_test.add_account(address, Account {
+      lamports: Rent::default().minimum_balance(data.len()),
+      data,
+      owner,
+      executable: false,
+      rent_epoch: 0,
+    });
+  }
+
+  pub fn add_anchor_account<T: AccountSerialize>(&mut self, address: Pubkey, account: &T) {
+    let mut data = Vec::new();
+    account.try_serialize(&mut data).unwrap();
+    self.add_account(address, data, sla::ID);
+  }
+
+  pub fn add_wallet(&mut self, wallet: &Pubkey) {
+    self.program_test.add_account(*wallet, Account {
+      lamports: 10_000_000_000,
+      ..Account::default()
+    });
+  }
+
+  pub fn add_mint(&mut self, authority: &Pubkey, decimals: u8) -> Pubkey {
+    let mint = Pubkey::new_unique();
+    let mut data = vec![0; spl_token::state::Mint::LEN];
+    spl_token::state::Mint {
+      mint_authority: COption::Some(*authority),
+      supply: 0,
+      decimals,
+      is_initialized: true,
+      freeze_authority: COption::None,
+    }.pack_into_slice(&mut data);
+    self.add_account(mint, data, spl_token::ID);
+    mint
+  }
+
+  pub fn add_token_account(&mut self, owner: &Pubkey, mint: &Pubkey, amount: u64) -> Pubkey {
+    let ata = get_ata(owner, mint);
+    let mut data = vec![0; spl_token::state::Account::LEN];
+    spl_token::state::Account {
+      mint: *mint,
+      owner: *owner,
+      amount,
+      delegate: COption::None,
+      state: spl_token::state::AccountState::Initialized,
+      is_native: COption::None,
+      delegated_amount: 0,
+      close_authority: COption::None,
+    }.pack_into_slice(&mut data);
+    self.add_account(ata, data, spl_token::ID);
+    ata
+  }
+
+  // Creates an NFT held by the user, with its metadata updatable by the combine authority
+  pub fn add_nft(&mut self, spec: NftSpec) -> Pubkey {
+    let mint = Pubkey::new_unique();
+    let mut data = vec![0; spl_token::state::Mint::LEN];
+    spl_token::state::Mint {
+      mint_authority: COption::None,
+      supply: 1,
+      decimals: 0,
+      is_initialized: true,
+      freeze_authority: COption::None,
+    }.pack_into_slice(&mut data);
+    self.add_account(mint, data, spl_token::ID);
+    self.add_token_account(&self.user.pubkey(), &mint, spec.amount);
+    self.add_metadata(&mint, spec.collection, spec.creator);
+
+    mint
+  }
+
+  fn add_metadata(&mut self, mint: &Pubkey, collection: Option<Collection>, creator: Option<Creator>) {
+    let metadata = Metadata {
+      key: Key::MetadataV1,
+      update_authority: self.combine_authority.pubkey(),
+      mint: *mint,
+      data: Data {
+        name: "Secret Llama Agent".to_string(),
+        symbol: "SLA".to_string(),
+        uri: "https://arweave.net/initial".to_string(),
+        seller_fee_basis_points: 500,
+        creators: creator.map(|creator| vec![creator]),
+      },
+      primary_sale_happened: true,
+      is_mutable: true,
+      edition_nonce: None,
+      token_standard: None,
+      collection,
+      uses: None,
+    };
+    let mut data = metadata.try_to_vec().unwrap();
+    data.resize(MAX_METADATA_LEN, 0);
+    self.add_account(find_metadata_pda(mint).0, data, mpl_token_metadata::ID);
+  }
+
+  // Collection NFT of a trait type (mint, metadata and master edition), managed by the combine
+  // authority so that `unmerge` and `swap_trait` can verify the traits they re-mint
+  pub fn add_trait_collection(&mut self, collection: &str) -> Pubkey {
+    let mint = pubkey(collection);
+    let master_edition = find_master_edition_pda(&mint).0;
+    let mut data = vec![0; spl_token::state::Mint::LEN];
+    spl_token::state::Mint {
+      mint_authority: COption::Some(master_edition),
+      supply: 1,
+      decimals: 0,
+      is_initialized: true,
+      freeze_authority: COption::Some(master_edition),
+    }.pack_into_slice(&mut data);
+    self.add_account(mint, data, spl_token::ID);
+    self.add_metadata(&mint, None, None);
+
+    let edition = MasterEditionV2 {
+      key: Key::MasterEditionV2,
+      supply: 0,
+      max_supply: Some(0),
+    };
+    let mut data = edition.try_to_vec().unwrap();
+    data.resize(MAX_MASTER_EDITION_LEN, 0);
+    self.add_account(master_edition, data, mpl_token_metadata::ID);
+
+    mint
+  }
+
+  pub fn add_avatar(&mut self) -> Pubkey {
+    let creator = self.avatar_creator.pubkey();
+    self.add_nft(NftSpec::new(sla_constants::LLAMA_COLLECTION, &creator))
+  }
+
+  // Agent of the collection that the user does not hold
+  pub fn add_foreign_avatar(&mut self) -> Pubkey {
+    let creator = self.avatar_creator.pubkey();
+    self.add_nft(NftSpec { amount: 0, ..NftSpec::new(sla_constants::LLAMA_COLLECTION, &creator) })
+  }
+
+  pub fn add_trait(&mut self, collection: &str) -> Pubkey {
+    let creator = self.trait_creator.pubkey();
+    self.add_nft(NftSpec::new(collection, &creator))
+  }
+
+  // Gives the user fungible assets (ID cards, scanners, badges) as if they had bought them
+  pub fn add_user_tokens(&mut self, mint: &Pubkey, amount: u64) -> Pubkey {
+    let user = self.user.pubkey();
+    self.add_token_account(&user, mint, amount)
+  }
+
+  pub async fn start(self) -> SlaTest {
+    SlaTest {
+      context: self.program_test.start_with_context().await,
+      config: self.config,
+      admin: self.admin,
+      combine_authority: self.combine_authority,
+      user: self.user,
+    }
+  }
+}
+
+pub struct SlaTest {
+  pub context: ProgramTestContext,
+  pub config: GlobalConfig,
+  pub admin: Keypair,
+  pub combine_authority: Keypair,
+  pub user: Keypair,
+}
+
+impl SlaTest {
+  pub async fn account(&mut self, address: &Pubkey) -> Option<Account> {
+    self.context.banks_client.get_account(*address).await.unwrap()
+  }
+
+  pub async fn anchor_account<T: AccountDeserialize>(&mut self, address: &Pubkey) -> T {
+    let account = self.account(address).await.expect("account not found");
+    T::try_deserialize(&mut account.data.as_slice()).unwrap()
+  }
+
+  pub async fn token_amount(&mut self, address: &Pubkey) -> u64 {
+    let account = self.account(address).await.expect("token account not found");
+    spl_token::state::Account::unpack(&account.data).unwrap().amount
+  }
+
+  pub async fn metadata(&mut self, mint: &Pubkey) -> Metadata {
+    let account = self.account(&find_metadata_pda(mint).0).await.expect("metadata not found");
+    Metadata::deserialize(&mut account.data.as_slice()).unwrap()
+  }
+
+  pub async fn migrate_ranking(&mut self, avatar_mint: &Pubkey) {
+    let ix = instructions::migrate_ranking(&self.user.pubkey(), avatar_mint);
+    process(&mut self.context, ix, &[&self.user]).await.unwrap();
+  }
+
+  pub async fn init_badge_supply_counter(&mut self, max_supply: u16) {
+    let ix = instructions::init_badge_supply_counter(
+      &self.admin.pubkey(),
+      max_supply,
+      max_supply,
+      max_supply,
+      max_supply,
+      max_supply,
+    );
+    process(&mut self.context, ix, &[&self.admin]).await.unwrap();
+  }
+
+  // The ladder takes its first tiers from the counter, so both are initialized
+  pub async fn init_rank_ladder(&mut self, max_supply: u16) {
+    self.init_badge_supply_counter(max_supply).await;
+    let ix = instructions::init_rank_ladder(&self.admin.pubkey());
+    process(&mut self.context, ix, &[&self.admin]).await.unwrap();
+  }
+}
+
+// Sends the instruction with the context payer paying the fees. Every transaction gets a new
+// blockhash so that sending the same instruction twice is not rejected as already processed
+pub async fn process(
+  context: &mut ProgramTestContext,
+  instruction: Instruction,
+  signers: &[&Keypair],
+) -> Result<(), TransportError> {
+  process_all(context, &[instruction], signers).await
+}
+
+pub async fn process_all(
+  context: &mut ProgramTestContext,
+  instructions: &[Instruction],
+  signers: &[&Keypair],
+) -> Result<(), TransportError> {
+  context.last_blockhash = context
+    .banks_client
+    .get_new_blockhash(&context.last_blockhash)
+    .await?
+    .0;
+
+  let mut all_signers = vec![&context.payer];
+  all_signers.extend_from_slice(signers);
+
+  let transaction = Transaction::new_signed_with_payer(
+    instructions,
+    Some(&context.payer.pubkey()),
+    &all_signers,
+    context.last_blockhash,
+  );
+  context.banks_client.process_transaction(transaction).await
+}
+
+// Points every occurrence of an account of the instruction to another one
+pub fn replace_account(instruction: &mut Instruction, old: &Pubkey, new: &Pubkey) {
+  for meta in instruction.accounts.iter_mut() {
+    if meta.pubkey == *old {
+      meta.pubkey = *new;
+    }
+  }
+}
+
+pub fn assert_sla_error(result: Result<(), TransportError>, expected: SlaErrors) {
+  let expected_code = match ProgramError::from(expected) {
+    ProgramError::Custom(code) => code,
+    error => panic!("{:?} is not a custom program error", error),
+  };
+
+  match result {
+    Err(TransportError::TransactionError(TransactionError::InstructionError(
+      _,
+      InstructionError::Custom(code),
+    ))) => assert_eq!(code, expected_code, "expected {:?}", expected),
+    other => panic!("expected {:?}, got {:?}", expected, other),
+  }
+}
@@ -0,0 +1,258 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::InstructionData;
+use solana_sdk::signature::Signer;
+
+use common::{assert_sla_error, process, replace_account, NftSpec, SlaTestBuilder, HAY_DECIMALS, PRICE};
+use sla::sla_accounts::{RankDiscounts, RankTierParams, RankingV2, ScannerRecord};
+use sla::sla_constants;
+use sla::sla_errors::SlaErrors;
+use sla::sla_fungible_token::FungibleAsset;
+use sla_client::instructions;
+use sla_client::pda::{find_metadata_pda, find_ranking_v2_pda, find_scanner_record_pda, get_ata};
+
+
+#[tokio::test]
+async fn mint_id_card_charges_hay() {
+  let mut test = SlaTestBuilder::new().start().await;
+
+  let user = test.user.pubkey();
+  let ix = instructions::mint_id_card(&test.config, &user);
+  process(&mut test.context, ix, &[&test.user]).await.unwrap();
+
+  let hay_treasury_ata = test.config.hay_treasury_ata;
+  let price = u64::from(PRICE) * 10u64.pow(u32::from(HAY_DECIMALS));
+  assert_eq!(test.token_amount(&hay_treasury_ata).await, price);
+  assert_eq!(test.token_amount(&get_ata(&user, &test.config.id_card_mint)).await, 1);
+}
+
+#[tokio::test]
+async fn legacy_mint_id_card_rejects_other_assets() {
+  let mut test = SlaTestBuilder::new().start().await;
+
+  let mut ix = instructions::mint_id_card(&test.config, &test.user.pubkey());
+  ix.data = sla::instruction::MintIdCard {
+    treasury_bump: 0,
+    asset_id: FungibleAsset::BADGE_BRONZE as u8,
+  }.data();
+  let result = process(&mut test.context, ix, &[&test.user]).await;
+  assert_sla_error(result, SlaErrors::AssetIsNotIdCard);
+}
+
+#[tokio::test]
+async fn mint_id_card_fails_with_another_mint() {
+  let mut test = SlaTestBuilder::new().start().await;
+
+  let user = test.user.pubkey();
+  let mut ix = instructions::mint_id_card(&test.config, &user);
+  let id_card_mint = test.config.id_card_mint;
+  let scanner_mint = test.config.scanner_mint;
+  replace_account(&mut ix, &get_ata(&user, &id_card_mint), &get_ata(&user, &scanner_mint));
+  replace_account(&mut ix, &id_card_mint, &scanner_mint);
+  let result = process(&mut test.context, ix, &[&test.user]).await;
+  assert_sla_error(result, SlaErrors::InvalidPubkey);
+}
+
+#[tokio::test]
+async fn mint_id_card_fails_when_the_price_overflows() {
+  // 10^20 base units per $HAY does not fit in a u64
+  let mut test = SlaTestBuilder::with_hay_decimals(20).start().await;
+
+  let ix = instructions::mint_id_card(&test.config, &test.user.pubkey());
+  let result = process(&mut test.context, ix, &[&test.user]).await;
+  assert_sla_error(result, SlaErrors::PriceOverflow);
+}
+
+#[tokio::test]
+async fn mint_scanner_charges_hay() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let mut test = builder.start().await;
+
+  let user = test.user.pubkey();
+  let ix = instructions::mint_scanner(&test.config, &user, &avatar_mint);
+  process(&mut test.context, ix, &[&test.user]).await.unwrap();
+
+  let hay_treasury_ata = test.config.hay_treasury_ata;
+  let price = u64::from(PRICE) * 10u64.pow(u32::from(HAY_DECIMALS));
+  assert_eq!(test.token_amount(&hay_treasury_ata).await, price);
+  assert_eq!(test.token_amount(&get_ata(&user, &test.config.scanner_mint)).await, 1);
+}
+
+#[tokio::test]
+async fn mint_scanner_fails_with_another_hay_treasury() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let mut test = builder.start().await;
+
+  let mut ix = instructions::mint_scanner(&test.config, &test.user.pubkey(), &avatar_mint);
+  let hay_treasury_ata = test.config.hay_treasury_ata;
+  replace_account(&mut ix, &hay_treasury_ata, &Pubkey::new_unique());
+  let result = process(&mut test.context, ix, &[&test.user]).await;
+  assert_sla_error(result, SlaErrors::InvalidPubkey);
+}
+
+#[tokio::test]
+async fn mint_scanner_is_limited_per_agent() {
+  let mut builder = SlaTestBuilder::new();
+  let first_agent = builder.add_avatar();
+  let second_agent = builder.add_avatar();
+  let mut test = builder.start().await;
+
+  let user = test.user.pubkey();
+  let ix = instructions::mint_scanner(&test.config, &user, &first_agent);
+  process(&mut test.context, ix.clone(), &[&test.user]).await.unwrap();
+
+  let result = process(&mut test.context, ix, &[&test.user]).await;
+  assert_sla_error(result, SlaErrors::ScannerAlreadyMinted);
+
+  // The limit is per agent
+  let ix = instructions::mint_scanner(&test.config, &user, &second_agent);
+  process(&mut test.context, ix, &[&test.user]).await.unwrap();
+
+  let scanner_record: ScannerRecord = test.anchor_account(&find_scanner_record_pda(&first_agent).0).await;
+  assert_eq!(scanner_record.minted, 1);
+}
+
+#[tokio::test]
+async fn mint_scanner_fails_without_the_agent() {
+  let mut builder = SlaTestBuilder::new();
+  let creator = builder.avatar_creator.pubkey();
+  let avatar_mint = builder.add_nft(NftSpec {
+    amount: 0,
+    ..NftSpec::new(sla_constants::LLAMA_COLLECTION, &creator)
+  });
+  let mut test = builder.start().await;
+
+  let ix = instructions::mint_scanner(&test.config, &test.user.pubkey(), &avatar_mint);
+  let result = process(&mut test.context, ix, &[&test.user]).await;
+  assert_sla_error(result, SlaErrors::AtaAmountIsNotOne);
+}
+
+#[tokio::test]
+async fn mint_scanner_fails_with_the_metadata_of_another_agent() {
+  let mut builder = SlaTestBuilder::new();
+  let user = builder.user.pubkey();
+  let throwaway_mint = builder.add_mint(&user, 0);
+  builder.add_token_account(&user, &throwaway_mint, 1);
+  let agent_mint = builder.add_foreign_avatar();
+  let mut test = builder.start().await;
+
+  // A mint the user holds must not borrow the metadata of a real agent to open a ScannerRecord
+  let mut ix = instructions::mint_scanner(&test.config, &user, &throwaway_mint);
+  replace_account(&mut ix, &find_metadata_pda(&throwaway_mint).0, &find_metadata_pda(&agent_mint).0);
+  let result = process(&mut test.context, ix, &[&test.user]).await;
+  assert_sla_error(result, SlaErrors::InvalidMetadataAccount);
+
+  assert!(test.account(&find_scanner_record_pda(&throwaway_mint).0).await.is_none());
+}
+
+fn add_ranking(builder: &mut SlaTestBuilder, avatar_mint: &Pubkey, ranking: FungibleAsset) {
+  let (ranking_v2_address, bump) = find_ranking_v2_pda(avatar_mint);
+  let ranking_v2 = RankingV2 { ranking: Some(ranking), bump, migrated: true, ..RankingV2::default() };
+  builder.add_anchor_account(ranking_v2_address, &ranking_v2);
+}
+
+#[tokio::test]
+async fn mint_id_card_v3_applies_the_rank_discount() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  add_ranking(&mut builder, &avatar_mint, FungibleAsset::BADGE_GOLD);
+  let mut test = builder.start().await;
+  test.init_rank_ladder(10).await;
+
+  // 10% off ID cards for Gold agents
+  let rank_discounts = RankDiscounts { id_card: 1_000, scanner: 0 };
+  let ix = instructions::set_rank_discounts(&test.admin.pubkey(), 2, rank_discounts);
+  process(&mut test.context, ix, &[&test.admin]).await.unwrap();
+
+  let user = test.user.pubkey();
+  let ix = instructions::mint_id_card_v3(&test.config, &user, &avatar_mint);
+  process(&mut test.context, ix, &[&test.user]).await.unwrap();
+
+  let hay_treasury_ata = test.config.hay_treasury_ata;
+  let price = u64::from(PRICE) * 9 / 10 * 10u64.pow(u32::from(HAY_DECIMALS));
+  assert_eq!(test.token_amount(&hay_treasury_ata).await, price);
+  assert_eq!(test.token_amount(&get_ata(&user, &test.config.id_card_mint)).await, 1);
+}
+
+#[tokio::test]
+async fn mint_scanner_applies_the_rank_discount() {
+  let mut builder = SlaTestBuilder::new();
+  let ranked_agent = builder.add_avatar();
+  let unranked_agent = builder.add_avatar();
+  add_ranking(&mut builder, &ranked_agent, FungibleAsset::BADGE_DIAMOND);
+  let mut test = builder.start().await;
+  test.init_rank_ladder(10).await;
+
+  let rank_discounts = RankDiscounts { id_card: 0, scanner: 5_000 };
+  let ix = instructions::set_rank_discounts(&test.admin.pubkey(), 4, rank_discounts);
+  process(&mut test.context, ix, &[&test.admin]).await.unwrap();
+
+  let user = test.user.pubkey();
+  let ix = instructions::mint_scanner(&test.config, &user, &ranked_agent);
+  process(&mut test.context, ix, &[&test.user]).await.unwrap();
+
+  let hay_treasury_ata = test.config.hay_treasury_ata;
+  let unit = 10u64.pow(u32::from(HAY_DECIMALS));
+  let discounted_price = u64::from(PRICE) / 2 * unit;
+  assert_eq!(test.token_amount(&hay_treasury_ata).await, discounted_price);
+
+  // Agents without a ranking pay the full price
+  let ix = instructions::mint_scanner(&test.config, &user, &unranked_agent);
+  process(&mut test.context, ix, &[&test.user]).await.unwrap();
+  assert_eq!(test.token_amount(&hay_treasury_ata).await, discounted_price + u64::from(PRICE) * unit);
+}
+
+#[tokio::test]
+async fn rank_discounts_apply_to_the_tiers_above_diamond() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let (ranking_v2_address, bump) = find_ranking_v2_pda(&avatar_mint);
+  let ranking_v2 = RankingV2 {
+    ranking: Some(FungibleAsset::BADGE_DIAMOND),
+    ladder_ranking: Some(5),
+    bump,
+    migrated: true,
+    ..RankingV2::default()
+  };
+  builder.add_anchor_account(ranking_v2_address, &ranking_v2);
+  let mut test = builder.start().await;
+  test.init_rank_ladder(10).await;
+
+  let admin = test.admin.pubkey();
+  let params = RankTierParams { badge_mint: Pubkey::new_unique(), price: PRICE, max_supply: 1, prerequisite: Some(4) };
+  let ix = instructions::set_rank_tier(&admin, 5, params);
+  process(&mut test.context, ix, &[&test.admin]).await.unwrap();
+
+  // The Diamond discount does not apply to agents above it
+  let ix = instructions::set_rank_discounts(&admin, 4, RankDiscounts { id_card: 1_000, scanner: 0 });
+  process(&mut test.context, ix, &[&test.admin]).await.unwrap();
+  let ix = instructions::set_rank_discounts(&admin, 5, RankDiscounts { id_card: 5_000, scanner: 0 });
+  process(&mut test.context, ix, &[&test.admin]).await.unwrap();
+
+  let ix = instructions::mint_id_card_v3(&test.config, &test.user.pubkey(), &avatar_mint);
+  process(&mut test.context, ix, &[&test.user]).await.unwrap();
+
+  let hay_treasury_ata = test.config.hay_treasury_ata;
+  let price = u64::from(PRICE) / 2 * 10u64.pow(u32::from(HAY_DECIMALS));
+  assert_eq!(test.token_amount(&hay_treasury_ata).await, price);
+}
+
+#[tokio::test]
+async fn set_rank_discounts_rejects_invalid_discounts() {
+  let mut test = SlaTestBuilder::new().start().await;
+  test.init_rank_ladder(10).await;
+
+  let admin = test.admin.pubkey();
+  let rank_discounts = RankDiscounts { id_card: 10_001, scanner: 0 };
+  let ix = instructions::set_rank_discounts(&admin, 0, rank_discounts);
+  let result = process(&mut test.context, ix, &[&test.admin]).await;
+  assert_sla_error(result, SlaErrors::InvalidDiscount);
+
+  // Discounts are set on existing tiers only
+  let ix = instructions::set_rank_discounts(&admin, 5, RankDiscounts { id_card: 1_000, scanner: 0 });
+  let result = process(&mut test.context, ix, &[&test.admin]).await;
+  assert_sla_error(result, SlaErrors::InvalidRankTier);
+}
@@ -0,0 +1,255 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use mpl_token_metadata::state::Collection;
+use solana_sdk::signature::{Keypair, Signer};
+
+use common::{assert_sla_error, process, pubkey, replace_account, NftSpec, SlaTestBuilder};
+use sla::sla_accounts::AvatarAccountV2;
+use sla::sla_constants;
+use sla::sla_errors::SlaErrors;
+use sla_client::instructions;
+use sla_client::pda::{find_avatar_v2_pda, find_metadata_pda, get_ata};
+
+const NEW_URI: &str = "https://arweave.net/merged";
+const BASE_URI: &str = "https://meta.sla.xyz/agents";
+
+
+#[tokio::test]
+async fn merge_burns_the_trait_and_records_it() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let trait_mint = builder.add_trait(sla_constants::HAT_COLLECTION);
+  let mut test = builder.start().await;
+
+  let ix = instructions::merge(&test.config, &test.user.pubkey(), &avatar_mint, &trait_mint, NEW_URI.to_string());
+  process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await.unwrap();
+
+  let (avatar_v2_address, _) = find_avatar_v2_pda(&avatar_mint);
+  let avatar_v2: AvatarAccountV2 = test.anchor_account(&avatar_v2_address).await;
+  let hat = avatar_v2.hat.expect("hat slot is empty");
+  assert_eq!(hat.mint, trait_mint);
+  assert!(avatar_v2.migrated);
+
+  let trait_token = get_ata(&test.user.pubkey(), &trait_mint);
+  assert_eq!(test.token_amount(&trait_token).await, 0);
+  assert_eq!(test.metadata(&avatar_mint).await.data.uri.trim_matches('\0'), NEW_URI);
+}
+
+#[tokio::test]
+async fn merge_fails_when_the_slot_is_taken() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let first_hat = builder.add_trait(sla_constants::HAT_COLLECTION);
+  let second_hat = builder.add_trait(sla_constants::HAT_COLLECTION);
+  let mut test = builder.start().await;
+
+  let user = test.user.pubkey();
+  let ix = instructions::merge(&test.config, &user, &avatar_mint, &first_hat, NEW_URI.to_string());
+  process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await.unwrap();
+
+  let ix = instructions::merge(&test.config, &user, &avatar_mint, &second_hat, NEW_URI.to_string());
+  let result = process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await;
+  assert_sla_error(result, SlaErrors::MergeCheckFailed);
+}
+
+#[tokio::test]
+async fn merge_fails_without_the_combine_authority() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let trait_mint = builder.add_trait(sla_constants::HAT_COLLECTION);
+  let mut test = builder.start().await;
+
+  let impostor = Keypair::new();
+  let mut config = test.config.clone();
+  config.combine_authority = impostor.pubkey();
+
+  let ix = instructions::merge(&config, &test.user.pubkey(), &avatar_mint, &trait_mint, NEW_URI.to_string());
+  let result = process(&mut test.context, ix, &[&test.user, &impostor]).await;
+  assert_sla_error(result, SlaErrors::InvalidPubkey);
+}
+
+#[tokio::test]
+async fn merge_fails_when_the_agent_is_not_held() {
+  let mut builder = SlaTestBuilder::new();
+  let creator = builder.avatar_creator.pubkey();
+  let avatar_mint = builder.add_nft(NftSpec {
+    amount: 0,
+    ..NftSpec::new(sla_constants::LLAMA_COLLECTION, &creator)
+  });
+  let trait_mint = builder.add_trait(sla_constants::HAT_COLLECTION);
+  let mut test = builder.start().await;
+
+  let ix = instructions::merge(&test.config, &test.user.pubkey(), &avatar_mint, &trait_mint, NEW_URI.to_string());
+  let result = process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await;
+  assert_sla_error(result, SlaErrors::AtaAmountIsNotOne);
+}
+
+#[tokio::test]
+async fn merge_fails_when_the_agent_collection_is_unverified() {
+  let mut builder = SlaTestBuilder::new();
+  let creator = builder.avatar_creator.pubkey();
+  let avatar_mint = builder.add_nft(NftSpec {
+    collection: Some(Collection { key: pubkey(sla_constants::LLAMA_COLLECTION), verified: false }),
+    ..NftSpec::new(sla_constants::LLAMA_COLLECTION, &creator)
+  });
+  let trait_mint = builder.add_trait(sla_constants::HAT_COLLECTION);
+  let mut test = builder.start().await;
+
+  let ix = instructions::merge(&test.config, &test.user.pubkey(), &avatar_mint, &trait_mint, NEW_URI.to_string());
+  let result = process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await;
+  assert_sla_error(result, SlaErrors::AvatarNotInCollection);
+}
+
+#[tokio::test]
+async fn merge_fails_when_the_agent_creator_is_not_configured() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_nft(NftSpec::new(sla_constants::LLAMA_COLLECTION, &Pubkey::new_unique()));
+  let trait_mint = builder.add_trait(sla_constants::HAT_COLLECTION);
+  let mut test = builder.start().await;
+
+  let ix = instructions::merge(&test.config, &test.user.pubkey(), &avatar_mint, &trait_mint, NEW_URI.to_string());
+  let result = process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await;
+  assert_sla_error(result, SlaErrors::CreatorInvalid);
+}
+
+#[tokio::test]
+async fn merge_fails_when_the_trait_has_no_collection() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let creator = builder.trait_creator.pubkey();
+  let trait_mint = builder.add_nft(NftSpec {
+    collection: None,
+    ..NftSpec::new(sla_constants::HAT_COLLECTION, &creator)
+  });
+  let mut test = builder.start().await;
+
+  let ix = instructions::merge(&test.config, &test.user.pubkey(), &avatar_mint, &trait_mint, NEW_URI.to_string());
+  let result = process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await;
+  assert_sla_error(result, SlaErrors::TraitNotInVerifiedCollection);
+}
+
+#[tokio::test]
+async fn merge_fails_when_the_trait_collection_is_unknown() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let creator = builder.trait_creator.pubkey();
+  let trait_mint = builder.add_nft(NftSpec {
+    collection: Some(Collection { key: Pubkey::new_unique(), verified: true }),
+    ..NftSpec::new(sla_constants::HAT_COLLECTION, &creator)
+  });
+  let mut test = builder.start().await;
+
+  let ix = instructions::merge(&test.config, &test.user.pubkey(), &avatar_mint, &trait_mint, NEW_URI.to_string());
+  let result = process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await;
+  assert_sla_error(result, SlaErrors::TraitCollectionUnknown);
+}
+
+#[tokio::test]
+async fn merge_fails_with_an_invalid_metadata_account() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let trait_mint = builder.add_trait(sla_constants::HAT_COLLECTION);
+  let mut test = builder.start().await;
+
+  // The avatar mint is not a metadata account
+  let mut ix = instructions::merge(&test.config, &test.user.pubkey(), &avatar_mint, &trait_mint, NEW_URI.to_string());
+  replace_account(&mut ix, &find_metadata_pda(&avatar_mint).0, &avatar_mint);
+  let result = process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await;
+  assert_sla_error(result, SlaErrors::InvalidMetadataAccount);
+}
+
+#[tokio::test]
+async fn merge_fails_with_the_metadata_of_another_agent() {
+  let mut builder = SlaTestBuilder::new();
+  let user = builder.user.pubkey();
+  let throwaway_mint = builder.add_mint(&user, 0);
+  builder.add_token_account(&user, &throwaway_mint, 1);
+  let victim_mint = builder.add_foreign_avatar();
+  let trait_mint = builder.add_trait(sla_constants::HAT_COLLECTION);
+  let mut test = builder.start().await;
+
+  // The held token is not the agent whose metadata would be updated
+  let mut ix = instructions::merge(&test.config, &user, &throwaway_mint, &trait_mint, NEW_URI.to_string());
+  replace_account(&mut ix, &find_metadata_pda(&throwaway_mint).0, &find_metadata_pda(&victim_mint).0);
+  let result = process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await;
+  assert_sla_error(result, SlaErrors::InvalidMetadataAccount);
+}
+
+// Sets the base URI and hands the agent over to the program
+async fn transfer_to_program(test: &mut common::SlaTest, avatar_mint: &Pubkey) {
+  let ix = instructions::set_metadata_base_uri(&test.admin.pubkey(), BASE_URI.to_string());
+  process(&mut test.context, ix, &[&test.admin]).await.unwrap();
+
+  let ix = instructions::transfer_update_authority(&test.config, avatar_mint);
+  process(&mut test.context, ix, &[&test.combine_authority]).await.unwrap();
+}
+
+#[tokio::test]
+async fn merge_v3_needs_no_co_signer() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let trait_mint = builder.add_trait(sla_constants::HAT_COLLECTION);
+  let mut test = builder.start().await;
+  transfer_to_program(&mut test, &avatar_mint).await;
+
+  let ix = instructions::merge_v3(&test.user.pubkey(), &avatar_mint, &trait_mint);
+  process(&mut test.context, ix, &[&test.user]).await.unwrap();
+
+  let avatar_v2: AvatarAccountV2 = test.anchor_account(&find_avatar_v2_pda(&avatar_mint).0).await;
+  let hat = avatar_v2.hat.expect("hat slot is empty");
+  assert_eq!(hat.mint, trait_mint);
+
+  let uri = format!("{}/{}/{}.json", BASE_URI, avatar_mint, hat.slot);
+  assert_eq!(test.metadata(&avatar_mint).await.data.uri.trim_matches('\0'), uri);
+}
+
+#[tokio::test]
+async fn merge_v3_fails_when_the_update_authority_is_not_transferred() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let trait_mint = builder.add_trait(sla_constants::HAT_COLLECTION);
+  let mut test = builder.start().await;
+
+  let ix = instructions::set_metadata_base_uri(&test.admin.pubkey(), BASE_URI.to_string());
+  process(&mut test.context, ix, &[&test.admin]).await.unwrap();
+
+  let ix = instructions::merge_v3(&test.user.pubkey(), &avatar_mint, &trait_mint);
+  let result = process(&mut test.context, ix, &[&test.user]).await;
+  assert_sla_error(result, SlaErrors::UpdateAuthorityNotTransferred);
+}
+
+#[tokio::test]
+async fn merge_v3_fails_without_a_base_uri() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let trait_mint = builder.add_trait(sla_constants::HAT_COLLECTION);
+  let mut test = builder.start().await;
+
+  let ix = instructions::transfer_update_authority(&test.config, &avatar_mint);
+  process(&mut test.context, ix, &[&test.combine_authority]).await.unwrap();
+
+  let ix = instructions::merge_v3(&test.user.pubkey(), &avatar_mint, &trait_mint);
+  let result = process(&mut test.context, ix, &[&test.user]).await;
+  assert_sla_error(result, SlaErrors::InvalidMetadataBaseUri);
+}
+
+#[tokio::test]
+async fn merge_v3_fails_with_the_metadata_of_another_agent() {
+  let mut builder = SlaTestBuilder::new();
+  let user = builder.user.pubkey();
+  let attacker_mint = builder.add_mint(&user, 0);
+  builder.add_token_account(&user, &attacker_mint, 1);
+  let victim_mint = builder.add_foreign_avatar();
+  let trait_mint = builder.add_trait(sla_constants::HAT_COLLECTION);
+  let mut test = builder.start().await;
+  transfer_to_program(&mut test, &victim_mint).await;
+
+  // The program signs for the victim metadata, so it must not be paired with another mint
+  let mut ix = instructions::merge_v3(&user, &attacker_mint, &trait_mint);
+  replace_account(&mut ix, &find_metadata_pda(&attacker_mint).0, &find_metadata_pda(&victim_mint).0);
+  let result = process(&mut test.context, ix, &[&test.user]).await;
+  assert_sla_error(result, SlaErrors::InvalidMetadataAccount);
+
+  assert_eq!(test.metadata(&victim_mint).await.data.uri.trim_matches('\0'), "https://arweave.net/initial");
+}
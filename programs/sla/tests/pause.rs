@@ -0,0 +1,62 @@
+mod common;
+
+use solana_sdk::signature::Signer;
+
+use common::{assert_sla_error, process, SlaTestBuilder};
+use sla::sla_accounts::{GlobalConfig, PauseFlags};
+use sla::sla_constants;
+use sla::sla_errors::SlaErrors;
+use sla_client::instructions;
+use sla_client::pda::find_config_pda;
+
+const NEW_URI: &str = "https://arweave.net/merged";
+
+
+#[tokio::test]
+async fn paused_family_rejects_its_instructions() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let trait_mint = builder.add_trait(sla_constants::HAT_COLLECTION);
+  let mut test = builder.start().await;
+
+  let paused = PauseFlags { merge: true, ..PauseFlags::default() };
+  let ix = instructions::set_paused(&test.admin.pubkey(), paused);
+  process(&mut test.context, ix, &[&test.admin]).await.unwrap();
+
+  let config: GlobalConfig = test.anchor_account(&find_config_pda().0).await;
+  assert!(config.paused.merge);
+
+  let ix = instructions::merge(&test.config, &test.user.pubkey(), &avatar_mint, &trait_mint, NEW_URI.to_string());
+  let result = process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await;
+  assert_sla_error(result, SlaErrors::ProgramPaused);
+
+  // Other families keep working
+  let ix = instructions::mint_scanner(&test.config, &test.user.pubkey(), &avatar_mint);
+  process(&mut test.context, ix, &[&test.user]).await.unwrap();
+}
+
+#[tokio::test]
+async fn unpaused_family_works_again() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let trait_mint = builder.add_trait(sla_constants::HAT_COLLECTION);
+  let mut test = builder.start().await;
+
+  let admin = test.admin.pubkey();
+  let paused = PauseFlags { merge: true, ..PauseFlags::default() };
+  process(&mut test.context, instructions::set_paused(&admin, paused), &[&test.admin]).await.unwrap();
+  process(&mut test.context, instructions::set_paused(&admin, PauseFlags::default()), &[&test.admin]).await.unwrap();
+
+  let ix = instructions::merge(&test.config, &test.user.pubkey(), &avatar_mint, &trait_mint, NEW_URI.to_string());
+  process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await.unwrap();
+}
+
+#[tokio::test]
+async fn only_the_admin_can_pause() {
+  let mut test = SlaTestBuilder::new().start().await;
+
+  let paused = PauseFlags { fungible: true, ..PauseFlags::default() };
+  let ix = instructions::set_paused(&test.user.pubkey(), paused);
+  let result = process(&mut test.context, ix, &[&test.user]).await;
+  assert_sla_error(result, SlaErrors::InvalidPubkey);
+}
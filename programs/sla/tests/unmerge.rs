@@ -0,0 +1,220 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+use common::{assert_sla_error, process, pubkey, NftSpec, SlaTestBuilder};
+use sla::sla_accounts::AvatarAccountV2;
+use sla::sla_constants;
+use sla::sla_errors::SlaErrors;
+use sla_client::instructions;
+use sla_client::pda::{find_avatar_v2_pda, get_ata};
+
+const HAT_ID: u8 = 4;
+
+const MERGED_URI: &str = "https://arweave.net/merged";
+const UNMERGED_URI: &str = "https://arweave.net/unmerged";
+const BASE_URI: &str = "https://meta.sla.xyz/agents";
+
+fn unmerge(
+  test: &common::SlaTest,
+  avatar_mint: &Pubkey,
+  returned_trait_mint: &Keypair,
+  trait_collection_mint: &Pubkey,
+  trait_id: u8,
+) -> anchor_lang::solana_program::instruction::Instruction {
+  instructions::unmerge(
+    &test.config,
+    &test.user.pubkey(),
+    avatar_mint,
+    &returned_trait_mint.pubkey(),
+    trait_collection_mint,
+    trait_id,
+    UNMERGED_URI.to_string(),
+  )
+}
+
+// `returned_trait_mint` is re-minted into the hat collection when a hat is swapped
+fn swap_trait(
+  test: &common::SlaTest,
+  avatar_mint: &Pubkey,
+  trait_mint: &Pubkey,
+  returned_trait_mint: &Keypair,
+) -> anchor_lang::solana_program::instruction::Instruction {
+  instructions::swap_trait(
+    &test.config,
+    &test.user.pubkey(),
+    avatar_mint,
+    trait_mint,
+    &returned_trait_mint.pubkey(),
+    &pubkey(sla_constants::HAT_COLLECTION),
+    UNMERGED_URI.to_string(),
+  )
+}
+
+async fn merge(test: &mut common::SlaTest, avatar_mint: &Pubkey, trait_mint: &Pubkey) {
+  let ix = instructions::merge(&test.config, &test.user.pubkey(), avatar_mint, trait_mint, MERGED_URI.to_string());
+  process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await.unwrap();
+}
+
+// The returned traits get their URI from the base URI of the config
+async fn set_base_uri(test: &mut common::SlaTest) {
+  let ix = instructions::set_metadata_base_uri(&test.admin.pubkey(), BASE_URI.to_string());
+  process(&mut test.context, ix, &[&test.admin]).await.unwrap();
+}
+
+// The returned trait is a verified NFT of the collection, held by the user, with the metadata derived by the program
+async fn assert_returned(test: &mut common::SlaTest, avatar_mint: &Pubkey, returned_trait_mint: &Pubkey) {
+  let returned_trait_token = get_ata(&test.user.pubkey(), returned_trait_mint);
+  assert_eq!(test.token_amount(&returned_trait_token).await, 1);
+
+  let metadata = test.metadata(returned_trait_mint).await;
+  assert_eq!(metadata.data.name.trim_matches('\0'), "Hat");
+  assert_eq!(metadata.data.symbol.trim_matches('\0'), sla_constants::TRAIT_SYMBOL);
+  let uri_prefix = format!("{}/{}/", BASE_URI, avatar_mint);
+  let uri = metadata.data.uri.trim_matches('\0');
+  assert!(uri.starts_with(&uri_prefix) && uri.ends_with(&format!("/{}.json", HAT_ID)), "unexpected URI {}", uri);
+  let collection = metadata.collection.expect("returned trait has no collection");
+  assert_eq!(collection.key, pubkey(sla_constants::HAT_COLLECTION));
+  assert!(collection.verified);
+}
+
+
+#[tokio::test]
+async fn unmerge_returns_the_trait_and_empties_the_slot() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let hat_mint = builder.add_trait(sla_constants::HAT_COLLECTION);
+  builder.add_trait_collection(sla_constants::HAT_COLLECTION);
+  let mut test = builder.start().await;
+  set_base_uri(&mut test).await;
+  merge(&mut test, &avatar_mint, &hat_mint).await;
+
+  let returned_trait_mint = Keypair::new();
+  let ix = unmerge(&test, &avatar_mint, &returned_trait_mint, &pubkey(sla_constants::HAT_COLLECTION), HAT_ID);
+  process(&mut test.context, ix, &[&test.user, &test.combine_authority, &returned_trait_mint]).await.unwrap();
+
+  let avatar_v2: AvatarAccountV2 = test.anchor_account(&find_avatar_v2_pda(&avatar_mint).0).await;
+  assert!(avatar_v2.hat.is_none());
+  assert_returned(&mut test, &avatar_mint, &returned_trait_mint.pubkey()).await;
+  assert_eq!(test.metadata(&avatar_mint).await.data.uri.trim_matches('\0'), UNMERGED_URI);
+}
+
+#[tokio::test]
+async fn swap_trait_keeps_the_slot_filled() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let first_hat = builder.add_trait(sla_constants::HAT_COLLECTION);
+  let second_hat = builder.add_trait(sla_constants::HAT_COLLECTION);
+  builder.add_trait_collection(sla_constants::HAT_COLLECTION);
+  let mut test = builder.start().await;
+  set_base_uri(&mut test).await;
+  merge(&mut test, &avatar_mint, &first_hat).await;
+
+  let returned_trait_mint = Keypair::new();
+  let ix = swap_trait(&test, &avatar_mint, &second_hat, &returned_trait_mint);
+  process(&mut test.context, ix, &[&test.user, &test.combine_authority, &returned_trait_mint]).await.unwrap();
+
+  let avatar_v2: AvatarAccountV2 = test.anchor_account(&find_avatar_v2_pda(&avatar_mint).0).await;
+  assert_eq!(avatar_v2.hat.expect("hat slot is empty").mint, second_hat);
+  assert_eq!(test.token_amount(&get_ata(&test.user.pubkey(), &second_hat)).await, 0);
+  assert_returned(&mut test, &avatar_mint, &returned_trait_mint.pubkey()).await;
+}
+
+#[tokio::test]
+async fn returned_traits_can_be_merged_again() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let hat_mint = builder.add_trait(sla_constants::HAT_COLLECTION);
+  builder.add_trait_collection(sla_constants::HAT_COLLECTION);
+  let mut test = builder.start().await;
+  set_base_uri(&mut test).await;
+  merge(&mut test, &avatar_mint, &hat_mint).await;
+
+  let returned_trait_mint = Keypair::new();
+  let ix = unmerge(&test, &avatar_mint, &returned_trait_mint, &pubkey(sla_constants::HAT_COLLECTION), HAT_ID);
+  process(&mut test.context, ix, &[&test.user, &test.combine_authority, &returned_trait_mint]).await.unwrap();
+
+  merge(&mut test, &avatar_mint, &returned_trait_mint.pubkey()).await;
+  let avatar_v2: AvatarAccountV2 = test.anchor_account(&find_avatar_v2_pda(&avatar_mint).0).await;
+  assert_eq!(avatar_v2.hat.expect("hat slot is empty").mint, returned_trait_mint.pubkey());
+}
+
+#[tokio::test]
+async fn unmerge_fails_without_a_base_uri() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let hat_mint = builder.add_trait(sla_constants::HAT_COLLECTION);
+  builder.add_trait_collection(sla_constants::HAT_COLLECTION);
+  let mut test = builder.start().await;
+  merge(&mut test, &avatar_mint, &hat_mint).await;
+
+  let returned_trait_mint = Keypair::new();
+  let ix = unmerge(&test, &avatar_mint, &returned_trait_mint, &pubkey(sla_constants::HAT_COLLECTION), HAT_ID);
+  let result = process(&mut test.context, ix, &[&test.user, &test.combine_authority, &returned_trait_mint]).await;
+  assert_sla_error(result, SlaErrors::InvalidMetadataBaseUri);
+}
+
+// Only the traits with the metadata derived by `unmerge` / `swap_trait` may come from the combine authority
+#[tokio::test]
+async fn traits_of_the_combine_authority_need_the_derived_metadata() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let creator = builder.combine_authority.pubkey();
+  let hat_mint = builder.add_nft(NftSpec::new(sla_constants::HAT_COLLECTION, &creator));
+  let mut test = builder.start().await;
+
+  let ix = instructions::merge(&test.config, &test.user.pubkey(), &avatar_mint, &hat_mint, MERGED_URI.to_string());
+  let result = process(&mut test.context, ix, &[&test.user, &test.combine_authority]).await;
+  assert_sla_error(result, SlaErrors::CreatorInvalid);
+}
+
+#[tokio::test]
+async fn swap_trait_fails_when_the_slot_is_empty() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let hat_mint = builder.add_trait(sla_constants::HAT_COLLECTION);
+  builder.add_trait_collection(sla_constants::HAT_COLLECTION);
+  let mut test = builder.start().await;
+
+  let returned_trait_mint = Keypair::new();
+  let ix = swap_trait(&test, &avatar_mint, &hat_mint, &returned_trait_mint);
+  let result = process(&mut test.context, ix, &[&test.user, &test.combine_authority, &returned_trait_mint]).await;
+  assert_sla_error(result, SlaErrors::UnmergeCheckFailed);
+}
+
+#[tokio::test]
+async fn unmerge_fails_when_the_slot_is_empty() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let mut test = builder.start().await;
+
+  let returned_trait_mint = Keypair::new();
+  let ix = unmerge(&test, &avatar_mint, &returned_trait_mint, &pubkey(sla_constants::HAT_COLLECTION), HAT_ID);
+  let result = process(&mut test.context, ix, &[&test.user, &test.combine_authority, &returned_trait_mint]).await;
+  assert_sla_error(result, SlaErrors::UnmergeCheckFailed);
+}
+
+#[tokio::test]
+async fn unmerge_fails_with_the_collection_of_another_trait() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let mut test = builder.start().await;
+
+  let returned_trait_mint = Keypair::new();
+  let ix = unmerge(&test, &avatar_mint, &returned_trait_mint, &pubkey(sla_constants::EYES_COLLECTION), HAT_ID);
+  let result = process(&mut test.context, ix, &[&test.user, &test.combine_authority, &returned_trait_mint]).await;
+  assert_sla_error(result, SlaErrors::TraitCollectionMismatch);
+}
+
+#[tokio::test]
+async fn unmerge_fails_with_an_invalid_trait_id() {
+  let mut builder = SlaTestBuilder::new();
+  let avatar_mint = builder.add_avatar();
+  let mut test = builder.start().await;
+
+  let returned_trait_mint = Keypair::new();
+  let ix = unmerge(&test, &avatar_mint, &returned_trait_mint, &pubkey(sla_constants::HAT_COLLECTION), 9);
+  let result = process(&mut test.context, ix, &[&test.user, &test.combine_authority, &returned_trait_mint]).await;
+  assert_sla_error(result, SlaErrors::TraitTypeInvalid);
+}